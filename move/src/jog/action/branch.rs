@@ -0,0 +1,45 @@
+use super::{
+    super::{expression::Expression, variable::Variable},
+    assign::Assign,
+    Action,
+};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug)]
+pub struct Branch<'a> {
+    condition: Expression<'a>,
+    then: Assign<'a>,
+    otherwise: Assign<'a>,
+}
+
+impl<'a> Branch<'a> {
+    pub fn new(condition: Expression<'a>, then: Assign<'a>, otherwise: Assign<'a>) -> Self {
+        Self {
+            condition,
+            then,
+            otherwise,
+        }
+    }
+}
+
+impl Action for Branch<'_> {
+    fn dependencies(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn definitions(&self) -> Vec<&Variable> {
+        let mut definitions = self.then.definitions();
+        definitions.extend(self.otherwise.definitions());
+        definitions
+    }
+}
+
+impl Display for Branch<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "if ({}) {{\n            {}\n        }} else {{\n            {}\n        }}",
+            self.condition, self.then, self.otherwise
+        )
+    }
+}
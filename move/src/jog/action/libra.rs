@@ -3,6 +3,7 @@ use super::{
         expression::{Address, Expression},
         variable::{Variable, EVENT},
     },
+    scale::UNIT,
     Action,
 };
 use std::fmt::{self, Display, Formatter};
@@ -77,10 +78,10 @@ impl Display for Withdraw {
                         &mut copy(contract_ref).coinstores,
                         *(&copy(context_ref).coinstore_index),
                     ),
-                    *(&mut copy(context_ref).scale)
+                    to_u64(*(&copy(context_ref).scale) / {}u128)
                 )
             );",
-            self.payee,
+            self.payee, UNIT,
         )
     }
 }
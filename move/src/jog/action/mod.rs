@@ -1,5 +1,6 @@
 pub mod assert;
 pub mod assign;
+pub mod branch;
 pub mod call;
 pub mod drop;
 pub mod flip;
@@ -2,16 +2,32 @@ use super::{
     super::{expression::Expression, variable::Variable},
     Action,
 };
+use sprint_parser::ast;
 use std::fmt::{self, Display, Formatter};
 
+/// The scale of a context is a Decimal, so that contracts scaled by fractions of a unit can be
+/// scaled up again without losing any precision.
+pub const UNIT: u64 = 10u64.pow(ast::DECIMAL_PLACES as u32);
+
 #[derive(Debug)]
 pub struct Scale<'a> {
     scalar: Expression<'a>,
+    decimal: bool,
 }
 
 impl<'a> Scale<'a> {
     pub fn new(scalar: Expression<'a>) -> Self {
-        Scale { scalar }
+        Scale {
+            scalar,
+            decimal: false,
+        }
+    }
+
+    pub fn decimal(scalar: Expression<'a>) -> Self {
+        Scale {
+            scalar,
+            decimal: true,
+        }
     }
 }
 
@@ -27,10 +43,16 @@ impl Action for Scale<'_> {
 
 impl Display for Scale<'_> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // The product is a u128, so it only overflows once the scale can't be paid out anyway.
         write!(
             f,
-            "*(&mut copy(context_ref).scale) = *(&copy(context_ref).scale) * {};",
+            "*(&mut copy(context_ref).scale) = *(&copy(context_ref).scale) * to_u128({})",
             self.scalar
-        )
+        )?;
+
+        match self.decimal {
+            true => write!(f, " / {}u128;", UNIT),
+            false => write!(f, ";"),
+        }
     }
 }
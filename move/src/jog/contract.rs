@@ -11,6 +11,8 @@ pub struct Contract<'a> {
     stack_offset: u64,
     methods: Vec<Method<'a>>,
     dependencies: Vec<&'a str>,
+    signed: bool,
+    decimal: bool,
 }
 
 impl<'a> Contract<'a> {
@@ -31,6 +33,16 @@ impl<'a> Contract<'a> {
         self.methods.push(method);
     }
 
+    /// Includes the methods for arithmetic and comparisons on Ints.
+    pub fn set_signed(&mut self) {
+        self.signed = true;
+    }
+
+    /// Includes the methods for multiplying, dividing and rounding Decimals.
+    pub fn set_decimal(&mut self) {
+        self.decimal = true;
+    }
+
     pub fn set_initial_state(&mut self, state: u64) {
         self.initial_state = state;
     }
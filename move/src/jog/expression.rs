@@ -122,10 +122,16 @@ impl<'a> TryFrom<Expression<'a>> for u64 {
 #[derive(Clone, Debug)]
 pub enum Binary {
     Add,
+    And,
+    Divide,
+    Equal,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    Multiply,
+    NotEqual,
+    Or,
     Subtract,
 }
 
@@ -133,10 +139,16 @@ impl Display for Binary {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Binary::Add => write!(f, "+"),
+            Binary::And => write!(f, "&&"),
+            Binary::Divide => write!(f, "/"),
+            Binary::Equal => write!(f, "=="),
             Binary::Greater => write!(f, ">"),
             Binary::GreaterEqual => write!(f, ">="),
             Binary::Less => write!(f, "<"),
             Binary::LessEqual => write!(f, "<="),
+            Binary::Multiply => write!(f, "*"),
+            Binary::NotEqual => write!(f, "!="),
+            Binary::Or => write!(f, "||"),
             Binary::Subtract => write!(f, "-"),
         }
     }
@@ -1,12 +1,17 @@
 use std::fmt::{self, Display, Formatter};
 
+const CONDITIONAL: &str = "conditional";
 const PREFIX: &str = "s";
 const SPAWN: &str = "spawn";
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum Identifier<'a> {
+    Branch(&'a str, String),
+    Conditional(u64),
     Raw(&'a str),
     Prefixed(&'a str),
+    /// One of the slots of a data value which is passed to a method.
+    Slot(&'a str, u64),
     Spawn(u64),
     Transition(&'a str),
 }
@@ -23,10 +28,24 @@ impl Identifier<'_> {
 impl Display for Identifier<'_> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
+            Self::Conditional(id) => write!(f, "{}_{}", CONDITIONAL, id),
             Self::Raw(name) => name.fmt(f),
-            Self::Prefixed(name) => write!(f, "{}_{}", PREFIX, name),
+            Self::Prefixed(name) => write!(f, "{}_{}", PREFIX, Name(name)),
+            Self::Slot(name, slot) => write!(f, "{}_{}_{}", PREFIX, Name(name), slot),
             Self::Spawn(id) => write!(f, "{}_{}", SPAWN, id),
-            Self::Transition(name) => write!(f, "transition_{}", name),
+            Self::Branch(name, path) => write!(f, "transition_{}_{}", Name(name), path),
+            Self::Transition(name) => write!(f, "transition_{}", Name(name)),
         }
     }
 }
+
+// Names of definitions in modules are qualified by the module, as in "Bonds.zero", but Move
+// identifiers can't contain dots. Sprint names never contain underscores, so those are used
+// instead.
+struct Name<'a>(&'a str);
+
+impl Display for Name<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.replace('.', "_").fmt(f)
+    }
+}
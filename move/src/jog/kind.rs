@@ -3,6 +3,7 @@ use std::fmt::{self, Display, Formatter};
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum Kind {
     Address,
+    Boolean,
     Coin,
     Context,
     Contract,
@@ -26,6 +27,7 @@ impl Display for Kind {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Self::Address => write!(f, "address"),
+            Self::Boolean => write!(f, "bool"),
             Self::Coin => write!(f, "LibraCoin.T"),
             Self::Context => write!(f, "Self.Context"),
             Self::Contract => write!(f, "Self.T"),
@@ -2,6 +2,7 @@ use super::{
     action::{
         assert::Assert,
         assign::Assign,
+        branch,
         libra::{DestroyHandle, Emit},
         update_state::UpdateState,
         Action,
@@ -26,10 +27,18 @@ pub struct Method<'a> {
     actions: Vec<Box<dyn Action + 'a>>,
     post_actions: Vec<Box<dyn Action + 'a>>,
     emit_actions: Vec<Box<dyn Action + 'a>>,
-    result: Option<Expression<'a>>,
+    result: Option<(Kind, Expression<'a>)>,
+    branch: Option<Branch<'a>>,
     acquires_resource: bool,
 }
 
+// Move has no conditional expressions, so a conditional result is returned from an if statement.
+#[derive(Debug)]
+struct Branch<'a> {
+    condition: Expression<'a>,
+    otherwise: Expression<'a>,
+}
+
 impl<'a> Method<'a> {
     fn new(public: bool, identifier: Identifier<'a>) -> Self {
         Self {
@@ -40,6 +49,7 @@ impl<'a> Method<'a> {
             post_actions: Default::default(),
             emit_actions: Default::default(),
             result: Default::default(),
+            branch: Default::default(),
             acquires_resource: false,
         }
     }
@@ -52,8 +62,34 @@ impl<'a> Method<'a> {
         Self::new(true, identifier)
     }
 
-    pub fn transition(name: &'a str, from: u64, to: Expression<'a>) -> Self {
-        let mut method = Self::public(Identifier::Transition(name));
+    pub fn transition(identifier: Identifier<'a>, from: u64, to: Expression<'a>) -> Self {
+        Self::transition_to(identifier, from, |method| {
+            method.add_action(Assign::new(TO_STATE.clone(), to.stack_expansion()))
+        })
+    }
+
+    pub fn conditional_transition(
+        identifier: Identifier<'a>,
+        from: u64,
+        condition: Expression<'a>,
+        then: Expression<'a>,
+        otherwise: Expression<'a>,
+    ) -> Self {
+        Self::transition_to(identifier, from, |method| {
+            method.add_action(branch::Branch::new(
+                condition,
+                Assign::new(TO_STATE.clone(), then.stack_expansion()),
+                Assign::new(TO_STATE.clone(), otherwise.stack_expansion()),
+            ))
+        })
+    }
+
+    fn transition_to(
+        identifier: Identifier<'a>,
+        from: u64,
+        assign_state: impl FnOnce(&mut Self),
+    ) -> Self {
+        let mut method = Self::public(identifier);
 
         method.add_action(Assign::new(
             CONTRACT_REF.clone(),
@@ -96,7 +132,7 @@ impl<'a> Method<'a> {
             ),
         ));
 
-        method.add_action(Assign::new(TO_STATE.clone(), to.stack_expansion()));
+        assign_state(&mut method);
 
         method.add_argument(OWNER.clone());
         method.add_argument(CONTEXT_INDEX.clone());
@@ -154,8 +190,22 @@ impl<'a> Method<'a> {
         self.post_actions.push(Box::new(post_action));
     }
 
-    pub fn set_result(&mut self, expression: Expression<'a>) {
-        self.result = Some(expression);
+    pub fn set_result(&mut self, kind: Kind, expression: Expression<'a>) {
+        self.result = Some((kind, expression));
+    }
+
+    pub fn set_conditional_result(
+        &mut self,
+        kind: Kind,
+        condition: Expression<'a>,
+        then: Expression<'a>,
+        otherwise: Expression<'a>,
+    ) {
+        self.result = Some((kind, then));
+        self.branch = Some(Branch {
+            condition,
+            otherwise,
+        });
     }
 
     pub fn set_acquires_resource(&mut self) {
@@ -165,7 +215,14 @@ impl<'a> Method<'a> {
     fn result(&self) -> String {
         self.result
             .as_ref()
-            .map(|e| format!(" {}", e))
+            .map(|(_, e)| format!(" {}", e))
+            .unwrap_or_default()
+    }
+
+    fn result_kind(&self) -> String {
+        self.result
+            .as_ref()
+            .map(|(k, _)| format!(": {}", k))
             .unwrap_or_default()
     }
 
@@ -4,8 +4,13 @@ mod visitor;
 
 use self::visitor::definitions;
 pub use jog::script;
-use sprint_parser::ast;
+use sprint_parser::ast::{self, json};
 
 pub fn generate(definitions: &[ast::Definition]) -> String {
-    definitions::visit(definitions).to_string()
+    definitions::visit(definitions)
+}
+
+/// Generates code for definitions which were exported as JSON, rather than parsed from Sprint.
+pub fn generate_json(json: &str) -> Result<String, json::Error> {
+    Ok(generate(&json::from_str(json)?))
 }
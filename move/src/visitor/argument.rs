@@ -1,48 +1,111 @@
-use super::{expression, Context};
+use super::{expression, kind, Context};
 use crate::jog::{
     action::push::Push,
     expression::{Binary, Expression},
     kind::Kind,
     variable::STACK,
 };
-use sprint_parser::ast;
+use sprint_parser::ast::{self, visit::Visit};
 
 pub(super) fn visit<'a>(
-    context: &mut Context<'a, '_>,
-    expression: &ast::Expression<'a>,
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
 ) -> Vec<Push<'a>> {
-    if !expression::results_in_state(expression.kind()) {
-        return vec![Push::new(
-            STACK.clone(),
-            expression::visit(context, expression),
-        )];
+    let mut arguments = Arguments {
+        context,
+        pushes: Vec::new(),
+    };
+    arguments.visit_expression(expression);
+    arguments.pushes
+}
+
+// The values which are pushed onto the stack for an argument.
+struct Arguments<'c, 'a> {
+    context: &'c mut Context<'a>,
+    pushes: Vec<Push<'a>>,
+}
+
+impl<'a> Visit<'a> for Arguments<'_, 'a> {
+    fn visit_expression(&mut self, expression: &'a ast::Expression) {
+        let context = &mut *self.context;
+
+        // Compound values are pushed slot by slot, so they are read directly like other values.
+        if kind::is_compound(expression.kind()) {
+            let slots = expression::visit_slots(context, expression).into_iter();
+            self.pushes
+                .extend(slots.map(|slot| Push::new(STACK.clone(), slot)));
+            return;
+        }
+
+        if !expression::results_in_state(expression.kind()) {
+            self.pushes.push(Push::new(
+                STACK.clone(),
+                kind::encode(
+                    &kind::visit(expression.kind()),
+                    expression::visit(context, expression),
+                ),
+            ));
+            return;
+        }
+
+        match &expression.expression {
+            ast::ExpressionType::Application(f, a) => {
+                let mut pushes = visit_application(context, f, a);
+                self.pushes.append(&mut pushes);
+            }
+            ast::ExpressionType::Variable(v) => self.pushes.push(Push::new(
+                STACK.clone(),
+                match context.definitions.get(&*v.name) {
+                    Some(definition) => {
+                        let definition = *definition;
+                        expression::visit_abstraction(context, &definition.expression)
+                    }
+                    None => expression::visit(context, expression),
+                },
+            )),
+            ast::ExpressionType::Conditional(_, _, _) => {
+                // The conditional gets a transition of its own, which can't share the name of the
+                // transition of the function which it is an argument in.
+                let branch = format!("argument_{}", context.conditionals.next().unwrap());
+                self.pushes.push(Push::new(
+                    STACK.clone(),
+                    expression::visit_branch(context, branch, expression),
+                ));
+            }
+            ast::ExpressionType::State(state) => self.visit_state(state),
+            _ => unreachable!(),
+        }
     }
 
-    match &expression.expression {
-        ast::ExpressionType::Application(f, a) => visit_application(context, &f, &a),
-        ast::ExpressionType::Variable(v) => vec![Push::new(
-            STACK.clone(),
-            match context.definitions.get(v.name) {
-                Some(definition) => {
-                    let definition = definition.clone();
-                    expression::visit_abstraction(context, &definition.expression)
-                }
-                None => expression::visit(context, expression),
-            },
-        )],
-        ast::ExpressionType::State(_) => unimplemented!("state arguments cannot be inlined"),
-        _ => unreachable!(),
+    fn visit_state(&mut self, _state: &'a ast::State) {
+        unimplemented!("state arguments cannot be inlined")
     }
 }
 
 fn visit_application<'a>(
-    context: &mut Context<'a, '_>,
-    abstraction: &ast::Expression<'a>,
-    argument: &ast::Expression<'a>,
+    context: &mut Context<'a>,
+    abstraction: &'a ast::Expression,
+    argument: &'a ast::Expression,
 ) -> Vec<Push<'a>> {
-    let mut abstraction = visit(context, abstraction);
-    let mut argument = visit(context, argument);
+    let abstraction = visit(context, abstraction);
+    let compound = kind::is_compound(argument.kind());
+    let argument = visit(context, argument);
 
+    // Each node of the chain has room for one slot, so compound values are applied one slot at
+    // a time, which leaves them on the stack in the same order as when they are passed directly.
+    match compound {
+        true => argument
+            .into_iter()
+            .fold(abstraction, |chain, slot| node(context, chain, vec![slot])),
+        false => node(context, abstraction, argument),
+    }
+}
+
+fn node<'a>(
+    context: &Context<'a>,
+    mut abstraction: Vec<Push<'a>>,
+    mut argument: Vec<Push<'a>>,
+) -> Vec<Push<'a>> {
     let mut pushes = Vec::new();
     let mut arguments = Vec::new();
 
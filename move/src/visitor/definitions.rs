@@ -1,29 +1,41 @@
-use super::{expression, Context, FunctionContext};
-use crate::jog::{
-    contract::Contract, identifier::Identifier, kind::Kind, method::Method, variable::Variable,
-};
+use super::{expression, kind, reduce, Context, FunctionContext};
+use crate::jog::{contract::Contract, identifier::Identifier, method::Method, variable::Variable};
 use sprint_parser::ast;
 use std::{convert::TryInto, rc::Rc};
 
 pub(super) const TERMINAL_ID: u64 = 0;
 
-pub fn visit<'a>(definitions: &[ast::Definition<'a>]) -> Contract<'a> {
-    let definitions = definitions.iter().map(Rc::new);
-    let mut context = Context::new(definitions.clone());
+pub fn visit(definitions: &[ast::Definition]) -> String {
+    let definitions = reduce::visit(definitions);
+    let contract = contract(&definitions);
+    contract.to_string()
+}
+
+// The contract borrows its names from the definitions, once any local bindings and lambdas in
+// them have been inlined.
+fn contract<'a>(definitions: &'a [ast::Definition]) -> Contract<'a> {
+    let mut context = Context::new(definitions);
 
     for definition in definitions {
         let mut expression = &definition.expression;
         let mut arguments = Vec::new();
+        let mut widths = Vec::new();
 
         while let ast::ExpressionType::Abstraction(a, e) = &expression.expression {
             expression = &e;
-            arguments.push(Variable::new(Identifier::Prefixed(a.name), Kind::Unsigned));
+            arguments.push(Variable::new(
+                Identifier::Prefixed(&a.name),
+                kind::visit(a.kind.clone()),
+            ));
+            widths.push(kind::width(a.kind.clone()));
         }
 
         if expression::results_in_state(expression.kind()) {
-            context
-                .function_context
-                .replace(FunctionContext::new(definition.variable.name, arguments));
+            context.function_context.replace(FunctionContext::new(
+                &definition.variable.name,
+                arguments,
+                widths,
+            ));
 
             let state = expression::visit(&mut context, expression)
                 .try_into()
@@ -36,14 +48,20 @@ pub fn visit<'a>(definitions: &[ast::Definition<'a>]) -> Contract<'a> {
                 context.functions.insert(key, Rc::new(Some(state).into()));
             }
 
-            if definition.variable.name == "main" {
+            if &*definition.variable.name == "main" {
                 context.contract.set_initial_state(state);
             }
         } else {
-            let mut method = Method::private(Identifier::Prefixed(definition.variable.name));
+            let mut method = Method::private(Identifier::Prefixed(&definition.variable.name));
+
+            // Arguments of methods are passed directly rather than through the stack.
+            context.function_context.take();
 
             method.set_arguments(arguments);
-            method.set_result(expression::visit(&mut context, expression));
+            method.set_result(
+                kind::visit(expression.kind()),
+                expression::visit(&mut context, expression),
+            );
             context.contract.add_method(method);
         }
     }
@@ -1,37 +1,157 @@
-use super::{argument, state, Context};
+use super::{argument, kind, state, Context};
 use crate::jog::{
-    action::push::Push,
+    action::{push::Push, scale::UNIT},
     call::Call,
     expression::{Address, Binary, Expression},
     identifier::Identifier,
     kind::Kind,
     method::Method,
-    variable::{STACK, STACK_LENGTH},
+    variable::{Variable, STACK, STACK_LENGTH},
 };
-use chrono::NaiveDate;
-use sprint_parser::ast;
+use sprint_parser::ast::{self, visit::Visit};
 use std::{cell::RefCell, rc::Rc};
 
 pub(super) fn visit<'a>(
-    context: &mut Context<'a, '_>,
-    expression: &ast::Expression<'a>,
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
 ) -> Expression<'a> {
     match &expression.expression {
         ast::ExpressionType::Abstraction(_, _) => unreachable!("use visit_abstraction instead"),
         ast::ExpressionType::Application(f, a) => visit_application(context, &f, &a),
-        ast::ExpressionType::Boolean(_) => unimplemented!(),
+        ast::ExpressionType::Boolean(b) => Expression::Expression(b.to_string().into()),
         ast::ExpressionType::Class(c) => visit_class(context, &c),
+        ast::ExpressionType::Conditional(c, t, o) => {
+            visit_conditional(context, expression, c, t, o)
+        }
+        ast::ExpressionType::Constructor(_, _) => unreachable!("use visit_slots instead"),
+        ast::ExpressionType::Conversion(k, e) => visit_conversion(context, k.clone(), e),
         ast::ExpressionType::Date(d) => visit_date(context, &d),
+        ast::ExpressionType::Decimal(d) => Expression::Unsigned(*d),
+        ast::ExpressionType::Difference(e, _) => visit(context, e),
+        ast::ExpressionType::Duration(d) => Expression::Unsigned(*d),
+        ast::ExpressionType::Field(_, _, _, k) | ast::ExpressionType::Projection(_, _, k) => {
+            let slot = visit_slots(context, expression).pop().unwrap();
+            kind::decode(&kind::visit(k.clone()), slot)
+        }
+        ast::ExpressionType::Int(i) => Expression::Unsigned(kind::int(*i)),
+        ast::ExpressionType::Is(c, e) => {
+            let data = kind::data(e.kind()).unwrap();
+            let (tag, _) = data.constructor(c).unwrap();
+            let slot = visit_slots(context, e).swap_remove(0);
+
+            Expression::Binary(
+                Binary::Equal,
+                slot.into(),
+                Expression::Unsigned(tag as u64).into(),
+            )
+        }
         ast::ExpressionType::Observable(o) => visit_observable(context, &o),
+        ast::ExpressionType::Record(_) => unreachable!("use visit_slots instead"),
+        ast::ExpressionType::Round(r, e) => visit_round(context, *r, e),
         ast::ExpressionType::State(s) => visit_state(context, &s),
+        ast::ExpressionType::Tuple(_) => unreachable!("use visit_slots instead"),
         ast::ExpressionType::Variable(v) => visit_variable(context, &v, Vec::new()),
         ast::ExpressionType::Word(w) => Expression::Expression(w.to_string().into()),
     }
 }
 
+/// Visits a value as the slots which it takes up on the stack. Only compound values, such as
+/// values of data types and tuples, take up more than one slot.
+pub(super) fn visit_slots<'a>(
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
+) -> Vec<Expression<'a>> {
+    let width = kind::width(expression.kind());
+
+    match &expression.expression {
+        ast::ExpressionType::Field(c, i, e, _) => {
+            let data = kind::data(e.kind()).unwrap();
+            let (_, fields) = data.constructor(c).unwrap();
+            let offset = 1 + fields[..*i].iter().cloned().map(kind::width).sum::<u64>();
+
+            let slots = visit_slots(context, e).into_iter();
+            slots.skip(offset as usize).take(width as usize).collect()
+        }
+        ast::ExpressionType::Projection(e, c, _) => {
+            let (position, _) = ast::Kind::component(e.kind(), c).unwrap();
+            let offset: u64 = match ast::Kind::simplify(e.kind()).as_ref() {
+                ast::Kind::Record(fields) => fields[..position]
+                    .iter()
+                    .map(|(_, k)| kind::width(k.clone()))
+                    .sum(),
+                ast::Kind::Tuple(kinds) => kinds[..position].iter().cloned().map(kind::width).sum(),
+                _ => unreachable!(),
+            };
+
+            let slots = visit_slots(context, e).into_iter();
+            slots.skip(offset as usize).take(width as usize).collect()
+        }
+        _ if !kind::is_compound(expression.kind()) => {
+            let kind = kind::visit(expression.kind());
+            vec![kind::encode(&kind, visit(context, expression))]
+        }
+        ast::ExpressionType::Application(_, _) | ast::ExpressionType::Constructor(_, _) => {
+            let mut fields = Vec::new();
+            let mut constructor = expression;
+
+            while let ast::ExpressionType::Application(f, a) = &constructor.expression {
+                constructor = f;
+                fields.push(a);
+            }
+
+            let name = match &constructor.expression {
+                ast::ExpressionType::Constructor(name, _) => name,
+                _ => unreachable!("data values are only returned by constructors"),
+            };
+            let data = kind::data(expression.kind()).unwrap();
+            let (tag, _) = data.constructor(name).unwrap();
+
+            let mut slots = vec![Expression::Unsigned(tag as u64)];
+
+            for field in fields.into_iter().rev() {
+                slots.append(&mut visit_slots(context, field));
+            }
+
+            slots.resize(width as usize, Expression::Unsigned(0));
+            slots
+        }
+        ast::ExpressionType::Conditional(c, t, o) => {
+            visit_conditional_slots(context, expression, c, t, o)
+        }
+        ast::ExpressionType::Record(fields) => {
+            let fields = fields.iter().map(|(_, e)| e);
+            fields.flat_map(|e| visit_slots(context, e)).collect()
+        }
+        ast::ExpressionType::Tuple(elements) => elements
+            .iter()
+            .flat_map(|e| visit_slots(context, e))
+            .collect(),
+        ast::ExpressionType::Variable(v) => visit_variable_slots(context, v),
+        _ => unreachable!(),
+    }
+}
+
+fn visit_variable_slots<'a>(
+    context: &Context<'a>,
+    variable: &'a ast::Variable,
+) -> Vec<Expression<'a>> {
+    let width = kind::width(variable.kind.clone());
+    let argument = context
+        .function_context
+        .as_ref()
+        .and_then(|function_context| function_context.find_argument(&variable.name));
+
+    (0..width)
+        .map(|slot| match argument {
+            Some(i) => stack_get(i + width - slot - 1),
+            None => Expression::Identifier(Identifier::Slot(&variable.name, slot)).copy(),
+        })
+        .collect()
+}
+
 pub(super) fn visit_abstraction<'a>(
-    context: &mut Context<'a, '_>,
-    mut expression: &ast::Expression<'a>,
+    context: &mut Context<'a>,
+    mut expression: &'a ast::Expression,
 ) -> Expression<'a> {
     while let ast::ExpressionType::Abstraction(_, e) = &expression.expression {
         expression = &e;
@@ -40,6 +160,8 @@ pub(super) fn visit_abstraction<'a>(
     // Sanity check -- something that doesn't result in a state shouldn't call this function.
     assert!(results_in_state(expression.kind()));
 
+    // Each definition has one state, which recursive uses refer to before it has been generated,
+    // so that recursion reuses the state rather than unfolding it.
     let key = expression as *const _;
 
     Expression::State(match context.functions.get(&key) {
@@ -53,9 +175,9 @@ pub(super) fn visit_abstraction<'a>(
 }
 
 fn visit_application<'a>(
-    context: &mut Context<'a, '_>,
-    mut abstraction: &ast::Expression<'a>,
-    argument: &ast::Expression<'a>,
+    context: &mut Context<'a>,
+    mut abstraction: &'a ast::Expression,
+    argument: &'a ast::Expression,
 ) -> Expression<'a> {
     let mut arguments = vec![argument];
 
@@ -70,46 +192,320 @@ fn visit_application<'a>(
     }
 }
 
-fn visit_class<'a>(context: &mut Context<'a, '_>, class: &ast::Class<'a>) -> Expression<'a> {
-    match class {
-        ast::Class::Comparable(c) => {
-            let (binary, left, right) = match c {
-                ast::Comparable::Greater(left, right) => (Binary::Greater, left, right),
-                ast::Comparable::Less(left, right) => (Binary::Less, left, right),
-                ast::Comparable::GreaterEqual(left, right) => (Binary::GreaterEqual, left, right),
-                ast::Comparable::LessEqual(left, right) => (Binary::LessEqual, left, right),
-            };
+fn visit_class<'a>(context: &mut Context<'a>, class: &'a ast::Class) -> Expression<'a> {
+    let (binary, left, right) = match class {
+        ast::Class::Comparable(c) => match c {
+            ast::Comparable::Greater(left, right) => (Binary::Greater, left, right),
+            ast::Comparable::Less(left, right) => (Binary::Less, left, right),
+            ast::Comparable::GreaterEqual(left, right) => (Binary::GreaterEqual, left, right),
+            ast::Comparable::LessEqual(left, right) => (Binary::LessEqual, left, right),
+        },
+        ast::Class::Equatable(e) => match e {
+            ast::Equatable::Equal(left, right) => (Binary::Equal, left, right),
+            ast::Equatable::NotEqual(left, right) => (Binary::NotEqual, left, right),
+        },
+        ast::Class::Negatable(ast::Negatable::Negate(e)) => {
+            let argument = visit(context, e);
+            return helper(context, ast::Kind::Int, "int_negate", vec![argument]);
+        }
+        ast::Class::Numerable(n) => match n {
+            ast::Numerable::Add(left, right) => (Binary::Add, left, right),
+            ast::Numerable::Subtract(left, right) => (Binary::Subtract, left, right),
+            ast::Numerable::Multiply(left, right) => (Binary::Multiply, left, right),
+            ast::Numerable::Divide(left, right) => (Binary::Divide, left, right),
+        },
+    };
 
-            Expression::Binary(
-                binary,
-                visit(context, left).into(),
-                visit(context, right).into(),
-            )
+    let kind = ast::Kind::simplify(left.kind());
+
+    // Compound values are equal when all of their slots are, since unused slots are always zero.
+    if kind::is_compound(kind.clone()) {
+        let (left, right) = (visit_slots(context, left), visit_slots(context, right));
+        let slots = left.into_iter().zip(right);
+        let combine = match binary {
+            Binary::Equal => Binary::And,
+            _ => Binary::Or,
+        };
+
+        let mut slots = slots.map(|(l, r)| Expression::Binary(binary.clone(), l.into(), r.into()));
+        let first = slots.next().unwrap();
+
+        return slots.fold(first, |all, slot| {
+            Expression::Binary(combine.clone(), all.into(), slot.into())
+        });
+    }
+
+    let (left, right) = (visit(context, left), visit(context, right));
+
+    match (kind.as_ref(), &binary) {
+        (ast::Kind::Int, _) => visit_signed(context, binary, left, right),
+        // Decimals are stored as a whole number of billionths, so only their products and
+        // quotients need to be rescaled.
+        (ast::Kind::Decimal, Binary::Multiply) => helper(
+            context,
+            ast::Kind::Decimal,
+            "decimal_multiply",
+            vec![left, right],
+        ),
+        (ast::Kind::Decimal, Binary::Divide) => helper(
+            context,
+            ast::Kind::Decimal,
+            "decimal_divide",
+            vec![left, right],
+        ),
+        _ => Expression::Binary(binary, left.into(), right.into()),
+    }
+}
+
+// Ints are stored as a sign above their magnitude, so most operations on them need a method of
+// their own. Each Int has a single representation, so equality works as it is.
+fn visit_signed<'a>(
+    context: &mut Context<'a>,
+    binary: Binary,
+    left: Expression<'a>,
+    right: Expression<'a>,
+) -> Expression<'a> {
+    let mut signed = |name, arguments| helper(context, ast::Kind::Int, name, arguments);
+    let not = |e| {
+        Expression::Binary(
+            Binary::Equal,
+            e,
+            Expression::Expression("false".into()).into(),
+        )
+    };
+
+    match binary {
+        Binary::Add => signed("int_add", vec![left, right]),
+        Binary::Subtract => signed("int_subtract", vec![left, right]),
+        Binary::Multiply => signed("int_multiply", vec![left, right]),
+        Binary::Divide => signed("int_divide", vec![left, right]),
+        Binary::Less => signed("int_less", vec![left, right]),
+        Binary::Greater => signed("int_less", vec![right, left]),
+        Binary::GreaterEqual => not(signed("int_less", vec![left, right]).into()),
+        Binary::LessEqual => not(signed("int_less", vec![right, left]).into()),
+        _ => Expression::Binary(binary, left.into(), right.into()),
+    }
+}
+
+fn visit_conversion<'a>(
+    context: &mut Context<'a>,
+    kind: Rc<ast::Kind>,
+    expression: &'a ast::Expression,
+) -> Expression<'a> {
+    let from = ast::Kind::simplify(expression.kind());
+    let to = ast::Kind::simplify(kind);
+    let argument = visit(context, expression);
+
+    match (from.as_ref(), to.as_ref()) {
+        (ast::Kind::Word, ast::Kind::Int) => {
+            helper(context, ast::Kind::Int, "word_to_int", vec![argument])
+        }
+        (ast::Kind::Int, ast::Kind::Word) => {
+            helper(context, ast::Kind::Int, "int_to_word", vec![argument])
+        }
+        (ast::Kind::Word, ast::Kind::Decimal) => Expression::Binary(
+            Binary::Multiply,
+            argument.into(),
+            Expression::Unsigned(UNIT).into(),
+        ),
+        (from, to) => unreachable!("there is no conversion from {} to {}", from, to),
+    }
+}
+
+fn visit_round<'a>(
+    context: &mut Context<'a>,
+    rounding: ast::Rounding,
+    expression: &'a ast::Expression,
+) -> Expression<'a> {
+    let name = match rounding {
+        ast::Rounding::Down => "decimal_floor",
+        ast::Rounding::Up => "decimal_ceiling",
+        ast::Rounding::Nearest => "decimal_round",
+    };
+
+    let argument = visit(context, expression);
+    helper(context, ast::Kind::Decimal, name, vec![argument])
+}
+
+// Calls one of the methods for Ints or Decimals, which are only included in contracts which use
+// them.
+fn helper<'a>(
+    context: &mut Context<'a>,
+    kind: ast::Kind,
+    name: &'static str,
+    arguments: Vec<Expression<'a>>,
+) -> Expression<'a> {
+    match kind {
+        ast::Kind::Decimal => context.contract.set_decimal(),
+        ast::Kind::Int => context.contract.set_signed(),
+        kind => unreachable!("there are no methods for {}", kind),
+    }
+
+    let mut call = Call::from(Identifier::Raw(name));
+
+    for argument in arguments {
+        call.add_argument(argument);
+    }
+
+    call.into()
+}
+
+fn visit_conditional<'a>(
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
+    condition: &'a ast::Expression,
+    then: &'a ast::Expression,
+    otherwise: &'a ast::Expression,
+) -> Expression<'a> {
+    if results_in_state(expression.kind()) {
+        // Contracts move to the state of either branch in a single transition.
+        let from = context.numbers.borrow_mut().next().unwrap();
+        let condition = visit(context, condition);
+        let then = visit_branch(context, "then", then);
+        let otherwise = visit_branch(context, "else", otherwise);
+
+        let function_context = context.function_context.as_ref().unwrap();
+        context.contract.add_method(Method::conditional_transition(
+            function_context.transition(),
+            from,
+            condition,
+            then,
+            otherwise,
+        ));
+
+        return from.into();
+    }
+
+    // Move only has conditional statements, so the branches are moved into a method of their
+    // own, which takes any arguments that are used in them.
+    let (parameters, arguments) = conditional_arguments(context, expression);
+
+    let identifier = Identifier::Conditional(context.conditionals.next().unwrap());
+    let mut method = Method::private(identifier.clone());
+    method.set_arguments(parameters);
+
+    let function_context = context.function_context.take();
+    method.set_conditional_result(
+        kind::visit(expression.kind()),
+        visit(context, condition),
+        visit(context, then),
+        visit(context, otherwise),
+    );
+    context.function_context = function_context;
+    context.contract.add_method(method);
+
+    let mut call = Call::from(identifier);
+
+    for argument in arguments {
+        call.add_argument(argument);
+    }
+
+    call.into()
+}
+
+// Compound values are chosen between slot by slot, with a conditional method for each slot.
+fn visit_conditional_slots<'a>(
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
+    condition: &'a ast::Expression,
+    then: &'a ast::Expression,
+    otherwise: &'a ast::Expression,
+) -> Vec<Expression<'a>> {
+    let (parameters, arguments) = conditional_arguments(context, expression);
+
+    let function_context = context.function_context.take();
+    let condition = visit(context, condition);
+    let then = visit_slots(context, then);
+    let otherwise = visit_slots(context, otherwise);
+    context.function_context = function_context;
+
+    then.into_iter()
+        .zip(otherwise)
+        .map(|(then, otherwise)| {
+            let identifier = Identifier::Conditional(context.conditionals.next().unwrap());
+            let mut method = Method::private(identifier.clone());
+
+            method.set_arguments(parameters.clone());
+            method.set_conditional_result(Kind::Unsigned, condition.clone(), then, otherwise);
+            context.contract.add_method(method);
+
+            let mut call = Call::from(identifier);
+
+            for argument in &arguments {
+                call.add_argument(argument.clone());
+            }
+
+            call.into()
+        })
+        .collect()
+}
+
+// The parameters of a conditional method, along with the arguments which it is called with.
+// Compound values are passed slot by slot.
+fn conditional_arguments<'a>(
+    context: &mut Context<'a>,
+    expression: &'a ast::Expression,
+) -> (Vec<Variable<'a>>, Vec<Expression<'a>>) {
+    let mut visitor = FreeVariables {
+        context,
+        bound: Vec::new(),
+        free: Vec::new(),
+    };
+    visitor.visit_expression(expression);
+    let free = visitor.free;
+
+    let mut parameters = Vec::new();
+    let mut arguments = Vec::new();
+
+    for argument in free {
+        if kind::is_compound(argument.kind.clone()) {
+            let width = kind::width(argument.kind.clone());
+
+            parameters.extend(
+                (0..width).map(|slot| {
+                    Variable::new(Identifier::Slot(&argument.name, slot), Kind::Unsigned)
+                }),
+            );
+            arguments.append(&mut visit_variable_slots(context, argument));
+        } else {
+            parameters.push(Variable::new(
+                Identifier::Prefixed(&argument.name),
+                kind::visit(argument.kind.clone()),
+            ));
+            arguments.push(visit_variable(context, argument, Vec::new()));
         }
-        ast::Class::Equatable(_) => unimplemented!(),
-        ast::Class::Negatable(_) => unimplemented!(),
-        ast::Class::Numerable(_) => unimplemented!(),
     }
+
+    (parameters, arguments)
+}
+
+pub(super) fn visit_branch<'a>(
+    context: &mut Context<'a>,
+    branch: impl Into<String>,
+    expression: &'a ast::Expression,
+) -> Expression<'a> {
+    let function_context = context.function_context.as_mut().unwrap();
+    function_context.branches.push(branch.into());
+
+    let result = visit(context, expression);
+    context.function_context.as_mut().unwrap().branches.pop();
+
+    result
 }
 
-fn visit_date<'a>(context: &mut Context<'a, '_>, date: &ast::Date) -> Expression<'a> {
+fn visit_date<'a>(context: &mut Context<'a>, date: &ast::Date) -> Expression<'a> {
     match date {
         ast::Date::Now => {
             context.contract.add_dependency("{{alice}}.Date");
             Expression::Observable("Date")
         }
-        ast::Date::Date(year, month, day, hour, minute, second) => {
-            let timestamp = NaiveDate::from_ymd(*year as i32, *month as u32, *day as u32)
-                .and_hms(*hour as u32, *minute as u32, *second as u32)
-                .timestamp() as u64;
-            timestamp.into()
-        }
+        ast::Date::Date(..) => date.timestamp().unwrap().into(),
     }
 }
 
 fn visit_observable<'a>(
-    context: &mut Context<'a, '_>,
-    observable: &ast::Observable<'a>,
+    context: &mut Context<'a>,
+    observable: &'a ast::Observable,
 ) -> Expression<'a> {
     match observable {
         ast::Observable::IsParty => {
@@ -122,60 +518,50 @@ fn visit_observable<'a>(
     }
 }
 
-fn visit_state<'a>(context: &mut Context<'a, '_>, state: &ast::state::State<'a>) -> Expression<'a> {
+fn visit_state<'a>(context: &mut Context<'a>, state: &'a ast::State) -> Expression<'a> {
     Expression::Unsigned(state::visit(context, state))
 }
 
 fn visit_variable<'a>(
-    context: &mut Context<'a, '_>,
-    variable: &ast::Variable<'a>,
-    arguments: Vec<&ast::Expression<'a>>,
+    context: &mut Context<'a>,
+    variable: &'a ast::Variable,
+    arguments: Vec<&'a ast::Expression>,
 ) -> Expression<'a> {
-    match context.definitions.get(variable.name) {
-        None => match context
-            .function_context
-            .as_ref()
-            .unwrap()
-            .find_argument(variable.name)
-        {
-            None => Expression::Identifier(Identifier::Prefixed(variable.name)).copy(),
-            Some(i) => Expression::Get(
-                Kind::Unsigned,
-                Expression::Identifier(STACK.identifier().clone())
-                    .copy()
-                    .freeze()
-                    .into(),
-                Expression::Binary(
-                    Binary::Subtract,
-                    Expression::Identifier(STACK_LENGTH.identifier().clone())
-                        .copy()
-                        .into(),
-                    Expression::Unsigned(i + 1).into(),
-                )
-                .into(),
-            ),
-        },
-        Some(definition) => {
+    // Arguments shadow top-level definitions of the same name.
+    let argument = context
+        .function_context
+        .as_ref()
+        .and_then(|function_context| function_context.find_argument(&variable.name));
+
+    match (argument, context.definitions.get(&*variable.name)) {
+        (Some(i), _) => kind::decode(&kind::visit(variable.kind.clone()), stack_get(i)),
+        (None, None) => Expression::Identifier(Identifier::Prefixed(&variable.name)).copy(),
+        (None, Some(definition)) => {
             let arguments = arguments.into_iter().rev();
-            let definition = definition.clone();
+            let definition = *definition;
 
             if results_in_state(variable.kind.clone()) {
                 let from = context.numbers.borrow_mut().next().unwrap();
                 let to = visit_abstraction(context, &definition.expression);
 
                 let function_context = context.function_context.as_ref().unwrap();
-                let mut method = Method::transition(function_context.name, from, to);
+                let mut method = Method::transition(function_context.transition(), from, to);
 
                 // We need to get context.numbers out before we visit arguments, since until
                 // stacks is consumed we can't borrow context immutably.
                 let numbers = context.numbers.clone();
-                let stacks = arguments.map(|argument| argument::visit(context, argument));
+                let stacks = arguments.map(|argument| {
+                    let compound = kind::is_compound(argument.kind());
+                    (compound, argument::visit(context, argument))
+                });
 
                 let mut arguments = Vec::new();
                 let mut position = 0;
 
-                for mut pushes in stacks {
+                for (compound, mut pushes) in stacks {
                     match pushes.len() {
+                        // Compound values are pushed slot by slot, rather than onto the heap.
+                        _ if compound => arguments.append(&mut pushes),
                         1 => arguments.push(pushes.pop().unwrap()),
                         _ => {
                             position += pushes.len() as u64;
@@ -211,7 +597,7 @@ fn visit_variable<'a>(
 
                 from.into()
             } else {
-                let mut call = Call::from(Identifier::Prefixed(variable.name));
+                let mut call = Call::from(Identifier::Prefixed(&variable.name));
 
                 for argument in arguments.map(|argument| visit(context, argument)) {
                     call.add_argument(argument);
@@ -223,6 +609,56 @@ fn visit_variable<'a>(
     }
 }
 
+// Reads the slot which is the given number of slots below the top of the stack.
+fn stack_get<'a>(offset: u64) -> Expression<'a> {
+    Expression::Get(
+        Kind::Unsigned,
+        Expression::Identifier(STACK.identifier().clone())
+            .copy()
+            .freeze()
+            .into(),
+        Expression::Binary(
+            Binary::Subtract,
+            Expression::Identifier(STACK_LENGTH.identifier().clone())
+                .copy()
+                .into(),
+            Expression::Unsigned(offset + 1).into(),
+        )
+        .into(),
+    )
+}
+
+// Collects the variables which are neither bound within the expression nor defined at the top
+// level, i.e. the arguments of the enclosing definition, which shadow top-level definitions.
+struct FreeVariables<'c, 'a> {
+    context: &'c Context<'a>,
+    bound: Vec<&'a str>,
+    free: Vec<&'a ast::Variable>,
+}
+
+impl<'a> Visit<'a> for FreeVariables<'_, 'a> {
+    fn visit_abstraction(&mut self, argument: &'a ast::Variable, body: &'a ast::Expression) {
+        self.bound.push(&argument.name);
+        self.visit_expression(body);
+        self.bound.pop();
+    }
+
+    fn visit_variable(&mut self, variable: &'a ast::Variable) {
+        let argument = self
+            .context
+            .function_context
+            .as_ref()
+            .and_then(|function_context| function_context.find_argument(&variable.name));
+
+        if !self.bound.contains(&&*variable.name)
+            && (argument.is_some() || !self.context.definitions.contains_key(&*variable.name))
+            && !self.free.contains(&variable)
+        {
+            self.free.push(variable)
+        }
+    }
+}
+
 pub(super) fn results_in_state(kind: Rc<ast::Kind>) -> bool {
     match kind.as_ref() {
         ast::Kind::Abstraction(_, s) => results_in_state(s.clone()),
@@ -0,0 +1,92 @@
+use crate::jog::{
+    call::Call,
+    expression::{Binary, Expression},
+    identifier::Identifier,
+    kind::Kind,
+};
+use sprint_parser::ast;
+use std::rc::Rc;
+
+pub(super) fn visit(kind: Rc<ast::Kind>) -> Kind {
+    match ast::Kind::simplify(kind).as_ref() {
+        ast::Kind::Boolean => Kind::Boolean,
+        ast::Kind::Observable(k) => visit(k.clone()),
+        // Words, ints, decimals, dates, durations and contracts are all represented by unsigned
+        // integers.
+        _ => Kind::Unsigned,
+    }
+}
+
+/// The number of slots which a value takes up on the stack. Values of data types start with the
+/// tag of their constructor, followed by its fields and padded with zeroes to the width of the
+/// largest constructor. Tuples and records are flattened into their components.
+pub(super) fn width(kind: Rc<ast::Kind>) -> u64 {
+    match ast::Kind::simplify(kind).as_ref() {
+        ast::Kind::Data(data) => {
+            let fields = data
+                .constructors
+                .iter()
+                .map(|(_, fields)| fields.iter().cloned().map(width).sum::<u64>());
+            1 + fields.max().unwrap_or(0)
+        }
+        ast::Kind::Record(fields) => fields.iter().map(|(_, k)| width(k.clone())).sum(),
+        ast::Kind::Tuple(kinds) => kinds.iter().cloned().map(width).sum(),
+        _ => 1,
+    }
+}
+
+/// Whether values of the kind are made up of other values, and so are visited slot by slot.
+pub(super) fn is_compound(kind: Rc<ast::Kind>) -> bool {
+    matches!(
+        ast::Kind::simplify(kind).as_ref(),
+        ast::Kind::Data(_) | ast::Kind::Record(_) | ast::Kind::Tuple(_)
+    )
+}
+
+pub(super) fn data(kind: Rc<ast::Kind>) -> Option<Rc<ast::Data>> {
+    match ast::Kind::simplify(kind).as_ref() {
+        ast::Kind::Data(data) => Some(data.clone()),
+        _ => None,
+    }
+}
+
+/// Whether a value of the kind is or takes a compound value.
+pub(super) fn has_compound(kind: Rc<ast::Kind>) -> bool {
+    match ast::Kind::simplify(kind.clone()).as_ref() {
+        ast::Kind::Abstraction(from, to) => has_compound(from.clone()) || has_compound(to.clone()),
+        ast::Kind::Observable(k) => has_compound(k.clone()),
+        _ => is_compound(kind),
+    }
+}
+
+/// The representation of an Int, with its sign in the top bit and its magnitude below it.
+pub(super) fn int(value: i64) -> u64 {
+    match value < 0 {
+        true => value.unsigned_abs() | 1 << 63,
+        false => value as u64,
+    }
+}
+
+/// Converts a value into its representation on the stack, which only holds unsigned integers.
+pub(super) fn encode<'a>(kind: &Kind, expression: Expression<'a>) -> Expression<'a> {
+    match kind {
+        Kind::Boolean => {
+            let mut call = Call::from(Identifier::Raw("bool_to_u64"));
+            call.add_argument(expression);
+            call.into()
+        }
+        _ => expression,
+    }
+}
+
+/// Converts a value read from the stack back into its representation in Move.
+pub(super) fn decode<'a>(kind: &Kind, expression: Expression<'a>) -> Expression<'a> {
+    match kind {
+        Kind::Boolean => Expression::Binary(
+            Binary::Equal,
+            expression.into(),
+            Expression::Unsigned(1).into(),
+        ),
+        _ => expression,
+    }
+}
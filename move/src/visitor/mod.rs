@@ -1,30 +1,37 @@
 pub mod argument;
 pub mod definitions;
 pub mod expression;
+pub mod kind;
+pub mod reduce;
 pub mod state;
 
 use self::definitions::TERMINAL_ID;
 use crate::{
-    jog::{contract::Contract, method::Method, variable::Variable},
+    jog::{contract::Contract, identifier::Identifier, method::Method, variable::Variable},
     numbers::Numbers,
 };
 use sprint_parser::ast;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-struct Context<'a, 'b> {
+struct Context<'a> {
     contract: Contract<'a>,
-    definitions: HashMap<&'a str, Rc<&'b ast::Definition<'a>>>,
+    definitions: HashMap<&'a str, &'a ast::Definition>,
     numbers: Rc<RefCell<Numbers>>,
+    conditionals: Numbers,
     function_context: Option<FunctionContext<'a>>,
-    functions: HashMap<*const ast::Expression<'a>, Rc<RefCell<Option<u64>>>>,
+    functions: HashMap<*const ast::Expression, Rc<RefCell<Option<u64>>>>,
 }
 
-impl<'a, 'b> Context<'a, 'b> {
-    pub fn new(definitions: impl Iterator<Item = Rc<&'b ast::Definition<'a>>>) -> Self {
+impl<'a> Context<'a> {
+    pub fn new(definitions: &'a [ast::Definition]) -> Self {
         Self {
             contract: Default::default(),
-            definitions: definitions.map(|d| (d.variable.name, d)).collect(),
+            definitions: definitions
+                .iter()
+                .map(|d| (d.variable.name.as_ref(), d))
+                .collect(),
             numbers: Rc::new(Numbers::from(TERMINAL_ID + 1).into()),
+            conditionals: Default::default(),
             function_context: Default::default(),
             functions: Default::default(),
         }
@@ -35,23 +42,39 @@ impl<'a, 'b> Context<'a, 'b> {
 struct FunctionContext<'a> {
     name: &'a str,
     arguments: Vec<Variable<'a>>,
+    widths: Vec<u64>,
     method: Option<Method<'a>>,
+    branches: Vec<String>,
 }
 
 impl<'a> FunctionContext<'a> {
-    fn new(name: &'a str, arguments: Vec<Variable<'a>>) -> Self {
+    fn new(name: &'a str, arguments: Vec<Variable<'a>>, widths: Vec<u64>) -> Self {
         Self {
             name,
             arguments,
+            widths,
             method: Default::default(),
+            branches: Default::default(),
         }
     }
 
+    // Transitions inside the branches of a conditional are named after the branches which lead
+    // to them, since a function can only be defined once.
+    fn transition(&self) -> Identifier<'a> {
+        match self.branches.is_empty() {
+            true => Identifier::Transition(self.name),
+            false => Identifier::Branch(self.name, self.branches.join("_")),
+        }
+    }
+
+    // The number of slots on the stack above the last slot of the argument, since the last
+    // argument is pushed last.
     fn find_argument(&self, name: &'a str) -> Option<u64> {
-        self.arguments
+        let position = self
+            .arguments
             .iter()
-            .rev()
-            .position(|v| v.identifier().has_name(name))
-            .map(|n| n as u64)
+            .rposition(|v| v.identifier().has_name(name))?;
+
+        Some(self.widths[position + 1..].iter().sum())
     }
 }
@@ -0,0 +1,286 @@
+use super::{expression::results_in_state, kind};
+use sprint_parser::ast::{
+    self,
+    fold::{self, Fold},
+    visit::Visit,
+    ExpressionType, Span,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+// Only top-level definitions become states and methods, so local bindings and lambdas are
+// inlined into the expressions which use them before any code is generated. Methods can't be
+// polymorphic either, so polymorphic definitions of values are inlined too. Methods only take
+// and return single values, so definitions which take or make compound values, such as tuples,
+// are inlined as well. Recursive definitions would be inlined forever, so they are kept as
+// methods even when they are polymorphic.
+pub(super) fn visit(definitions: &[ast::Definition]) -> Vec<ast::Definition> {
+    let (polymorphic, mut definitions): (Vec<_>, Vec<_>) =
+        definitions.iter().partition(|definition| {
+            let kind = definition.variable.kind.clone();
+            !results_in_state(kind.clone())
+                && (!ast::Kind::variables(kind.clone()).is_empty() || kind::has_compound(kind))
+        });
+
+    let recursive = recursive(&polymorphic);
+    let (recursive, polymorphic): (Vec<_>, Vec<_>) = polymorphic
+        .into_iter()
+        .partition(|definition| recursive.contains(&*definition.variable.name));
+    definitions.extend(recursive);
+
+    let scope = Scope {
+        bindings: None,
+        definitions: polymorphic
+            .into_iter()
+            .map(|d| (d.variable.name.as_ref(), &d.expression))
+            .collect::<HashMap<_, _>>()
+            .into(),
+    };
+
+    definitions
+        .into_iter()
+        .map(|definition| {
+            let expression = reduce(definition.expression.clone(), &scope);
+            ast::Definition::new(definition.variable.clone(), expression.into())
+        })
+        .collect()
+}
+
+// The definitions which use themselves, either directly or through the other definitions.
+fn recursive<'a>(definitions: &[&'a ast::Definition]) -> HashSet<&'a str> {
+    let uses: HashMap<_, _> = definitions
+        .iter()
+        .map(|definition| {
+            let mut uses = Uses::default();
+            uses.visit_expression(&definition.expression);
+            (definition.variable.name.as_ref(), uses.used)
+        })
+        .collect();
+
+    let reaches_itself = |name: &str| {
+        let mut stack = uses[name].clone();
+        let mut seen = HashSet::new();
+
+        while let Some(used) = stack.pop() {
+            if used == name {
+                return true;
+            }
+            if seen.insert(used) {
+                stack.extend(uses.get(used).into_iter().flatten());
+            }
+        }
+
+        false
+    };
+
+    uses.keys()
+        .copied()
+        .filter(|name| reaches_itself(name))
+        .collect()
+}
+
+// Collects the variables which aren't bound within the expression, which includes any top-level
+// definitions that it uses.
+#[derive(Default)]
+struct Uses<'a> {
+    bound: Vec<&'a str>,
+    used: Vec<&'a str>,
+}
+
+impl<'a> Visit<'a> for Uses<'a> {
+    fn visit_abstraction(&mut self, argument: &'a ast::Variable, body: &'a ast::Expression) {
+        self.bound.push(&argument.name);
+        self.visit_expression(body);
+        self.bound.pop();
+    }
+
+    fn visit_variable(&mut self, variable: &'a ast::Variable) {
+        if !self.bound.contains(&&*variable.name) {
+            self.used.push(&variable.name);
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Value<'a> {
+    Expression(ast::Expression),
+    Closure(ast::Variable, Rc<ast::Expression>, Scope<'a>),
+}
+
+impl From<Value<'_>> for ast::Expression {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Expression(expression) => expression,
+            // Lambdas which are never applied are kept, with their argument shadowing any
+            // binding of the same name.
+            Value::Closure(argument, body, scope) => {
+                let variable = ast::Expression::new(argument.clone().into(), argument.span);
+                let scope = scope.bind(argument.name.clone(), Value::Expression(variable));
+                let body = ast::Expression::from(reduce(unshared(body), &scope));
+                let span = body.span;
+
+                ast::Expression::new(ExpressionType::Abstraction(argument, body.into()), span)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Scope<'a> {
+    bindings: Option<Rc<(Rc<str>, Value<'a>, Scope<'a>)>>,
+    definitions: Rc<HashMap<&'a str, &'a ast::Expression>>,
+}
+
+impl<'a> Scope<'a> {
+    fn bind(&self, name: Rc<str>, value: Value<'a>) -> Self {
+        Self {
+            bindings: Some(Rc::new((name, value, self.clone()))),
+            definitions: self.definitions.clone(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value<'a>> {
+        let mut scope = self;
+
+        while let Some(binding) = &scope.bindings {
+            let (bound, value, outer) = binding.as_ref();
+
+            if &**bound == name {
+                return Some(value.clone());
+            }
+            scope = outer;
+        }
+
+        // Top-level definitions don't capture any local bindings.
+        self.definitions.get(name).map(|expression| {
+            let scope = Self {
+                bindings: None,
+                definitions: self.definitions.clone(),
+            };
+
+            reduce((*expression).clone(), &scope)
+        })
+    }
+}
+
+// Expressions which can't be applied or taken apart are rebuilt from their reduced children.
+impl Fold for Scope<'_> {
+    fn fold_expression(&mut self, expression: ast::Expression) -> ast::Expression {
+        reduce(expression, self).into()
+    }
+}
+
+fn reduce<'a>(expression: ast::Expression, scope: &Scope<'a>) -> Value<'a> {
+    let span = expression.span;
+
+    let reduced = match expression.expression {
+        ExpressionType::Abstraction(argument, body) => {
+            return Value::Closure(argument, Rc::new(*body), scope.clone())
+        }
+        ExpressionType::Application(f, argument) => match reduce(*f, scope) {
+            Value::Closure(parameter, body, closure_scope) => {
+                let argument = reduce(*argument, scope);
+                return reduce(
+                    unshared(body),
+                    &closure_scope.bind(parameter.name, argument),
+                );
+            }
+            Value::Expression(f) => ExpressionType::Application(
+                f.into(),
+                ast::Expression::from(reduce(*argument, scope)).into(),
+            ),
+        },
+        ExpressionType::Field(constructor, index, e, kind) => {
+            let e = ast::Expression::from(reduce(*e, scope));
+
+            // Fields of constructors which are applied in place are taken directly.
+            match constructed(&e) {
+                Some((name, fields)) if name == constructor => return fields[index].clone(),
+                _ => ExpressionType::Field(constructor, index, e.into(), kind),
+            }
+        }
+        ExpressionType::Is(constructor, e) => {
+            let e = ast::Expression::from(reduce(*e, scope));
+
+            match constructed(&e) {
+                Some((name, _)) => ExpressionType::Boolean(name == constructor),
+                None => ExpressionType::Is(constructor, e.into()),
+            }
+        }
+        ExpressionType::Projection(e, component, kind) => {
+            let e = ast::Expression::from(reduce(*e, scope));
+            return Value::Expression(project(e, component, kind, span));
+        }
+        ExpressionType::Variable(variable) => match scope.get(&variable.name) {
+            Some(value) => return value,
+            None => variable.into(),
+        },
+        expression => {
+            let expression = ast::Expression::new(expression, span);
+            return Value::Expression(fold::fold_expression(&mut scope.clone(), expression));
+        }
+    };
+
+    Value::Expression(ast::Expression::new(reduced, span))
+}
+
+// The body of a closure, which is only copied if the closure is applied more than once.
+fn unshared(body: Rc<ast::Expression>) -> ast::Expression {
+    Rc::try_unwrap(body).unwrap_or_else(|body| (*body).clone())
+}
+
+// Components of tuples and records which are written out are taken directly, and components of
+// conditionals are taken from each branch, so that only the components which are used are kept.
+fn project(
+    expression: ast::Expression,
+    component: ast::Component,
+    kind: Rc<ast::Kind>,
+    span: Option<Span>,
+) -> ast::Expression {
+    match (expression.expression, &component) {
+        (ExpressionType::Record(mut fields), ast::Component::Name(name)) => {
+            let position = fields.iter().position(|(field, _)| field == name);
+            fields.swap_remove(position.unwrap()).1
+        }
+        (ExpressionType::Tuple(mut elements), ast::Component::Index(index)) => {
+            elements.swap_remove(*index)
+        }
+        (ExpressionType::Conditional(condition, then, otherwise), _) => {
+            let then = project(*then, component.clone(), kind.clone(), span);
+            let otherwise = project(*otherwise, component, kind, span);
+
+            ast::Expression::new(
+                ExpressionType::Conditional(condition, then.into(), otherwise.into()),
+                expression.span,
+            )
+        }
+        (e, _) => ast::Expression::new(
+            ExpressionType::Projection(
+                ast::Expression::new(e, expression.span).into(),
+                component,
+                kind,
+            ),
+            span,
+        ),
+    }
+}
+
+// The constructor and fields of a data value, if it is made by applying a constructor.
+fn constructed<'a>(mut expression: &ast::Expression) -> Option<(Rc<str>, Vec<Value<'a>>)> {
+    let mut fields = Vec::new();
+
+    while let ExpressionType::Application(f, field) = &expression.expression {
+        fields.push(Value::Expression(field.as_ref().clone()));
+        expression = f;
+    }
+
+    match &expression.expression {
+        ExpressionType::Constructor(name, _) => {
+            fields.reverse();
+            Some((name.clone(), fields))
+        }
+        _ => None,
+    }
+}
@@ -12,67 +12,111 @@ use crate::{
     },
     numbers::Numbers,
 };
-use sprint_parser::ast;
+use sprint_parser::ast::{self, visit::Visit};
 use std::convert::TryFrom;
 
-pub(super) fn visit<'a>(context: &mut Context<'a, '_>, state: &ast::state::State<'a>) -> u64 {
+pub(super) fn visit<'a>(context: &mut Context<'a>, state: &'a ast::State) -> u64 {
     if state.is_terminal() {
         return TERMINAL_ID;
     }
 
     let from = context.numbers.borrow_mut().next().unwrap();
 
-    for transition in state.transitions() {
-        let to = expression::visit(context, transition.next());
+    let mut transitions = Transitions {
+        context,
+        from,
+        method: None,
+        spawn_numbers: Numbers::default(),
+    };
+    transitions.visit_state(state);
 
-        let function_context = context.function_context.as_ref().unwrap();
-        let mut method = Method::transition(function_context.name, from, to);
+    from
+}
 
-        for condition in transition.conditions() {
-            method.add_action(Assert::new(expression::visit(context, condition), 0));
-        }
+// Each transition becomes a method which asserts its conditions and then performs its effects.
+struct Transitions<'c, 'a> {
+    context: &'c mut Context<'a>,
+    from: u64,
+    method: Option<Method<'a>>,
+    spawn_numbers: Numbers,
+}
 
-        let mut spawn_numbers = Numbers::default();
+impl<'a> Transitions<'_, 'a> {
+    fn method(&mut self) -> &mut Method<'a> {
+        self.method.as_mut().unwrap()
+    }
+}
 
+impl<'a> Visit<'a> for Transitions<'_, 'a> {
+    fn visit_transition(&mut self, transition: &'a ast::Transition) {
+        let to = expression::visit(self.context, transition.next());
+
+        let function_context = self.context.function_context.as_ref().unwrap();
+        let method = Method::transition(function_context.transition(), self.from, to);
+
+        self.method = Some(method);
+        self.spawn_numbers = Numbers::default();
+
+        for condition in transition.conditions() {
+            self.visit_condition(condition);
+        }
         for effect in transition.effects() {
-            match effect {
-                ast::state::Effect::Flip => method.add_action(Flip::default()),
-                ast::state::Effect::Scale(scalar) => {
-                    method.add_action(Scale::new(expression::visit(context, scalar)))
-                }
-                ast::state::Effect::Spawn(child_state) => {
-                    let child = expression::visit(context, child_state);
-
-                    let spawned_context =
-                        Variable::new(Identifier::Raw("spawned_context"), Kind::Context);
-
-                    let expression = match u64::try_from(child) {
-                        Ok(_) => {
-                            let variable = Variable::new(
-                                Identifier::Spawn(spawn_numbers.next().unwrap()),
-                                Kind::Unsigned,
-                            );
-                            let expression = variable.identifier().clone().into();
-
-                            method.add_argument(variable);
-                            expression
-                        }
-                        Err(expression) => expression,
-                    };
-
-                    method.add_action(Spawn::new(spawned_context.clone(), expression));
-
-                    method.add_post_action(Push::new(
-                        CONTEXTS.clone(),
-                        Expression::Identifier(spawned_context.identifier().clone()).r#move(),
-                    ));
-                }
-                ast::state::Effect::Withdraw => method.add_action(Withdraw::new(Address::Party)),
-            }
+            self.visit_effect(effect);
         }
 
-        context.contract.add_method(method);
+        let method = self.method.take().unwrap();
+        self.context.contract.add_method(method);
     }
 
-    from
+    fn visit_condition(&mut self, condition: &'a ast::Expression) {
+        let condition = expression::visit(self.context, condition);
+        self.method().add_action(Assert::new(condition, 0));
+    }
+
+    fn visit_effect(&mut self, effect: &'a ast::Effect) {
+        match effect {
+            ast::Effect::Flip => self.method().add_action(Flip::default()),
+            ast::Effect::Scale(scalar) => {
+                let decimal = matches!(
+                    ast::Kind::simplify(scalar.kind()).as_ref(),
+                    ast::Kind::Observable(k) if *ast::Kind::simplify(k.clone()) == ast::Kind::Decimal
+                );
+                let scalar = expression::visit(self.context, scalar);
+
+                self.method().add_action(match decimal {
+                    true => Scale::decimal(scalar),
+                    false => Scale::new(scalar),
+                })
+            }
+            ast::Effect::Spawn(child_state) => {
+                let child = expression::visit(self.context, child_state);
+
+                let spawned_context =
+                    Variable::new(Identifier::Raw("spawned_context"), Kind::Context);
+
+                let expression = match u64::try_from(child) {
+                    Ok(_) => {
+                        let variable = Variable::new(
+                            Identifier::Spawn(self.spawn_numbers.next().unwrap()),
+                            Kind::Unsigned,
+                        );
+                        let expression = variable.identifier().clone().into();
+
+                        self.method().add_argument(variable);
+                        expression
+                    }
+                    Err(expression) => expression,
+                };
+
+                let method = self.method();
+                method.add_action(Spawn::new(spawned_context.clone(), expression));
+
+                method.add_post_action(Push::new(
+                    CONTEXTS.clone(),
+                    Expression::Identifier(spawned_context.identifier().clone()).r#move(),
+                ));
+            }
+            ast::Effect::Withdraw => self.method().add_action(Withdraw::new(Address::Party)),
+        }
+    }
 }
@@ -7,8 +7,8 @@ use functional_tests::{
     evaluator::eval,
     preprocessor::{build_transactions, split_input},
 };
-use sprint_move::generate;
-use sprint_parser::parser::contract;
+use sprint_move::{generate, generate_json};
+use sprint_parser::{ast::json, parser::contract};
 use std::{
     env,
     fmt::Display,
@@ -45,6 +45,113 @@ fn scale() {
     test(generate(&contract), &[], suite);
 }
 
+#[test]
+fn scale_json() {
+    let suite = Path::new("tests/tests/scale.mvir");
+    let contract = contract("main = scale (konst 5) one").unwrap();
+
+    test(
+        generate_json(&json::to_string(&contract)).unwrap(),
+        &[],
+        suite,
+    );
+}
+
+#[test]
+fn arithmetic() {
+    let suite = Path::new("tests/tests/arithmetic.mvir");
+    let contract =
+        contract("main = scale (konst ((7 - 1) * 2 / 3 + toWord (-(+3) + +3))) one").unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn int() {
+    let suite = Path::new("tests/tests/scale.mvir");
+    let contract = contract(
+        "main = scale (konst (toWord (if -3 < toInt 1 then toInt 10 / -2 * -1 else +0))) one",
+    )
+    .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn decimal() {
+    let suite = Path::new("tests/tests/scale.mvir");
+    let contract =
+        contract("main = scale (konst (0.25 * toDecimal 10)) (scale (konst (round 1.5)) one)")
+            .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn boolean() {
+    let suite = Path::new("tests/tests/boolean.mvir");
+    let contract = contract(
+        "flag :: Bool -> Bool\n\
+         flag b = b /= False\n\
+         option :: Bool -> Contract -> Contract\n\
+         option isCall c = scale (konst 5) c\n\
+         main = option (flag True) one",
+    )
+    .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn local() {
+    let suite = Path::new("tests/tests/scale.mvir");
+    let contract = contract(
+        "main = let c = scale (konst k) one in c\n\
+         \x20 where\n\
+         \x20   double = \\x -> x * 2\n\
+         \x20   k = double 2 + 1",
+    )
+    .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn conditional() {
+    let suite = Path::new("tests/tests/conditional.mvir");
+    let contract = contract(
+        "main = if 2 > 1 then scale (konst k) one else zero\n\
+         \x20 where\n\
+         \x20   k = case 3 of\n\
+         \x20     0 -> 0\n\
+         \x20     _ -> if True then 5 else 1",
+    )
+    .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn conditional_argument() {
+    let suite = Path::new("tests/tests/conditional_argument.mvir");
+    let contract = contract("main = and (if 2 > 1 then one else zero) one").unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
+#[test]
+fn compound_argument() {
+    let suite = Path::new("tests/tests/compound_argument.mvir");
+    let contract = contract(
+        "f :: (Word, Word) -> Contract\n\
+         f p = scale (konst (p.0 * 10 + p.1)) one\n\
+         main = and (f (0, 5)) one",
+    )
+    .unwrap();
+
+    test(generate(&contract), &[], suite);
+}
+
 #[test]
 fn before() {
     let date = Path::new(DATE);
@@ -1,42 +1,107 @@
-use super::{Expression, Kind};
+use super::{Expression, Kind, Owned, Source};
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
-pub enum Class<'a> {
-    Comparable(Comparable<'a>),
-    Equatable(Equatable<'a>),
-    Negatable(Negatable<'a>),
-    Numerable(Numerable<'a>),
+pub enum Class<S: Source = Owned> {
+    Comparable(Comparable<S>),
+    Equatable(Equatable<S>),
+    Negatable(Negatable<S>),
+    Numerable(Numerable<S>),
 }
 
 #[derive(Clone, Debug)]
-pub enum Comparable<'a> {
-    Greater(Box<Expression<'a>>, Box<Expression<'a>>),
-    Less(Box<Expression<'a>>, Box<Expression<'a>>),
-    GreaterEqual(Box<Expression<'a>>, Box<Expression<'a>>),
-    LessEqual(Box<Expression<'a>>, Box<Expression<'a>>),
+pub enum Comparable<S: Source = Owned> {
+    Greater(Box<Expression<S>>, Box<Expression<S>>),
+    Less(Box<Expression<S>>, Box<Expression<S>>),
+    GreaterEqual(Box<Expression<S>>, Box<Expression<S>>),
+    LessEqual(Box<Expression<S>>, Box<Expression<S>>),
 }
 
 #[derive(Clone, Debug)]
-pub enum Equatable<'a> {
-    Equal(Box<Expression<'a>>, Box<Expression<'a>>),
-    NotEqual(Box<Expression<'a>>, Box<Expression<'a>>),
+pub enum Equatable<S: Source = Owned> {
+    Equal(Box<Expression<S>>, Box<Expression<S>>),
+    NotEqual(Box<Expression<S>>, Box<Expression<S>>),
 }
 
 #[derive(Clone, Debug)]
-pub enum Negatable<'a> {
-    Negate(Box<Expression<'a>>),
+pub enum Negatable<S: Source = Owned> {
+    Negate(Box<Expression<S>>),
 }
 
 #[derive(Clone, Debug)]
-pub enum Numerable<'a> {
-    Add(Box<Expression<'a>>, Box<Expression<'a>>),
-    Subtract(Box<Expression<'a>>, Box<Expression<'a>>),
-    Multiply(Box<Expression<'a>>, Box<Expression<'a>>),
-    Divide(Box<Expression<'a>>, Box<Expression<'a>>),
+pub enum Numerable<S: Source = Owned> {
+    Add(Box<Expression<S>>, Box<Expression<S>>),
+    Subtract(Box<Expression<S>>, Box<Expression<S>>),
+    Multiply(Box<Expression<S>>, Box<Expression<S>>),
+    Divide(Box<Expression<S>>, Box<Expression<S>>),
 }
 
-impl Numerable<'_> {
+// The operands of a class, by shared or mutable reference, from the one list of variants.
+macro_rules! operands {
+    ($class:expr) => {
+        match $class {
+            Self::Comparable(Comparable::Greater(l, r))
+            | Self::Comparable(Comparable::Less(l, r))
+            | Self::Comparable(Comparable::GreaterEqual(l, r))
+            | Self::Comparable(Comparable::LessEqual(l, r))
+            | Self::Equatable(Equatable::Equal(l, r))
+            | Self::Equatable(Equatable::NotEqual(l, r))
+            | Self::Numerable(Numerable::Add(l, r))
+            | Self::Numerable(Numerable::Subtract(l, r))
+            | Self::Numerable(Numerable::Multiply(l, r))
+            | Self::Numerable(Numerable::Divide(l, r)) => vec![l, r],
+            Self::Negatable(Negatable::Negate(e)) => vec![e],
+        }
+    };
+}
+
+impl<S: Source> Class<S> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Comparable(_) => "Comparable",
+            Self::Equatable(_) => "Equatable",
+            Self::Negatable(_) => "Negatable",
+            Self::Numerable(_) => "Numerable",
+        }
+    }
+
+    /// Checks whether operands of the given kind may be used with this class.
+    pub fn has_instance(&self, kind: &Kind) -> bool {
+        matches!(
+            (self, kind),
+            (Self::Comparable(_), Kind::Date)
+                | (Self::Comparable(_), Kind::Decimal)
+                | (Self::Comparable(_), Kind::Duration)
+                | (Self::Comparable(_), Kind::Int)
+                | (Self::Comparable(_), Kind::Word)
+                | (Self::Equatable(_), Kind::Boolean)
+                | (Self::Equatable(_), Kind::Data(_))
+                | (Self::Equatable(_), Kind::Date)
+                | (Self::Equatable(_), Kind::Decimal)
+                | (Self::Equatable(_), Kind::Duration)
+                | (Self::Equatable(_), Kind::Int)
+                | (Self::Equatable(_), Kind::Word)
+                | (Self::Negatable(_), Kind::Int)
+                | (Self::Numerable(Numerable::Add(_, _)), Kind::Date)
+                | (Self::Numerable(Numerable::Subtract(_, _)), Kind::Date)
+                | (Self::Numerable(_), Kind::Decimal)
+                | (Self::Numerable(Numerable::Add(_, _)), Kind::Duration)
+                | (Self::Numerable(Numerable::Subtract(_, _)), Kind::Duration)
+                | (Self::Numerable(_), Kind::Int)
+                | (Self::Numerable(_), Kind::Word)
+        )
+    }
+
+    pub fn operands(&self) -> Vec<&Expression<S>> {
+        operands!(self)
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Expression<S>> {
+        operands!(self)
+    }
+}
+
+impl<S: Source> Numerable<S> {
     pub(super) fn kind(&self) -> Rc<Kind> {
         match self {
             Self::Add(e, _) | Self::Subtract(e, _) | Self::Multiply(e, _) | Self::Divide(e, _) => {
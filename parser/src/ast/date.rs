@@ -1,5 +1,36 @@
-#[derive(Clone, Debug)]
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use std::convert::TryFrom;
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum Date {
     Now,
     Date(u64, u64, u64, u64, u64, u64),
 }
+
+impl Date {
+    /// The number of seconds since the Unix epoch, which is only known for fixed dates.
+    pub fn timestamp(&self) -> Option<u64> {
+        match *self {
+            Self::Now => None,
+            Self::Date(year, month, day, hour, minute, second) => {
+                let timestamp = NaiveDate::from_ymd(year as i32, month as u32, day as u32)
+                    .and_hms(hour as u32, minute as u32, second as u32)
+                    .timestamp();
+                u64::try_from(timestamp).ok()
+            }
+        }
+    }
+
+    pub fn from_timestamp(timestamp: u64) -> Option<Self> {
+        let date = NaiveDateTime::from_timestamp_opt(i64::try_from(timestamp).ok()?, 0)?;
+
+        Some(Self::Date(
+            date.year() as u64,
+            date.month().into(),
+            date.day().into(),
+            date.hour().into(),
+            date.minute().into(),
+            date.second().into(),
+        ))
+    }
+}
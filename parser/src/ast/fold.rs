@@ -0,0 +1,241 @@
+//! A walk over the AST which takes each node by value and gives back its replacement, with the
+//! same layout as [`visit`](super::visit). The default functions rebuild a node from its folded
+//! children and keep its span.
+
+use std::{mem, rc::Rc};
+
+use super::{
+    state::{Effect, Transition},
+    Class, Definition, Expression, ExpressionType, Observable, Owned, Source, State, Variable,
+};
+
+pub trait Fold<S: Source = Owned> {
+    fn fold_definition(&mut self, definition: Definition<S>) -> Definition<S> {
+        fold_definition(self, definition)
+    }
+
+    fn fold_expression(&mut self, expression: Expression<S>) -> Expression<S> {
+        fold_expression(self, expression)
+    }
+
+    fn fold_abstraction(
+        &mut self,
+        argument: Variable<S>,
+        body: Expression<S>,
+    ) -> (Variable<S>, Expression<S>) {
+        fold_abstraction(self, argument, body)
+    }
+
+    fn fold_variable(&mut self, variable: Variable<S>) -> Variable<S> {
+        variable
+    }
+
+    fn fold_class(&mut self, class: Class<S>) -> Class<S> {
+        fold_class(self, class)
+    }
+
+    fn fold_observable(&mut self, observable: Observable<S>) -> Observable<S> {
+        fold_observable(self, observable)
+    }
+
+    fn fold_state(&mut self, state: State<S>) -> State<S> {
+        fold_state(self, state)
+    }
+
+    fn fold_transition(&mut self, transition: Transition<S>) -> Transition<S> {
+        fold_transition(self, transition)
+    }
+
+    fn fold_condition(&mut self, condition: Expression<S>) -> Expression<S> {
+        fold_condition(self, condition)
+    }
+
+    fn fold_effect(&mut self, effect: Effect<S>) -> Effect<S> {
+        fold_effect(self, effect)
+    }
+}
+
+pub fn fold_definition<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    definition: Definition<S>,
+) -> Definition<S> {
+    Definition::new(
+        folder.fold_variable(definition.variable),
+        folder.fold_expression(definition.expression),
+    )
+}
+
+pub fn fold_expression<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    expression: Expression<S>,
+) -> Expression<S> {
+    let folded = match expression.expression {
+        ExpressionType::Abstraction(argument, body) => {
+            let (argument, body) = folder.fold_abstraction(argument, *body);
+            ExpressionType::Abstraction(argument, Box::new(body))
+        }
+        ExpressionType::Application(function, argument) => {
+            ExpressionType::Application(boxed(folder, function), boxed(folder, argument))
+        }
+        ExpressionType::Class(class) => ExpressionType::Class(folder.fold_class(class)),
+        ExpressionType::Conditional(condition, then, otherwise) => ExpressionType::Conditional(
+            boxed(folder, condition),
+            boxed(folder, then),
+            boxed(folder, otherwise),
+        ),
+        ExpressionType::Conversion(k, e) => ExpressionType::Conversion(k, boxed(folder, e)),
+        ExpressionType::Difference(e, k) => ExpressionType::Difference(boxed(folder, e), k),
+        ExpressionType::Field(name, i, e, k) => ExpressionType::Field(name, i, boxed(folder, e), k),
+        ExpressionType::Is(name, e) => ExpressionType::Is(name, boxed(folder, e)),
+        ExpressionType::Observable(observable) => {
+            ExpressionType::Observable(folder.fold_observable(observable))
+        }
+        ExpressionType::Projection(e, c, k) => ExpressionType::Projection(boxed(folder, e), c, k),
+        ExpressionType::Record(fields) => ExpressionType::Record(
+            fields
+                .into_iter()
+                .map(|(name, e)| (name, folder.fold_expression(e)))
+                .collect(),
+        ),
+        ExpressionType::Round(r, e) => ExpressionType::Round(r, boxed(folder, e)),
+        ExpressionType::State(state) => ExpressionType::State(folder.fold_state(state)),
+        ExpressionType::Tuple(elements) => ExpressionType::Tuple(
+            elements
+                .into_iter()
+                .map(|e| folder.fold_expression(e))
+                .collect(),
+        ),
+        ExpressionType::Variable(variable) => {
+            ExpressionType::Variable(folder.fold_variable(variable))
+        }
+        e @ (ExpressionType::Boolean(_)
+        | ExpressionType::Constructor(_, _)
+        | ExpressionType::Date(_)
+        | ExpressionType::Decimal(_)
+        | ExpressionType::Duration(_)
+        | ExpressionType::Int(_)
+        | ExpressionType::Word(_)) => e,
+    };
+
+    Expression::new(folded, expression.span)
+}
+
+pub fn fold_abstraction<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    argument: Variable<S>,
+    body: Expression<S>,
+) -> (Variable<S>, Expression<S>) {
+    (folder.fold_variable(argument), folder.fold_expression(body))
+}
+
+pub fn fold_class<S: Source, F: Fold<S> + ?Sized>(folder: &mut F, mut class: Class<S>) -> Class<S> {
+    for operand in class.operands_mut() {
+        *operand = folder.fold_expression(take(operand));
+    }
+    class
+}
+
+/// A constant shared with other expressions is copied before it is folded.
+pub fn fold_observable<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    observable: Observable<S>,
+) -> Observable<S> {
+    match observable {
+        Observable::Konst(e) => {
+            let e = Rc::try_unwrap(e).unwrap_or_else(|e| (*e).clone());
+            Observable::Konst(Rc::new(folder.fold_expression(e)))
+        }
+        o => o,
+    }
+}
+
+pub fn fold_state<S: Source, F: Fold<S> + ?Sized>(folder: &mut F, mut state: State<S>) -> State<S> {
+    for transition in state.transitions_mut() {
+        *transition = folder.fold_transition(mem::take(transition));
+    }
+    state
+}
+
+pub fn fold_transition<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    mut transition: Transition<S>,
+) -> Transition<S> {
+    for condition in transition.conditions_mut() {
+        *condition = folder.fold_condition(take(condition));
+    }
+    for effect in transition.effects_mut() {
+        *effect = folder.fold_effect(mem::replace(effect, Effect::Flip));
+    }
+    let next = transition.next_mut();
+    *next = folder.fold_expression(take(next));
+    transition
+}
+
+pub fn fold_condition<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    condition: Expression<S>,
+) -> Expression<S> {
+    folder.fold_expression(condition)
+}
+
+pub fn fold_effect<S: Source, F: Fold<S> + ?Sized>(folder: &mut F, effect: Effect<S>) -> Effect<S> {
+    match effect {
+        Effect::Scale(e) => Effect::Scale(folder.fold_expression(e)),
+        Effect::Spawn(e) => Effect::Spawn(folder.fold_expression(e)),
+        e @ (Effect::Flip | Effect::Withdraw) => e,
+    }
+}
+
+/// Folds a boxed expression in place, so that its box is reused.
+fn boxed<S: Source, F: Fold<S> + ?Sized>(
+    folder: &mut F,
+    mut expression: Box<Expression<S>>,
+) -> Box<Expression<S>> {
+    *expression = folder.fold_expression(take(&mut expression));
+    expression
+}
+
+/// Moves an expression out from behind a reference, leaving an empty tuple in its place.
+fn take<S: Source>(expression: &mut Expression<S>) -> Expression<S> {
+    let empty = Expression::new(ExpressionType::Tuple(Vec::new()), None);
+    mem::replace(expression, empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::contract;
+
+    struct Double;
+
+    impl Fold for Double {
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            match expression.expression {
+                ExpressionType::Word(w) => Expression::new(w.saturating_mul(2).into(), None),
+                _ => fold_expression(self, expression),
+            }
+        }
+    }
+
+    #[test]
+    fn fold_words() {
+        let definitions = contract("main = scale (konst 2) (scale (konst 3) one)").unwrap();
+        let main = definitions
+            .into_iter()
+            .find(|d| &*d.variable.name == "main");
+        let main = Double.fold_definition(main.unwrap());
+
+        let mut words = Vec::new();
+        let mut expressions = vec![&main.expression];
+        while let Some(e) = expressions.pop() {
+            match &e.expression {
+                ExpressionType::Word(w) => words.push(*w),
+                ExpressionType::Application(f, a) => expressions.extend([&**f, &**a]),
+                ExpressionType::Observable(Observable::Konst(e)) => expressions.push(e),
+                _ => {}
+            }
+        }
+        words.sort_unstable();
+        assert_eq!(words, [4, 6]);
+    }
+}
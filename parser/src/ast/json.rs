@@ -0,0 +1,569 @@
+//! JSON export and import of typed definitions, so that other tools can inspect contracts, or
+//! generate them without writing Sprint.
+//!
+//! Kinds are written out resolved, with the kind variables of polymorphic definitions numbered so
+//! that variables which are the same kind keep the same number.
+
+use super::{
+    state::{Effect, Transition},
+    Class, Comparable, Component, Data, Date, Definition, Definitions, Equatable, Expression,
+    ExpressionType, Kind, Negatable, Numerable, Observable, Rounding, Span, State, Variable,
+};
+use serde::{de::Error as _, Deserialize, Serialize};
+use std::{collections::HashMap, rc::Rc};
+
+pub use serde_json::Error;
+
+/// The version of the JSON schema, which is raised whenever it changes.
+pub const VERSION: u32 = 1;
+
+pub fn to_string(definitions: &[Definition]) -> String {
+    let mut export = Export::default();
+    let document = Document {
+        version: VERSION,
+        definitions: definitions.iter().map(|d| export.definition(d)).collect(),
+    };
+
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+/// Reads definitions which were written by [`to_string`].
+pub fn from_str(json: &str) -> Result<Definitions, Error> {
+    let document: Document = serde_json::from_str(json)?;
+
+    if document.version != VERSION {
+        return Err(Error::custom(format!(
+            "unsupported schema version {}, expected {}",
+            document.version, VERSION
+        )));
+    }
+
+    let mut import = Import::default();
+    Ok(document
+        .definitions
+        .into_iter()
+        .map(|d| import.definition(d))
+        .collect())
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    definitions: Vec<JsonDefinition>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonDefinition {
+    variable: JsonVariable,
+    expression: JsonExpression,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonVariable {
+    name: Rc<str>,
+    kind: JsonKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonExpression {
+    expression: Box<JsonExpressionType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>,
+}
+
+// Classes and observables are flattened into expressions, so an addition is written as
+// {"Add": [left, right]} rather than nested in {"Class": {"Numerable": ...}}.
+#[derive(Serialize, Deserialize)]
+enum JsonExpressionType {
+    Abstraction(JsonVariable, JsonExpression),
+    Application(JsonExpression, JsonExpression),
+    Boolean(bool),
+    Greater(JsonExpression, JsonExpression),
+    Less(JsonExpression, JsonExpression),
+    GreaterEqual(JsonExpression, JsonExpression),
+    LessEqual(JsonExpression, JsonExpression),
+    Equal(JsonExpression, JsonExpression),
+    NotEqual(JsonExpression, JsonExpression),
+    Negate(JsonExpression),
+    Add(JsonExpression, JsonExpression),
+    Subtract(JsonExpression, JsonExpression),
+    Multiply(JsonExpression, JsonExpression),
+    Divide(JsonExpression, JsonExpression),
+    Conditional(JsonExpression, JsonExpression, JsonExpression),
+    Constructor(Rc<str>, JsonKind),
+    Conversion(JsonKind, JsonExpression),
+    Date(JsonDate),
+    Decimal(u64),
+    Difference(JsonExpression, JsonKind),
+    Duration(u64),
+    Field(Rc<str>, usize, JsonExpression, JsonKind),
+    Int(i64),
+    Is(Rc<str>, JsonExpression),
+    IsParty,
+    IsCounterparty,
+    Konst(JsonExpression),
+    Projection(JsonExpression, JsonComponent, JsonKind),
+    Record(Vec<(Rc<str>, JsonExpression)>),
+    Round(JsonRounding, JsonExpression),
+    State(Vec<JsonTransition>),
+    Tuple(Vec<JsonExpression>),
+    Variable(JsonVariable),
+    Word(u64),
+}
+
+#[derive(Serialize, Deserialize)]
+enum JsonDate {
+    Now,
+    Date(u64, u64, u64, u64, u64, u64),
+}
+
+#[derive(Serialize, Deserialize)]
+enum JsonComponent {
+    Index(usize),
+    Name(Rc<str>),
+}
+
+#[derive(Serialize, Deserialize)]
+enum JsonRounding {
+    Down,
+    Up,
+    Nearest,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonTransition {
+    conditions: Vec<JsonExpression>,
+    // In the order in which they take effect.
+    effects: Vec<JsonEffect>,
+    next: JsonExpression,
+}
+
+#[derive(Serialize, Deserialize)]
+enum JsonEffect {
+    Flip,
+    Scale(JsonExpression),
+    Spawn(JsonExpression),
+    Withdraw,
+}
+
+#[derive(Serialize, Deserialize)]
+enum JsonKind {
+    Abstraction(Box<JsonKind>, Box<JsonKind>),
+    Alias(String, Box<JsonKind>),
+    Boolean,
+    Data(String, Vec<(String, Vec<JsonKind>)>),
+    Date,
+    Decimal,
+    Duration,
+    Int,
+    Observable(Box<JsonKind>),
+    Record(Vec<(String, JsonKind)>),
+    State,
+    Tuple(Vec<JsonKind>),
+    Variable(usize),
+    Word,
+}
+
+#[derive(Default)]
+struct Export {
+    // The number of each kind variable, by the address of the variable.
+    variables: HashMap<*const Kind, usize>,
+}
+
+impl Export {
+    fn definition(&mut self, definition: &Definition) -> JsonDefinition {
+        JsonDefinition {
+            variable: self.variable(&definition.variable),
+            expression: self.expression(&definition.expression),
+        }
+    }
+
+    fn variable(&mut self, variable: &Variable) -> JsonVariable {
+        JsonVariable {
+            name: variable.name.clone(),
+            kind: self.kind(&variable.kind),
+            span: variable.span,
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> JsonExpression {
+        use JsonExpressionType as J;
+
+        let json = match &expression.expression {
+            ExpressionType::Abstraction(v, e) => {
+                J::Abstraction(self.variable(v), self.expression(e))
+            }
+            ExpressionType::Application(f, a) => {
+                J::Application(self.expression(f), self.expression(a))
+            }
+            ExpressionType::Boolean(b) => J::Boolean(*b),
+            ExpressionType::Class(class) => self.class(class),
+            ExpressionType::Conditional(c, t, o) => {
+                J::Conditional(self.expression(c), self.expression(t), self.expression(o))
+            }
+            ExpressionType::Constructor(name, k) => J::Constructor(name.clone(), self.kind(k)),
+            ExpressionType::Conversion(k, e) => J::Conversion(self.kind(k), self.expression(e)),
+            ExpressionType::Date(Date::Now) => J::Date(JsonDate::Now),
+            ExpressionType::Date(Date::Date(year, month, day, hour, minute, second)) => {
+                J::Date(JsonDate::Date(*year, *month, *day, *hour, *minute, *second))
+            }
+            ExpressionType::Decimal(d) => J::Decimal(*d),
+            ExpressionType::Difference(e, k) => J::Difference(self.expression(e), self.kind(k)),
+            ExpressionType::Duration(d) => J::Duration(*d),
+            ExpressionType::Field(name, index, e, k) => {
+                J::Field(name.clone(), *index, self.expression(e), self.kind(k))
+            }
+            ExpressionType::Int(i) => J::Int(*i),
+            ExpressionType::Is(name, e) => J::Is(name.clone(), self.expression(e)),
+            ExpressionType::Observable(Observable::IsParty) => J::IsParty,
+            ExpressionType::Observable(Observable::IsCounterparty) => J::IsCounterparty,
+            ExpressionType::Observable(Observable::Konst(e)) => J::Konst(self.expression(e)),
+            ExpressionType::Projection(e, component, k) => {
+                let component = match component {
+                    Component::Index(index) => JsonComponent::Index(*index),
+                    Component::Name(name) => JsonComponent::Name(name.clone()),
+                };
+                J::Projection(self.expression(e), component, self.kind(k))
+            }
+            ExpressionType::Record(fields) => J::Record(
+                fields
+                    .iter()
+                    .map(|(name, e)| (name.clone(), self.expression(e)))
+                    .collect(),
+            ),
+            ExpressionType::Round(rounding, e) => {
+                let rounding = match rounding {
+                    Rounding::Down => JsonRounding::Down,
+                    Rounding::Up => JsonRounding::Up,
+                    Rounding::Nearest => JsonRounding::Nearest,
+                };
+                J::Round(rounding, self.expression(e))
+            }
+            ExpressionType::State(state) => J::State(
+                state
+                    .transitions()
+                    .iter()
+                    .map(|t| self.transition(t))
+                    .collect(),
+            ),
+            ExpressionType::Tuple(elements) => {
+                J::Tuple(elements.iter().map(|e| self.expression(e)).collect())
+            }
+            ExpressionType::Variable(v) => J::Variable(self.variable(v)),
+            ExpressionType::Word(w) => J::Word(*w),
+        };
+
+        JsonExpression {
+            expression: json.into(),
+            span: expression.span,
+        }
+    }
+
+    fn class(&mut self, class: &Class) -> JsonExpressionType {
+        use JsonExpressionType as J;
+
+        let operands: Vec<_> = class
+            .operands()
+            .into_iter()
+            .map(|e| self.expression(e))
+            .collect();
+        let mut operands = operands.into_iter();
+        let mut next = || operands.next().unwrap();
+
+        match class {
+            Class::Comparable(Comparable::Greater(..)) => J::Greater(next(), next()),
+            Class::Comparable(Comparable::Less(..)) => J::Less(next(), next()),
+            Class::Comparable(Comparable::GreaterEqual(..)) => J::GreaterEqual(next(), next()),
+            Class::Comparable(Comparable::LessEqual(..)) => J::LessEqual(next(), next()),
+            Class::Equatable(Equatable::Equal(..)) => J::Equal(next(), next()),
+            Class::Equatable(Equatable::NotEqual(..)) => J::NotEqual(next(), next()),
+            Class::Negatable(Negatable::Negate(..)) => J::Negate(next()),
+            Class::Numerable(Numerable::Add(..)) => J::Add(next(), next()),
+            Class::Numerable(Numerable::Subtract(..)) => J::Subtract(next(), next()),
+            Class::Numerable(Numerable::Multiply(..)) => J::Multiply(next(), next()),
+            Class::Numerable(Numerable::Divide(..)) => J::Divide(next(), next()),
+        }
+    }
+
+    fn transition(&mut self, transition: &Transition) -> JsonTransition {
+        let effects = transition
+            .effects()
+            .into_iter()
+            .map(|effect| match effect {
+                Effect::Flip => JsonEffect::Flip,
+                Effect::Scale(e) => JsonEffect::Scale(self.expression(e)),
+                Effect::Spawn(e) => JsonEffect::Spawn(self.expression(e)),
+                Effect::Withdraw => JsonEffect::Withdraw,
+            })
+            .collect();
+
+        JsonTransition {
+            conditions: transition
+                .conditions()
+                .iter()
+                .map(|e| self.expression(e))
+                .collect(),
+            effects,
+            next: self.expression(transition.next()),
+        }
+    }
+
+    fn kind(&mut self, kind: &Rc<Kind>) -> JsonKind {
+        let kind = Kind::resolve(kind.clone());
+
+        match kind.as_ref() {
+            Kind::Abstraction(from, to) => {
+                JsonKind::Abstraction(self.kind(from).into(), self.kind(to).into())
+            }
+            Kind::Alias(name, k) => JsonKind::Alias(name.to_string(), self.kind(k).into()),
+            Kind::Boolean => JsonKind::Boolean,
+            Kind::Data(data) => JsonKind::Data(
+                data.name.clone(),
+                data.constructors
+                    .iter()
+                    .map(|(name, fields)| (name.clone(), self.kinds(fields)))
+                    .collect(),
+            ),
+            Kind::Date => JsonKind::Date,
+            Kind::Decimal => JsonKind::Decimal,
+            Kind::Duration => JsonKind::Duration,
+            Kind::Int => JsonKind::Int,
+            Kind::Observable(k) => JsonKind::Observable(self.kind(k).into()),
+            Kind::Record(fields) => JsonKind::Record(
+                fields
+                    .iter()
+                    .map(|(name, k)| (name.clone(), self.kind(k)))
+                    .collect(),
+            ),
+            Kind::State => JsonKind::State,
+            Kind::Tuple(elements) => JsonKind::Tuple(self.kinds(elements)),
+            Kind::Unresolved(_) => {
+                let count = self.variables.len();
+                JsonKind::Variable(*self.variables.entry(Rc::as_ptr(&kind)).or_insert(count))
+            }
+            Kind::Word => JsonKind::Word,
+        }
+    }
+
+    fn kinds(&mut self, kinds: &[Rc<Kind>]) -> Vec<JsonKind> {
+        kinds.iter().map(|k| self.kind(k)).collect()
+    }
+}
+
+#[derive(Default)]
+struct Import {
+    variables: HashMap<usize, Rc<Kind>>,
+}
+
+impl Import {
+    fn definition(&mut self, definition: JsonDefinition) -> Definition {
+        Definition::new(
+            self.variable(definition.variable),
+            self.expression(definition.expression),
+        )
+    }
+
+    fn variable(&mut self, variable: JsonVariable) -> Variable {
+        Variable::new(variable.name, self.kind(variable.kind), variable.span)
+    }
+
+    fn expression(&mut self, expression: JsonExpression) -> Expression {
+        use JsonExpressionType as J;
+
+        let span = expression.span;
+        let expression = match *expression.expression {
+            J::Abstraction(v, e) => ExpressionType::Abstraction(self.variable(v), self.boxed(e)),
+            J::Application(f, a) => ExpressionType::Application(self.boxed(f), self.boxed(a)),
+            J::Boolean(b) => ExpressionType::Boolean(b),
+            J::Greater(l, r) => {
+                Class::Comparable(Comparable::Greater(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Less(l, r) => {
+                Class::Comparable(Comparable::Less(self.boxed(l), self.boxed(r))).into()
+            }
+            J::GreaterEqual(l, r) => {
+                Class::Comparable(Comparable::GreaterEqual(self.boxed(l), self.boxed(r))).into()
+            }
+            J::LessEqual(l, r) => {
+                Class::Comparable(Comparable::LessEqual(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Equal(l, r) => {
+                Class::Equatable(Equatable::Equal(self.boxed(l), self.boxed(r))).into()
+            }
+            J::NotEqual(l, r) => {
+                Class::Equatable(Equatable::NotEqual(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Negate(e) => Class::Negatable(Negatable::Negate(self.boxed(e))).into(),
+            J::Add(l, r) => Class::Numerable(Numerable::Add(self.boxed(l), self.boxed(r))).into(),
+            J::Subtract(l, r) => {
+                Class::Numerable(Numerable::Subtract(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Multiply(l, r) => {
+                Class::Numerable(Numerable::Multiply(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Divide(l, r) => {
+                Class::Numerable(Numerable::Divide(self.boxed(l), self.boxed(r))).into()
+            }
+            J::Conditional(c, t, o) => {
+                ExpressionType::Conditional(self.boxed(c), self.boxed(t), self.boxed(o))
+            }
+            J::Constructor(name, k) => ExpressionType::Constructor(name, self.kind(k)),
+            J::Conversion(k, e) => ExpressionType::Conversion(self.kind(k), self.boxed(e)),
+            J::Date(JsonDate::Now) => ExpressionType::Date(Date::Now),
+            J::Date(JsonDate::Date(year, month, day, hour, minute, second)) => {
+                ExpressionType::Date(Date::Date(year, month, day, hour, minute, second))
+            }
+            J::Decimal(d) => ExpressionType::Decimal(d),
+            J::Difference(e, k) => ExpressionType::Difference(self.boxed(e), self.kind(k)),
+            J::Duration(d) => ExpressionType::Duration(d),
+            J::Field(name, index, e, k) => {
+                ExpressionType::Field(name, index, self.boxed(e), self.kind(k))
+            }
+            J::Int(i) => ExpressionType::Int(i),
+            J::Is(name, e) => ExpressionType::Is(name, self.boxed(e)),
+            J::IsParty => Observable::IsParty.into(),
+            J::IsCounterparty => Observable::IsCounterparty.into(),
+            J::Konst(e) => Observable::from(self.expression(e)).into(),
+            J::Projection(e, component, k) => {
+                let component = match component {
+                    JsonComponent::Index(index) => Component::Index(index),
+                    JsonComponent::Name(name) => Component::Name(name),
+                };
+                ExpressionType::Projection(self.boxed(e), component, self.kind(k))
+            }
+            J::Record(fields) => ExpressionType::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, e)| (name, self.expression(e)))
+                    .collect(),
+            ),
+            J::Round(rounding, e) => {
+                let rounding = match rounding {
+                    JsonRounding::Down => Rounding::Down,
+                    JsonRounding::Up => Rounding::Up,
+                    JsonRounding::Nearest => Rounding::Nearest,
+                };
+                ExpressionType::Round(rounding, self.boxed(e))
+            }
+            J::State(transitions) => {
+                let mut state = State::default();
+                for transition in transitions {
+                    state.add_transition(self.transition(transition));
+                }
+                state.into()
+            }
+            J::Tuple(elements) => {
+                ExpressionType::Tuple(elements.into_iter().map(|e| self.expression(e)).collect())
+            }
+            J::Variable(v) => self.variable(v).into(),
+            J::Word(w) => ExpressionType::Word(w),
+        };
+
+        Expression::new(expression, span)
+    }
+
+    fn boxed(&mut self, expression: JsonExpression) -> Box<Expression> {
+        Box::new(self.expression(expression))
+    }
+
+    fn transition(&mut self, transition: JsonTransition) -> Transition {
+        let mut result = Transition::default();
+
+        for condition in transition.conditions {
+            result.add_condition(self.expression(condition));
+        }
+
+        // Transitions keep their effects in the reverse of the order that they take effect.
+        for effect in transition.effects.into_iter().rev() {
+            result.add_effect(match effect {
+                JsonEffect::Flip => Effect::Flip,
+                JsonEffect::Scale(e) => Effect::Scale(self.expression(e)),
+                JsonEffect::Spawn(e) => Effect::Spawn(self.expression(e)),
+                JsonEffect::Withdraw => Effect::Withdraw,
+            });
+        }
+
+        result.set_next(self.expression(transition.next));
+        result
+    }
+
+    fn kind(&mut self, kind: JsonKind) -> Rc<Kind> {
+        match kind {
+            JsonKind::Abstraction(from, to) => {
+                Kind::Abstraction(self.kind(*from), self.kind(*to)).into()
+            }
+            JsonKind::Alias(name, k) => Kind::Alias(name.into(), self.kind(*k)).into(),
+            JsonKind::Boolean => Kind::Boolean.into(),
+            JsonKind::Data(name, constructors) => {
+                let constructors = constructors
+                    .into_iter()
+                    .map(|(name, fields)| {
+                        (name, fields.into_iter().map(|k| self.kind(k)).collect())
+                    })
+                    .collect();
+                Kind::Data(Data { name, constructors }.into()).into()
+            }
+            JsonKind::Date => Kind::Date.into(),
+            JsonKind::Decimal => Kind::Decimal.into(),
+            JsonKind::Duration => Kind::Duration.into(),
+            JsonKind::Int => Kind::Int.into(),
+            JsonKind::Observable(k) => Kind::Observable(self.kind(*k)).into(),
+            JsonKind::Record(fields) => Kind::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, k)| (name, self.kind(k)))
+                    .collect(),
+            )
+            .into(),
+            JsonKind::State => Kind::State.into(),
+            JsonKind::Tuple(elements) => {
+                Kind::Tuple(elements.into_iter().map(|k| self.kind(k)).collect()).into()
+            }
+            JsonKind::Variable(number) => self
+                .variables
+                .entry(number)
+                .or_insert_with(|| Kind::default().into())
+                .clone(),
+            JsonKind::Word => Kind::Word.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::contract;
+
+    #[test]
+    fn json_round_trip() {
+        let input = "data Leg = Fixed Word | Floating\n\
+                     rate :: Leg -> Word\n\
+                     rate leg = case leg of\n  Fixed n -> n * 2\n  Floating -> 1\n\
+                     pair :: (Word, Date)\n\
+                     pair = (rate (Fixed 3), 2020-01-01T00:00:00Z)\n\
+                     main = before pair.1 (scale (konst (toDecimal pair.0 + 0.5)) (give one))";
+        let definitions = contract(input).unwrap();
+        let json = to_string(&definitions);
+        let imported = from_str(&json).unwrap();
+
+        assert_eq!(imported.len(), definitions.len());
+        assert_eq!(to_string(&imported), json);
+
+        let konst = imported
+            .iter()
+            .find(|d| &*d.variable.name == "konst")
+            .unwrap();
+        assert_eq!(konst.variable.kind.to_string(), "a -> Observable a");
+    }
+
+    #[test]
+    fn json_version() {
+        let json = format!("{{\"version\": {}, \"definitions\": []}}", VERSION + 1);
+        assert!(from_str(&json).is_err());
+        assert!(from_str("{\"version\": 1, \"definitions\": []}")
+            .unwrap()
+            .is_empty());
+    }
+}
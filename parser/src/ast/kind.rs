@@ -1,3 +1,7 @@
+use super::{
+    visit::{self, Visit},
+    Component, Source,
+};
 use std::{
     cell::RefCell,
     collections::HashMap,
@@ -8,14 +12,43 @@ use std::{
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Kind {
     Abstraction(Rc<Self>, Rc<Self>),
+    /// A kind named by a type alias, which is displayed by its name.
+    Alias(Rc<String>, Rc<Self>),
     Boolean,
+    Data(Rc<Data>),
     Date,
+    Decimal,
+    Duration,
+    Int,
     Observable(Rc<Self>),
+    /// A record, whose fields are kept sorted by name.
+    Record(Rc<[(String, Rc<Self>)]>),
     State,
+    Tuple(Rc<[Rc<Self>]>),
     Unresolved(RefCell<Option<Rc<Self>>>),
     Word,
 }
 
+/// A data type declared with "data", along with the kinds of the fields of each of its
+/// constructors. Fields can only have data types declared before this one, so a data type never
+/// contains itself.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Data {
+    pub name: String,
+    pub constructors: Vec<(String, Vec<Rc<Kind>>)>,
+}
+
+impl Data {
+    /// The tag of the named constructor, which is its position in the declaration, and the
+    /// kinds of its fields.
+    pub fn constructor(&self, name: &str) -> Option<(usize, &[Rc<Kind>])> {
+        self.constructors
+            .iter()
+            .position(|(constructor, _)| constructor == name)
+            .map(|tag| (tag, self.constructors[tag].1.as_slice()))
+    }
+}
+
 impl Default for Kind {
     fn default() -> Self {
         Self::Unresolved(None.into())
@@ -23,7 +56,19 @@ impl Default for Kind {
 }
 
 impl Kind {
-    pub fn simplify(mut kind: Rc<Kind>) -> Rc<Kind> {
+    /// Looks through resolved kinds and aliases to the kind which they stand for.
+    pub fn simplify(kind: Rc<Kind>) -> Rc<Kind> {
+        let mut kind = Self::resolve(kind);
+
+        while let Kind::Alias(_, k) = kind.clone().as_ref() {
+            kind = Self::resolve(k.clone());
+        }
+
+        kind
+    }
+
+    /// Looks through resolved kinds, but not aliases, so that their names can still be shown.
+    pub fn resolve(mut kind: Rc<Kind>) -> Rc<Kind> {
         while let Kind::Unresolved(k) = kind.clone().as_ref() {
             match k.borrow().as_ref() {
                 Some(k) => kind = k.clone(),
@@ -35,23 +80,97 @@ impl Kind {
     }
 
     pub fn contains(this: Rc<Kind>, other: Rc<Kind>) -> bool {
-        if Rc::ptr_eq(&this, &other) {
-            return true;
+        struct Contains(Rc<Kind>, bool);
+
+        impl Visit<'_> for Contains {
+            fn visit_kind(&mut self, kind: Rc<Kind>) {
+                self.1 |= Rc::ptr_eq(&kind, &self.0);
+                if !self.1 {
+                    visit::visit_kind(self, kind);
+                }
+            }
+        }
+
+        let mut contains = Contains(other, false);
+        contains.visit_kind(this);
+        contains.1
+    }
+
+    /// Copies a kind, replacing each of its unresolved kinds with a fresh one.
+    pub fn instantiate(kind: Rc<Kind>) -> Rc<Kind> {
+        Self::instantiate_with(kind, &mut HashMap::new())
+    }
+
+    // Aliases can't contain unresolved kinds, so they are kept as they are.
+    fn instantiate_with(kind: Rc<Kind>, fresh: &mut HashMap<*const Kind, Rc<Kind>>) -> Rc<Kind> {
+        let kind = Self::resolve(kind);
+
+        match kind.as_ref() {
+            Self::Abstraction(from, to) => Self::Abstraction(
+                Self::instantiate_with(from.clone(), fresh),
+                Self::instantiate_with(to.clone(), fresh),
+            )
+            .into(),
+            Self::Observable(k) => {
+                Self::Observable(Self::instantiate_with(k.clone(), fresh)).into()
+            }
+            Self::Record(fields) => Self::Record(
+                fields
+                    .iter()
+                    .map(|(name, k)| (name.clone(), Self::instantiate_with(k.clone(), fresh)))
+                    .collect(),
+            )
+            .into(),
+            Self::Tuple(kinds) => Self::Tuple(
+                kinds
+                    .iter()
+                    .map(|k| Self::instantiate_with(k.clone(), fresh))
+                    .collect(),
+            )
+            .into(),
+            Self::Unresolved(_) => fresh.entry(Rc::as_ptr(&kind)).or_default().clone(),
+            _ => kind,
         }
+    }
+
+    /// The distinct unresolved kinds which a kind contains.
+    pub fn variables(kind: Rc<Kind>) -> Vec<Rc<Kind>> {
+        let kind = Self::simplify(kind);
+
+        let components = match kind.as_ref() {
+            Self::Abstraction(from, to) => vec![from.clone(), to.clone()],
+            Self::Observable(k) => vec![k.clone()],
+            Self::Record(fields) => fields.iter().map(|(_, k)| k.clone()).collect(),
+            Self::Tuple(kinds) => kinds.to_vec(),
+            Self::Unresolved(_) => return vec![kind],
+            _ => Vec::new(),
+        };
+
+        let mut variables: Vec<Rc<Kind>> = Vec::new();
 
-        match this.as_ref() {
-            Self::Abstraction(from, to) => {
-                Self::contains(from.clone(), other.clone()) || Self::contains(to.clone(), other)
+        for variable in components.into_iter().flat_map(Self::variables) {
+            if !variables.iter().any(|v| Rc::ptr_eq(v, &variable)) {
+                variables.push(variable);
             }
-            Self::Boolean => false,
-            Self::Date => false,
-            Self::Observable(k) => Self::contains(k.clone(), other),
-            Self::State => false,
-            Self::Unresolved(k) => match k.borrow().as_ref() {
-                Some(k) => Self::contains(k.clone(), other),
-                None => false,
-            },
-            Self::Word => false,
+        }
+
+        variables
+    }
+
+    /// The position of a component of a tuple or a record, along with its kind.
+    pub fn component<S: Source>(
+        kind: Rc<Kind>,
+        component: &Component<S>,
+    ) -> Option<(usize, Rc<Kind>)> {
+        match (Self::simplify(kind).as_ref(), component) {
+            (Self::Record(fields), Component::Name(name)) => fields
+                .iter()
+                .position(|(field, _)| field == &**name)
+                .map(|position| (position, fields[position].1.clone())),
+            (Self::Tuple(kinds), Component::Index(index)) => {
+                kinds.get(*index).map(|kind| (*index, kind.clone()))
+            }
+            _ => None,
         }
     }
 }
@@ -104,7 +223,7 @@ impl Display for Formatter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.kind {
             Kind::Abstraction(from, to) => {
-                let from = Kind::simplify(from.clone());
+                let from = Kind::resolve(from.clone());
 
                 match from.as_ref() {
                     Kind::Abstraction(_, _) => write!(f, "({})", self.with(&from)),
@@ -113,10 +232,26 @@ impl Display for Formatter<'_> {
 
                 write!(f, " -> {}", self.with(to))
             }
+            Kind::Alias(name, _) => write!(f, "{}", name),
             Kind::Boolean => write!(f, "Bool"),
+            Kind::Data(data) => write!(f, "{}", data.name),
             Kind::Date => write!(f, "Date"),
+            Kind::Decimal => write!(f, "Decimal"),
+            Kind::Duration => write!(f, "Duration"),
+            Kind::Int => write!(f, "Int"),
             Kind::Observable(k) => write!(f, "Observable {}", self.with(k)),
+            Kind::Record(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(name, k)| format!("{} :: {}", name, self.with(k)))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
             Kind::State => write!(f, "Contract"),
+            Kind::Tuple(kinds) => {
+                let kinds: Vec<_> = kinds.iter().map(|k| self.with(k).to_string()).collect();
+                write!(f, "({})", kinds.join(", "))
+            }
             Kind::Unresolved(k) => match k.borrow().as_ref() {
                 Some(k) => self.with(k).fmt(f),
                 None => write!(f, "{}", self.symbol()),
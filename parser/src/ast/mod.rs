@@ -1,32 +1,44 @@
+pub mod fold;
+pub mod json;
 pub mod state;
+pub mod visit;
+pub mod visit_mut;
 
 mod class;
 mod date;
 mod kind;
+mod source;
 
 pub use self::{
     class::{Class, Comparable, Equatable, Negatable, Numerable},
     date::Date,
-    kind::Kind,
-    state::State,
+    kind::{Data, Kind},
+    source::{borrowed, Borrowed, Owned, Source, Span},
+    state::{Effect, State, Transition},
 };
 
-use super::parser::Span;
 use std::{
+    fmt::{self, Display},
     hash::{Hash, Hasher},
     rc::Rc,
 };
 
-pub type Definitions<'a> = Vec<Definition<'a>>;
+pub type Definitions<S = Owned> = Vec<Definition<S>>;
+
+/// Decimals are fixed-point numbers, stored as a whole number of billionths.
+pub const DECIMAL_PLACES: usize = 9;
+
+/// The primitive which uses of scale refer to when scaling by a Decimal.
+pub const SCALE_DECIMAL: &str = "scale_decimal";
 
 #[derive(Clone, Debug)]
-pub struct Definition<'a> {
-    pub variable: Variable<'a>,
-    pub expression: Expression<'a>,
+pub struct Definition<S: Source = Owned> {
+    pub variable: Variable<S>,
+    pub expression: Expression<S>,
 }
 
-impl<'a> Definition<'a> {
-    pub fn new(variable: Variable<'a>, expression: Expression<'a>) -> Self {
+impl<S: Source> Definition<S> {
+    pub fn new(variable: Variable<S>, expression: Expression<S>) -> Self {
         Self {
             variable,
             expression,
@@ -35,13 +47,13 @@ impl<'a> Definition<'a> {
 }
 
 #[derive(Clone, Debug)]
-pub struct Expression<'a> {
-    pub expression: ExpressionType<'a>,
-    pub span: Option<Span<'a>>,
+pub struct Expression<S: Source = Owned> {
+    pub expression: ExpressionType<S>,
+    pub span: Option<S::Span>,
 }
 
-impl<'a> Expression<'a> {
-    pub fn new(expression: ExpressionType<'a>, span: Option<Span<'a>>) -> Self {
+impl<S: Source> Expression<S> {
+    pub fn new(expression: ExpressionType<S>, span: Option<S::Span>) -> Self {
         Self { expression, span }
     }
 
@@ -51,24 +63,44 @@ impl<'a> Expression<'a> {
 }
 
 #[derive(Clone, Debug)]
-pub enum ExpressionType<'a> {
-    Abstraction(Variable<'a>, Box<Expression<'a>>),
-    Application(Box<Expression<'a>>, Box<Expression<'a>>),
+pub enum ExpressionType<S: Source = Owned> {
+    Abstraction(Variable<S>, Box<Expression<S>>),
+    Application(Box<Expression<S>>, Box<Expression<S>>),
     Boolean(bool),
-    Class(Class<'a>),
+    Class(Class<S>),
+    Conditional(Box<Expression<S>>, Box<Expression<S>>, Box<Expression<S>>),
+    /// A constructor of a data type, with the kind of a function from its fields to the type.
+    Constructor(S::Name, Rc<Kind>),
+    Conversion(Rc<Kind>, Box<Expression<S>>),
     Date(Date),
-    Observable(Observable<'a>),
-    State(State<'a>),
-    Variable(Variable<'a>),
+    Decimal(u64),
+    /// A subtraction, which gives a Duration when one date is subtracted from another and the
+    /// kind of its operands otherwise.
+    Difference(Box<Expression<S>>, Rc<Kind>),
+    Duration(u64),
+    /// The field at an index of a value made with the named constructor.
+    Field(S::Name, usize, Box<Expression<S>>, Rc<Kind>),
+    Int(i64),
+    /// Whether a value was made with the named constructor.
+    Is(S::Name, Box<Expression<S>>),
+    Observable(Observable<S>),
+    /// A component of a tuple or a record.
+    Projection(Box<Expression<S>>, Component<S>, Rc<Kind>),
+    /// A record, whose fields are kept sorted by name.
+    Record(Vec<(S::Name, Expression<S>)>),
+    Round(Rounding, Box<Expression<S>>),
+    State(State<S>),
+    Tuple(Vec<Expression<S>>),
+    Variable(Variable<S>),
     Word(u64),
 }
 
-impl ExpressionType<'_> {
+impl<S: Source> ExpressionType<S> {
     pub fn kind(&self) -> Rc<Kind> {
         match self {
             Self::Abstraction(from, to) => Kind::Abstraction(from.kind.clone(), to.kind()).into(),
 
-            Self::Application(f, _) => match f.kind().as_ref() {
+            Self::Application(f, _) => match Kind::simplify(f.kind()).as_ref() {
                 Kind::Abstraction(_, k) => k.clone(),
                 _ => unreachable!(),
             },
@@ -82,8 +114,26 @@ impl ExpressionType<'_> {
                 Class::Numerable(n) => n.kind(),
             },
 
+            Self::Conditional(_, then, _) => then.kind(),
+
+            Self::Constructor(_, kind) => kind.clone(),
+
+            Self::Conversion(kind, _) => kind.clone(),
+
             Self::Date(_) => Kind::Date.into(),
 
+            Self::Decimal(_) => Kind::Decimal.into(),
+
+            Self::Difference(_, k) => k.clone(),
+
+            Self::Duration(_) => Kind::Duration.into(),
+
+            Self::Field(_, _, _, kind) => kind.clone(),
+
+            Self::Int(_) => Kind::Int.into(),
+
+            Self::Is(_, _) => Kind::Boolean.into(),
+
             Self::Observable(o) => Kind::Observable(match o {
                 Observable::IsParty => Kind::Boolean.into(),
                 Observable::IsCounterparty => Kind::Boolean.into(),
@@ -91,8 +141,24 @@ impl ExpressionType<'_> {
             })
             .into(),
 
+            Self::Projection(_, _, kind) => kind.clone(),
+
+            Self::Record(fields) => Kind::Record(
+                fields
+                    .iter()
+                    .map(|(name, e)| (name.to_string(), e.kind()))
+                    .collect(),
+            )
+            .into(),
+
+            Self::Round(_, _) => Kind::Word.into(),
+
             Self::State(_) => Kind::State.into(),
 
+            Self::Tuple(elements) => {
+                Kind::Tuple(elements.iter().map(Expression::kind).collect()).into()
+            }
+
             Self::Variable(v) => v.kind.clone(),
 
             Self::Word(_) => Kind::Word.into(),
@@ -100,78 +166,122 @@ impl ExpressionType<'_> {
     }
 }
 
-impl From<bool> for ExpressionType<'_> {
+impl<S: Source> From<bool> for ExpressionType<S> {
     fn from(b: bool) -> Self {
         Self::Boolean(b)
     }
 }
 
-impl<'a> From<Class<'a>> for ExpressionType<'a> {
-    fn from(c: Class<'a>) -> Self {
+impl<S: Source> From<Class<S>> for ExpressionType<S> {
+    fn from(c: Class<S>) -> Self {
         Self::Class(c)
     }
 }
 
-impl<'a> From<Observable<'a>> for ExpressionType<'a> {
-    fn from(o: Observable<'a>) -> Self {
+impl<S: Source> From<i64> for ExpressionType<S> {
+    fn from(i: i64) -> Self {
+        Self::Int(i)
+    }
+}
+
+impl<S: Source> From<Observable<S>> for ExpressionType<S> {
+    fn from(o: Observable<S>) -> Self {
         Self::Observable(o)
     }
 }
 
-impl<'a> From<State<'a>> for ExpressionType<'a> {
-    fn from(s: State<'a>) -> Self {
+impl<S: Source> From<State<S>> for ExpressionType<S> {
+    fn from(s: State<S>) -> Self {
         Self::State(s)
     }
 }
 
-impl<'a> From<Variable<'a>> for ExpressionType<'a> {
-    fn from(v: Variable<'a>) -> Self {
+impl<S: Source> From<Variable<S>> for ExpressionType<S> {
+    fn from(v: Variable<S>) -> Self {
         Self::Variable(v)
     }
 }
 
-impl From<u64> for ExpressionType<'_> {
+impl<S: Source> From<u64> for ExpressionType<S> {
     fn from(w: u64) -> Self {
         Self::Word(w)
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct Variable<'a> {
-    pub name: &'a str,
+pub struct Variable<S: Source = Owned> {
+    pub name: S::Name,
     pub kind: Rc<Kind>,
-    pub span: Option<Span<'a>>,
+    pub span: Option<S::Span>,
+}
+
+impl<S: Source> Variable<S> {
+    pub fn new(name: impl Into<S::Name>, kind: Rc<Kind>, span: Option<S::Span>) -> Self {
+        Variable {
+            name: name.into(),
+            kind,
+            span,
+        }
+    }
 }
 
-impl<'a> Variable<'a> {
-    pub fn new(name: &'a str, kind: Rc<Kind>, span: Option<Span<'a>>) -> Self {
-        Variable { name, kind, span }
+impl<'a> Variable<Borrowed<'a>> {
+    /// The module which a qualified use, such as "Bonds.zero", refers to.
+    pub fn qualifier(&self) -> Option<&'a str> {
+        let (module, _) = self.span?.fragment.rsplit_once('.')?;
+        Some(module)
     }
 }
 
-impl PartialEq for Variable<'_> {
+impl<S: Source> PartialEq for Variable<S> {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
     }
 }
 
-impl Eq for Variable<'_> {}
+impl<S: Source> Eq for Variable<S> {}
 
-impl Hash for Variable<'_> {
+impl<S: Source> Hash for Variable<S> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
     }
 }
 
+/// A component of a tuple, by its position, or of a record, by its name.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Component<S: Source = Owned> {
+    Index(usize),
+    Name(S::Name),
+}
+
+impl Copy for Component<Borrowed<'_>> {}
+
+impl<S: Source> Display for Component<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{}", index),
+            Self::Name(name) => write!(f, "{}", &**name),
+        }
+    }
+}
+
+/// How a Decimal is rounded to a whole number.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rounding {
+    Down,
+    Up,
+    Nearest,
+}
+
 #[derive(Clone, Debug)]
-pub enum Observable<'a> {
+pub enum Observable<S: Source = Owned> {
     IsParty,
     IsCounterparty,
-    Konst(Rc<Expression<'a>>),
+    Konst(Rc<Expression<S>>),
 }
 
-impl<'a> From<Expression<'a>> for Observable<'a> {
-    fn from(e: Expression<'a>) -> Self {
+impl<S: Source> From<Expression<S>> for Observable<S> {
+    fn from(e: Expression<S>) -> Self {
         Self::Konst(e.into())
     }
 }
@@ -184,7 +294,7 @@ mod tests {
     fn kind_of_application() {
         // one :: Word -> Word -> Boolean
         // one x y = true
-        let one = Expression::new(
+        let one: Expression = Expression::new(
             ExpressionType::Abstraction(
                 Variable::new("x", Kind::Word.into(), None),
                 Expression::new(
@@ -0,0 +1,253 @@
+//! What the AST refers to names and code by. Programs are checked with names and spans which
+//! borrow from their source, so that errors can show the code which they are about, and the
+//! parser gives them back owned once they pass, so that contracts can be built in Rust, or kept
+//! once their source is gone.
+
+use super::{
+    state::{Effect, Transition},
+    Class, Comparable, Component, Definition, Equatable, Expression, ExpressionType, Negatable,
+    Numerable, Observable, State, Variable,
+};
+use crate::parser;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, hash::Hash, marker::PhantomData, ops::Deref, rc::Rc};
+
+pub trait Source: Clone + Debug + Default + PartialEq {
+    type Name: Clone + Debug + Eq + Hash + Deref<Target = str>;
+    type Span: Copy + Debug + PartialEq;
+}
+
+/// Names and spans which point into the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Borrowed<'a>(PhantomData<&'a str>);
+
+impl<'a> Source for Borrowed<'a> {
+    type Name = &'a str;
+    type Span = parser::Span<'a>;
+}
+
+/// Names and spans which don't borrow from anything.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Owned;
+
+impl Source for Owned {
+    type Name = Rc<str>;
+    type Span = Span;
+}
+
+/// The AST with names and spans which borrow from the source, as the parser checks it.
+pub mod borrowed {
+    use super::Borrowed;
+
+    pub type Class<'a> = super::Class<Borrowed<'a>>;
+    pub type Comparable<'a> = super::Comparable<Borrowed<'a>>;
+    pub type Component<'a> = super::Component<Borrowed<'a>>;
+    pub type Definition<'a> = super::Definition<Borrowed<'a>>;
+    pub type Definitions<'a> = Vec<Definition<'a>>;
+    pub type Effect<'a> = super::Effect<Borrowed<'a>>;
+    pub type Equatable<'a> = super::Equatable<Borrowed<'a>>;
+    pub type Expression<'a> = super::Expression<Borrowed<'a>>;
+    pub type ExpressionType<'a> = super::ExpressionType<Borrowed<'a>>;
+    pub type Negatable<'a> = super::Negatable<Borrowed<'a>>;
+    pub type Numerable<'a> = super::Numerable<Borrowed<'a>>;
+    pub type Observable<'a> = super::Observable<Borrowed<'a>>;
+    pub type State<'a> = super::State<Borrowed<'a>>;
+    pub type Transition<'a> = super::Transition<Borrowed<'a>>;
+    pub type Variable<'a> = super::Variable<Borrowed<'a>>;
+}
+
+/// A position in the source, by its offset from the start along with its line and column, which
+/// count from 1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Span {
+    pub offset: usize,
+    pub line: u32,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl From<parser::Span<'_>> for Span {
+    fn from(span: parser::Span) -> Self {
+        Self {
+            offset: span.offset,
+            line: span.line,
+            column: span.get_column(),
+            length: span.fragment.len(),
+        }
+    }
+}
+
+impl From<&Definition<Borrowed<'_>>> for Definition {
+    fn from(definition: &Definition<Borrowed>) -> Self {
+        Self::new(
+            (&definition.variable).into(),
+            (&definition.expression).into(),
+        )
+    }
+}
+
+impl From<&Expression<Borrowed<'_>>> for Expression {
+    fn from(expression: &Expression<Borrowed>) -> Self {
+        use ExpressionType as E;
+
+        let boxed = |e: &Expression<Borrowed>| Box::new(Self::from(e));
+
+        let owned = match &expression.expression {
+            E::Abstraction(v, e) => E::Abstraction(v.into(), boxed(e)),
+            E::Application(f, a) => E::Application(boxed(f), boxed(a)),
+            E::Boolean(b) => E::Boolean(*b),
+            E::Class(c) => E::Class(c.into()),
+            E::Conditional(c, t, o) => E::Conditional(boxed(c), boxed(t), boxed(o)),
+            E::Constructor(name, k) => E::Constructor((*name).into(), k.clone()),
+            E::Conversion(k, e) => E::Conversion(k.clone(), boxed(e)),
+            E::Date(d) => E::Date(d.clone()),
+            E::Decimal(d) => E::Decimal(*d),
+            E::Difference(e, k) => E::Difference(boxed(e), k.clone()),
+            E::Duration(d) => E::Duration(*d),
+            E::Field(name, i, e, k) => E::Field((*name).into(), *i, boxed(e), k.clone()),
+            E::Int(i) => E::Int(*i),
+            E::Is(name, e) => E::Is((*name).into(), boxed(e)),
+            E::Observable(o) => E::Observable(o.into()),
+            E::Projection(e, c, k) => E::Projection(boxed(e), c.into(), k.clone()),
+            E::Record(fields) => E::Record(
+                fields
+                    .iter()
+                    .map(|(name, e)| ((*name).into(), e.into()))
+                    .collect(),
+            ),
+            E::Round(r, e) => E::Round(*r, boxed(e)),
+            E::State(s) => E::State(s.into()),
+            E::Tuple(elements) => E::Tuple(elements.iter().map(Self::from).collect()),
+            E::Variable(v) => E::Variable(v.into()),
+            E::Word(w) => E::Word(*w),
+        };
+
+        Self::new(owned, expression.span.map(Span::from))
+    }
+}
+
+impl From<&Variable<Borrowed<'_>>> for Variable {
+    fn from(variable: &Variable<Borrowed>) -> Self {
+        Self::new(
+            variable.name,
+            variable.kind.clone(),
+            variable.span.map(Span::from),
+        )
+    }
+}
+
+impl From<&Component<Borrowed<'_>>> for Component {
+    fn from(component: &Component<Borrowed>) -> Self {
+        match component {
+            Component::Index(index) => Self::Index(*index),
+            Component::Name(name) => Self::Name((*name).into()),
+        }
+    }
+}
+
+impl From<&Observable<Borrowed<'_>>> for Observable {
+    fn from(observable: &Observable<Borrowed>) -> Self {
+        match observable {
+            Observable::IsParty => Self::IsParty,
+            Observable::IsCounterparty => Self::IsCounterparty,
+            Observable::Konst(e) => Expression::from(e.as_ref()).into(),
+        }
+    }
+}
+
+impl From<&Class<Borrowed<'_>>> for Class {
+    fn from(class: &Class<Borrowed>) -> Self {
+        use {Comparable as C, Equatable as E, Negatable as G, Numerable as N};
+
+        let boxed = |e: &Expression<Borrowed>| Box::new(Expression::from(e));
+
+        match class {
+            Class::Comparable(c) => Self::Comparable(match c {
+                C::Greater(l, r) => C::Greater(boxed(l), boxed(r)),
+                C::Less(l, r) => C::Less(boxed(l), boxed(r)),
+                C::GreaterEqual(l, r) => C::GreaterEqual(boxed(l), boxed(r)),
+                C::LessEqual(l, r) => C::LessEqual(boxed(l), boxed(r)),
+            }),
+            Class::Equatable(e) => Self::Equatable(match e {
+                E::Equal(l, r) => E::Equal(boxed(l), boxed(r)),
+                E::NotEqual(l, r) => E::NotEqual(boxed(l), boxed(r)),
+            }),
+            Class::Negatable(G::Negate(e)) => Self::Negatable(G::Negate(boxed(e))),
+            Class::Numerable(n) => Self::Numerable(match n {
+                N::Add(l, r) => N::Add(boxed(l), boxed(r)),
+                N::Subtract(l, r) => N::Subtract(boxed(l), boxed(r)),
+                N::Multiply(l, r) => N::Multiply(boxed(l), boxed(r)),
+                N::Divide(l, r) => N::Divide(boxed(l), boxed(r)),
+            }),
+        }
+    }
+}
+
+impl From<&State<Borrowed<'_>>> for State {
+    fn from(state: &State<Borrowed>) -> Self {
+        let mut owned = Self::default();
+
+        for transition in state.transitions() {
+            owned.add_transition(transition.into());
+        }
+
+        owned
+    }
+}
+
+impl From<&Transition<Borrowed<'_>>> for Transition {
+    fn from(transition: &Transition<Borrowed>) -> Self {
+        let mut owned = Self::default();
+
+        for condition in transition.conditions() {
+            owned.add_condition(condition.into());
+        }
+        for effect in transition.effects().into_iter().rev() {
+            owned.add_effect(effect.into());
+        }
+        owned.set_next(transition.next().into());
+
+        owned
+    }
+}
+
+impl From<&Effect<Borrowed<'_>>> for Effect {
+    fn from(effect: &Effect<Borrowed>) -> Self {
+        match effect {
+            Effect::Flip => Self::Flip,
+            Effect::Scale(e) => Self::Scale(e.into()),
+            Effect::Spawn(e) => Self::Spawn(e.into()),
+            Effect::Withdraw => Self::Withdraw,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Kind, parser::contract};
+
+    #[test]
+    fn owned_definitions() {
+        let definitions = contract("main =\n  give (scale (konst 2) one)").unwrap();
+        let main = definitions.iter().find(|d| &*d.variable.name == "main");
+        let expression = &main.unwrap().expression;
+
+        let span = expression.span.unwrap();
+        assert_eq!((span.line, span.column), (2, 3));
+        assert_eq!(*Kind::simplify(expression.kind()), Kind::State);
+
+        let give = definitions.iter().find(|d| &*d.variable.name == "give");
+        let state = match &give.unwrap().expression.expression {
+            ExpressionType::Abstraction(_, body) => match &body.expression {
+                ExpressionType::State(state) => state.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert!(matches!(
+            state.transitions()[0].effects()[..],
+            [Effect::Flip]
+        ));
+    }
+}
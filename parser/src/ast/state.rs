@@ -1,16 +1,20 @@
-use super::{Expression, ExpressionType};
+use super::{Expression, ExpressionType, Owned, Source};
 
 #[derive(Default, Clone, Debug)]
-pub struct State<'a> {
-    transitions: Vec<Transition<'a>>,
+pub struct State<S: Source = Owned> {
+    transitions: Vec<Transition<S>>,
 }
 
-impl<'a> State<'a> {
-    pub fn transitions(&self) -> &[Transition<'a>] {
+impl<S: Source> State<S> {
+    pub fn transitions(&self) -> &[Transition<S>] {
         &self.transitions
     }
 
-    pub fn add_transition(&mut self, transition: Transition<'a>) -> &mut Self {
+    pub fn transitions_mut(&mut self) -> &mut [Transition<S>] {
+        &mut self.transitions
+    }
+
+    pub fn add_transition(&mut self, transition: Transition<S>) -> &mut Self {
         self.transitions.push(transition);
         self
     }
@@ -21,13 +25,13 @@ impl<'a> State<'a> {
 }
 
 #[derive(Clone, Debug)]
-pub struct Transition<'a> {
-    conditions: Vec<Expression<'a>>,
-    effects: Vec<Effect<'a>>,
-    next: Expression<'a>,
+pub struct Transition<S: Source = Owned> {
+    conditions: Vec<Expression<S>>,
+    effects: Vec<Effect<S>>,
+    next: Expression<S>,
 }
 
-impl Default for Transition<'_> {
+impl<S: Source> Default for Transition<S> {
     fn default() -> Self {
         Self {
             conditions: Default::default(),
@@ -37,39 +41,53 @@ impl Default for Transition<'_> {
     }
 }
 
-impl<'a> Transition<'a> {
-    pub fn conditions(&self) -> &[Expression<'a>] {
+impl<S: Source> Transition<S> {
+    pub fn conditions(&self) -> &[Expression<S>] {
         &self.conditions
     }
 
-    pub fn add_condition(&mut self, condition: Expression<'a>) -> &mut Self {
+    pub fn conditions_mut(&mut self) -> &mut [Expression<S>] {
+        &mut self.conditions
+    }
+
+    pub fn add_condition(&mut self, condition: Expression<S>) -> &mut Self {
         self.conditions.push(condition);
         self
     }
 
-    pub fn effects(&self) -> Vec<&Effect<'a>> {
+    /// The effects in the order in which they take effect, which is the reverse of the order in
+    /// which they were added.
+    pub fn effects(&self) -> Vec<&Effect<S>> {
         self.effects.iter().rev().collect()
     }
 
-    pub fn add_effect(&mut self, effect: Effect<'a>) -> &mut Self {
+    pub fn effects_mut(&mut self) -> Vec<&mut Effect<S>> {
+        self.effects.iter_mut().rev().collect()
+    }
+
+    pub fn add_effect(&mut self, effect: Effect<S>) -> &mut Self {
         self.effects.push(effect);
         self
     }
 
-    pub fn next(&self) -> &Expression<'a> {
+    pub fn next(&self) -> &Expression<S> {
         &self.next
     }
 
-    pub fn set_next(&mut self, next: Expression<'a>) -> &mut Self {
+    pub fn next_mut(&mut self) -> &mut Expression<S> {
+        &mut self.next
+    }
+
+    pub fn set_next(&mut self, next: Expression<S>) -> &mut Self {
         self.next = next;
         self
     }
 }
 
 #[derive(Clone, Debug)]
-pub enum Effect<'a> {
+pub enum Effect<S: Source = Owned> {
     Flip,
-    Scale(Expression<'a>),
-    Spawn(Expression<'a>),
+    Scale(Expression<S>),
+    Spawn(Expression<S>),
     Withdraw,
 }
@@ -0,0 +1,247 @@
+//! A read-only walk over the AST, whether its names and spans are owned or borrowed from the
+//! source. Each method of [`Visit`] defaults to the function of the same name in this module,
+//! which visits the children of the node, so an implementation only overrides the nodes it cares
+//! about and calls back into the function to keep walking.
+
+use super::{
+    state::{Effect, Transition},
+    Class, Definition, Expression, ExpressionType, Kind, Observable, Owned, Source, State,
+    Variable,
+};
+use std::rc::Rc;
+
+pub trait Visit<'a, S: Source = Owned> {
+    fn visit_definition(&mut self, definition: &'a Definition<S>) {
+        visit_definition(self, definition)
+    }
+
+    fn visit_expression(&mut self, expression: &'a Expression<S>) {
+        visit_expression(self, expression)
+    }
+
+    fn visit_abstraction(&mut self, argument: &'a Variable<S>, body: &'a Expression<S>) {
+        visit_abstraction(self, argument, body)
+    }
+
+    fn visit_variable(&mut self, _variable: &'a Variable<S>) {}
+
+    fn visit_class(&mut self, class: &'a Class<S>) {
+        visit_class(self, class)
+    }
+
+    fn visit_observable(&mut self, observable: &'a Observable<S>) {
+        visit_observable(self, observable)
+    }
+
+    fn visit_state(&mut self, state: &'a State<S>) {
+        visit_state(self, state)
+    }
+
+    fn visit_transition(&mut self, transition: &'a Transition<S>) {
+        visit_transition(self, transition)
+    }
+
+    fn visit_condition(&mut self, condition: &'a Expression<S>) {
+        visit_condition(self, condition)
+    }
+
+    fn visit_effect(&mut self, effect: &'a Effect<S>) {
+        visit_effect(self, effect)
+    }
+
+    /// Kinds are only walked when an implementation asks for them, since every expression has
+    /// one.
+    fn visit_kind(&mut self, kind: Rc<Kind>) {
+        visit_kind(self, kind)
+    }
+}
+
+pub fn visit_definition<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    definition: &'a Definition<S>,
+) {
+    visitor.visit_variable(&definition.variable);
+    visitor.visit_expression(&definition.expression);
+}
+
+pub fn visit_expression<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    expression: &'a Expression<S>,
+) {
+    match &expression.expression {
+        ExpressionType::Abstraction(argument, body) => visitor.visit_abstraction(argument, body),
+        ExpressionType::Application(function, argument) => {
+            visitor.visit_expression(function);
+            visitor.visit_expression(argument);
+        }
+        ExpressionType::Class(class) => visitor.visit_class(class),
+        ExpressionType::Conditional(condition, then, otherwise) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then);
+            visitor.visit_expression(otherwise);
+        }
+        ExpressionType::Conversion(_, e)
+        | ExpressionType::Difference(e, _)
+        | ExpressionType::Field(_, _, e, _)
+        | ExpressionType::Is(_, e)
+        | ExpressionType::Projection(e, _, _)
+        | ExpressionType::Round(_, e) => visitor.visit_expression(e),
+        ExpressionType::Observable(observable) => visitor.visit_observable(observable),
+        ExpressionType::Record(fields) => {
+            for (_, e) in fields {
+                visitor.visit_expression(e);
+            }
+        }
+        ExpressionType::State(state) => visitor.visit_state(state),
+        ExpressionType::Tuple(elements) => {
+            for e in elements {
+                visitor.visit_expression(e);
+            }
+        }
+        ExpressionType::Variable(variable) => visitor.visit_variable(variable),
+        ExpressionType::Boolean(_)
+        | ExpressionType::Constructor(_, _)
+        | ExpressionType::Date(_)
+        | ExpressionType::Decimal(_)
+        | ExpressionType::Duration(_)
+        | ExpressionType::Int(_)
+        | ExpressionType::Word(_) => {}
+    }
+}
+
+pub fn visit_abstraction<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    argument: &'a Variable<S>,
+    body: &'a Expression<S>,
+) {
+    visitor.visit_variable(argument);
+    visitor.visit_expression(body);
+}
+
+pub fn visit_class<'a, S: Source, V: Visit<'a, S> + ?Sized>(visitor: &mut V, class: &'a Class<S>) {
+    for operand in class.operands() {
+        visitor.visit_expression(operand);
+    }
+}
+
+pub fn visit_observable<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    observable: &'a Observable<S>,
+) {
+    if let Observable::Konst(e) = observable {
+        visitor.visit_expression(e);
+    }
+}
+
+pub fn visit_state<'a, S: Source, V: Visit<'a, S> + ?Sized>(visitor: &mut V, state: &'a State<S>) {
+    for transition in state.transitions() {
+        visitor.visit_transition(transition);
+    }
+}
+
+/// Visits the conditions, then the effects in the order in which they take effect, then the next
+/// state.
+pub fn visit_transition<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    transition: &'a Transition<S>,
+) {
+    for condition in transition.conditions() {
+        visitor.visit_condition(condition);
+    }
+    for effect in transition.effects() {
+        visitor.visit_effect(effect);
+    }
+    visitor.visit_expression(transition.next());
+}
+
+pub fn visit_condition<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    condition: &'a Expression<S>,
+) {
+    visitor.visit_expression(condition);
+}
+
+pub fn visit_effect<'a, S: Source, V: Visit<'a, S> + ?Sized>(
+    visitor: &mut V,
+    effect: &'a Effect<S>,
+) {
+    match effect {
+        Effect::Scale(e) | Effect::Spawn(e) => visitor.visit_expression(e),
+        Effect::Flip | Effect::Withdraw => {}
+    }
+}
+
+/// Visits the kinds which make up a kind, looking through resolved kinds and aliases. The fields
+/// of data types aren't visited, since a data type is declared apart from the kinds which use it.
+pub fn visit_kind<'a, S: Source, V: Visit<'a, S> + ?Sized>(visitor: &mut V, kind: Rc<Kind>) {
+    match kind.as_ref() {
+        Kind::Abstraction(from, to) => {
+            visitor.visit_kind(from.clone());
+            visitor.visit_kind(to.clone());
+        }
+        Kind::Alias(_, k) | Kind::Observable(k) => visitor.visit_kind(k.clone()),
+        Kind::Record(fields) => {
+            for (_, k) in fields.iter() {
+                visitor.visit_kind(k.clone());
+            }
+        }
+        Kind::Tuple(kinds) => {
+            for k in kinds.iter() {
+                visitor.visit_kind(k.clone());
+            }
+        }
+        Kind::Unresolved(k) => {
+            let resolved = k.borrow().clone();
+            if let Some(k) = resolved {
+                visitor.visit_kind(k);
+            }
+        }
+        Kind::Boolean
+        | Kind::Data(_)
+        | Kind::Date
+        | Kind::Decimal
+        | Kind::Duration
+        | Kind::Int
+        | Kind::State
+        | Kind::Word => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::contract;
+
+    #[derive(Default)]
+    struct Effects(Vec<&'static str>);
+
+    impl Visit<'_> for Effects {
+        fn visit_effect(&mut self, effect: &Effect) {
+            self.0.push(match effect {
+                Effect::Flip => "flip",
+                Effect::Scale(_) => "scale",
+                Effect::Spawn(_) => "spawn",
+                Effect::Withdraw => "withdraw",
+            });
+            visit_effect(self, effect);
+        }
+    }
+
+    #[test]
+    fn visit_effects() {
+        let definitions = contract("main = give (scale (konst 2) one)").unwrap();
+        let main = definitions.iter().find(|d| &*d.variable.name == "main");
+
+        let mut effects = Effects::default();
+        effects.visit_definition(main.unwrap());
+        assert!(effects.0.is_empty());
+
+        let mut effects = Effects::default();
+        for definition in &definitions {
+            effects.visit_definition(definition);
+        }
+        for effect in ["flip", "scale", "withdraw"] {
+            assert!(effects.0.contains(&effect));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! A walk over the AST which may change it in place, with the same layout as
+//! [`visit`](super::visit).
+
+use std::rc::Rc;
+
+use super::{
+    state::{Effect, Transition},
+    Class, Definition, Expression, ExpressionType, Observable, Owned, Source, State, Variable,
+};
+
+pub trait VisitMut<S: Source = Owned> {
+    fn visit_definition_mut(&mut self, definition: &mut Definition<S>) {
+        visit_definition_mut(self, definition)
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression<S>) {
+        visit_expression_mut(self, expression)
+    }
+
+    fn visit_abstraction_mut(&mut self, argument: &mut Variable<S>, body: &mut Expression<S>) {
+        visit_abstraction_mut(self, argument, body)
+    }
+
+    fn visit_variable_mut(&mut self, _variable: &mut Variable<S>) {}
+
+    fn visit_class_mut(&mut self, class: &mut Class<S>) {
+        visit_class_mut(self, class)
+    }
+
+    fn visit_observable_mut(&mut self, observable: &mut Observable<S>) {
+        visit_observable_mut(self, observable)
+    }
+
+    fn visit_state_mut(&mut self, state: &mut State<S>) {
+        visit_state_mut(self, state)
+    }
+
+    fn visit_transition_mut(&mut self, transition: &mut Transition<S>) {
+        visit_transition_mut(self, transition)
+    }
+
+    fn visit_condition_mut(&mut self, condition: &mut Expression<S>) {
+        visit_condition_mut(self, condition)
+    }
+
+    fn visit_effect_mut(&mut self, effect: &mut Effect<S>) {
+        visit_effect_mut(self, effect)
+    }
+}
+
+pub fn visit_definition_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    definition: &mut Definition<S>,
+) {
+    visitor.visit_variable_mut(&mut definition.variable);
+    visitor.visit_expression_mut(&mut definition.expression);
+}
+
+pub fn visit_expression_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    expression: &mut Expression<S>,
+) {
+    match &mut expression.expression {
+        ExpressionType::Abstraction(argument, body) => {
+            visitor.visit_abstraction_mut(argument, body)
+        }
+        ExpressionType::Application(function, argument) => {
+            visitor.visit_expression_mut(function);
+            visitor.visit_expression_mut(argument);
+        }
+        ExpressionType::Class(class) => visitor.visit_class_mut(class),
+        ExpressionType::Conditional(condition, then, otherwise) => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_expression_mut(then);
+            visitor.visit_expression_mut(otherwise);
+        }
+        ExpressionType::Conversion(_, e)
+        | ExpressionType::Difference(e, _)
+        | ExpressionType::Field(_, _, e, _)
+        | ExpressionType::Is(_, e)
+        | ExpressionType::Projection(e, _, _)
+        | ExpressionType::Round(_, e) => visitor.visit_expression_mut(e),
+        ExpressionType::Observable(observable) => visitor.visit_observable_mut(observable),
+        ExpressionType::Record(fields) => {
+            for (_, e) in fields {
+                visitor.visit_expression_mut(e);
+            }
+        }
+        ExpressionType::State(state) => visitor.visit_state_mut(state),
+        ExpressionType::Tuple(elements) => {
+            for e in elements {
+                visitor.visit_expression_mut(e);
+            }
+        }
+        ExpressionType::Variable(variable) => visitor.visit_variable_mut(variable),
+        ExpressionType::Boolean(_)
+        | ExpressionType::Constructor(_, _)
+        | ExpressionType::Date(_)
+        | ExpressionType::Decimal(_)
+        | ExpressionType::Duration(_)
+        | ExpressionType::Int(_)
+        | ExpressionType::Word(_) => {}
+    }
+}
+
+pub fn visit_abstraction_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    argument: &mut Variable<S>,
+    body: &mut Expression<S>,
+) {
+    visitor.visit_variable_mut(argument);
+    visitor.visit_expression_mut(body);
+}
+
+pub fn visit_class_mut<S: Source, V: VisitMut<S> + ?Sized>(visitor: &mut V, class: &mut Class<S>) {
+    for operand in class.operands_mut() {
+        visitor.visit_expression_mut(operand);
+    }
+}
+
+/// A constant shared with other expressions is copied before it is visited.
+pub fn visit_observable_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    observable: &mut Observable<S>,
+) {
+    if let Observable::Konst(e) = observable {
+        visitor.visit_expression_mut(Rc::make_mut(e));
+    }
+}
+
+pub fn visit_state_mut<S: Source, V: VisitMut<S> + ?Sized>(visitor: &mut V, state: &mut State<S>) {
+    for transition in state.transitions_mut() {
+        visitor.visit_transition_mut(transition);
+    }
+}
+
+pub fn visit_transition_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    transition: &mut Transition<S>,
+) {
+    for condition in transition.conditions_mut() {
+        visitor.visit_condition_mut(condition);
+    }
+    for effect in transition.effects_mut() {
+        visitor.visit_effect_mut(effect);
+    }
+    visitor.visit_expression_mut(transition.next_mut());
+}
+
+pub fn visit_condition_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    condition: &mut Expression<S>,
+) {
+    visitor.visit_expression_mut(condition);
+}
+
+pub fn visit_effect_mut<S: Source, V: VisitMut<S> + ?Sized>(
+    visitor: &mut V,
+    effect: &mut Effect<S>,
+) {
+    match effect {
+        Effect::Scale(e) | Effect::Spawn(e) => visitor.visit_expression_mut(e),
+        Effect::Flip | Effect::Withdraw => {}
+    }
+}
@@ -0,0 +1,138 @@
+//! A concrete syntax tree, which keeps every character of a program, including its comments and
+//! whitespace, so that printing the tree gives back exactly the source it was parsed from.
+
+use super::parser::Span;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    Whitespace,
+    Newline,
+    Comment,
+    Keyword,
+    Identifier,
+    // Names which start with a capital, such as modules, kinds and constructors.
+    Name,
+    Literal,
+    Symbol,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub span: Span<'a>,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(kind: TokenKind, span: Span<'a>) -> Self {
+        Token { kind, span }
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.span.fragment
+    }
+
+    /// Whether the token is whitespace or a comment, which the grammar ignores.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment
+        )
+    }
+
+    /// Whether the token is the given keyword or symbol.
+    pub fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenKind::Keyword | TokenKind::Symbol) && self.text() == text
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NodeKind {
+    Module,
+    Header,
+    Import,
+    Alias,
+    Data,
+    Constructor,
+    Signature,
+    Binding,
+    Where,
+    Block,
+    Kind,
+    Lambda,
+    Let,
+    Conditional,
+    Case,
+    Alternative,
+    Pattern,
+    Binary,
+    Negation,
+    Application,
+    Projection,
+    Parentheses,
+    Tuple,
+    Record,
+    Field,
+    Name,
+    Literal,
+}
+
+#[derive(Clone, Debug)]
+pub enum Element<'a> {
+    Node(Node<'a>),
+    Token(Token<'a>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Node<'a> {
+    pub kind: NodeKind,
+    pub children: Vec<Element<'a>>,
+}
+
+impl<'a> Node<'a> {
+    pub fn new(kind: NodeKind) -> Self {
+        Node {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// The tokens of the node itself which aren't trivia, leaving out those of its children.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Token(token) if !token.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    /// Every token of the node and its children, in the order they appear in the source.
+    pub fn descendants(&self) -> Vec<&Token<'a>> {
+        let mut tokens = Vec::new();
+
+        for child in &self.children {
+            match child {
+                Element::Node(node) => tokens.extend(node.descendants()),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+}
+
+impl Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.descendants() {
+            write!(f, "{}", token.text())?;
+        }
+
+        Ok(())
+    }
+}
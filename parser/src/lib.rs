@@ -1,4 +1,5 @@
 pub mod ast;
+pub mod cst;
 pub mod parser;
 
-mod hash_count;
+mod occurrences;
@@ -0,0 +1,84 @@
+use crate::ast::borrowed::Variable;
+use std::collections::HashMap;
+
+// Each use of a variable has its own kind, since uses of polymorphic definitions can be
+// instantiated with different kinds.
+#[derive(Clone, Debug, Default)]
+pub struct Occurrences<'a>(HashMap<&'a str, Vec<Variable<'a>>>);
+
+impl<'a> Occurrences<'a> {
+    #[cfg(test)]
+    pub fn count(&self, name: &str) -> usize {
+        self.0.get(name).map(Vec::len).unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, variable: Variable<'a>) {
+        self.0.entry(variable.name).or_default().push(variable);
+    }
+
+    // Qualified uses always refer to top-level definitions, so they are left for them rather
+    // than taken by local bindings of the same name.
+    pub fn take(&mut self, name: &str) -> Vec<Variable<'a>> {
+        let variables = self.0.remove(name).unwrap_or_default();
+        let (qualified, unqualified): (Vec<_>, Vec<_>) = variables
+            .into_iter()
+            .partition(|variable| variable.qualifier().is_some());
+
+        if let Some(variable) = qualified.first() {
+            self.0.insert(variable.name, qualified);
+        }
+
+        unqualified
+    }
+
+    pub fn extend(&mut self, other: Self) {
+        for (name, variables) in other.0 {
+            self.0.entry(name).or_default().extend(variables);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Variable<'a>> {
+        self.0.values().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Span;
+
+    fn variable(span: &str) -> Variable<'_> {
+        let name = span.rsplit('.').next().unwrap();
+        Variable::new(name, Default::default(), Some(Span::new(span)))
+    }
+
+    #[test]
+    fn occurrences_take() {
+        let mut occurrences = Occurrences::default();
+        occurrences.insert(variable("x"));
+        occurrences.insert(variable("x"));
+        occurrences.insert(variable("Bonds.x"));
+        occurrences.insert(variable("y"));
+        assert_eq!(occurrences.count("x"), 3);
+        assert_eq!(occurrences.iter().count(), 4);
+
+        assert_eq!(occurrences.take("x").len(), 2);
+        assert_eq!(occurrences.count("x"), 1);
+        assert!(occurrences.take("x").is_empty());
+        assert!(occurrences.take("z").is_empty());
+    }
+
+    #[test]
+    fn occurrences_extend() {
+        let mut occurrences = Occurrences::default();
+        occurrences.insert(variable("x"));
+
+        let mut other = Occurrences::default();
+        other.insert(variable("x"));
+        other.insert(variable("y"));
+
+        occurrences.extend(other);
+        assert_eq!(occurrences.count("x"), 2);
+        assert_eq!(occurrences.count("y"), 1);
+    }
+}
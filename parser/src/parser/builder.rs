@@ -1,73 +1,109 @@
 use super::{
-    context::Context,
+    context::{Access, Arithmetic, Context, Instance, Match},
     error::SprintError,
-    primitive::{self, PRIMITIVES},
+    generalize, primitive,
     unify::Unify,
     Error, Result, Span,
 };
-use crate::ast::{Definition, Expression, ExpressionType, Kind, Variable};
+use crate::ast::{
+    borrowed::{
+        Class, Comparable, Component, Definition, Equatable, Expression, ExpressionType, Negatable,
+        Numerable, Variable,
+    },
+    Date, Kind,
+};
 use nom::Err;
-use std::rc::Rc;
+use std::{iter, rc::Rc, result};
 
-pub fn program<'a>(definitions: Vec<Context<'a, Expression<'a>>>) -> Result<'a, Context<'a, ()>> {
-    let mut context = Context::from(());
+pub type Alternative<'a> = (Pattern<'a>, Context<'a, Expression<'a>>);
 
-    context.unify(primitive::zero()).unwrap();
-    context.unify(primitive::one()).unwrap();
-    context.unify(primitive::give()).unwrap();
-    context.unify(primitive::and()).unwrap();
-    context.unify(primitive::or()).unwrap();
-    context.unify(primitive::before()).unwrap();
-    context.unify(primitive::after()).unwrap();
-    context.unify(primitive::scale()).unwrap();
-    context.unify(primitive::anytime()).unwrap();
-
-    context = definitions.into_iter().fold(Ok(context), unify_context)?;
-    context
-        .unify(signature(Span::new("main"), Kind::State).unwrap())
-        .map_err(Err::Error)?;
-
-    for (variable, _) in &context.variables {
-        if !context.definitions.contains_key(variable.name) {
-            if variable.name == "main" {
-                return Err(Err::Error(Error::from_sprint_error(
-                    SprintError::UndefinedMainError,
-                    None,
-                )));
-            }
-            return Err(Err::Error(Error::from_sprint_error(
-                SprintError::UnknownIdentifierError(
-                    variable.name,
-                    Rc::make_mut(&mut variable.kind.clone()).clone(),
-                ),
-                variable.span,
-            )));
-        }
-    }
+pub enum Pattern<'a> {
+    Wildcard,
+    Literal(Expression<'a>),
+    /// A constructor, along with the names which its fields are bound to.
+    Constructor(Span<'a>, Vec<Span<'a>>),
+    /// The names which the fields of a record are bound to.
+    Record(Vec<(Span<'a>, Span<'a>)>),
+    /// The names which the elements of a tuple are bound to.
+    Tuple(Vec<Span<'a>>),
+}
 
-    Ok(context)
+pub type Binding<'a> = (Span<'a>, Vec<Span<'a>>, Context<'a, Expression<'a>>);
+
+pub fn program<'a>(
+    definitions: Vec<Context<'a, Expression<'a>>>,
+    broken: &[&'a str],
+) -> result::Result<Context<'a, ()>, Vec<Error<'a>>> {
+    let primitives = vec![
+        primitive::zero(),
+        primitive::one(),
+        primitive::give(),
+        primitive::and(),
+        primitive::or(),
+        primitive::before(),
+        primitive::after(),
+        primitive::scale(),
+        primitive::anytime(),
+        primitive::konst(),
+        primitive::to_int(),
+        primitive::to_word(),
+        primitive::to_decimal(),
+        primitive::floor(),
+        primitive::ceiling(),
+        primitive::round(),
+        primitive::scale_decimal(),
+    ];
+    let main = signature(Span::new("main"), Kind::State.into()).unwrap();
+
+    let definitions = primitives
+        .into_iter()
+        .chain(definitions)
+        .chain(iter::once(main));
+
+    generalize::program(definitions, broken)
 }
 
-pub fn signature(identifier: Span, kind: Kind) -> Result<Context<Expression>> {
-    let variable = Variable::new(identifier.fragment, kind.into(), Some(identifier));
+pub fn signature(identifier: Span, kind: Rc<Kind>) -> Result<Context<Expression>> {
+    let variable = Variable::new(identifier.fragment, kind, Some(identifier));
 
     let mut context = Context::from(Expression::new(
         ExpressionType::Variable(variable.clone()),
         Some(identifier),
     ));
-    context.variables.insert_without_increment(variable);
+    context.signatures.insert(identifier.fragment, variable);
 
     Ok(context)
 }
 
 pub fn definition<'a>(
     identifier: Span<'a>,
+    arguments: Vec<Span<'a>>,
+    expression: Context<'a, Expression<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (expression, definition) = abstraction(arguments, expression)?.clear();
+    let variable = Variable::new(identifier.fragment, definition.kind(), Some(identifier));
+
+    let mut context = Context::from(Expression::new(
+        ExpressionType::Variable(variable.clone()),
+        Some(identifier),
+    ));
+
+    let definition = Definition::new(variable.clone(), definition);
+
+    context.definitions.insert(identifier.fragment, definition);
+
+    context.unify(expression).map_err(Err::Error)?;
+
+    Ok(context)
+}
+
+pub fn abstraction<'a>(
     arguments: Vec<Span<'a>>,
     mut expression: Context<'a, Expression<'a>>,
 ) -> Result<'a, Context<'a, Expression<'a>>> {
     for argument in arguments.iter().rev() {
         let argument = Variable::new(argument.fragment, Default::default(), Some(*argument));
-        let argument = expression.variables.take(&argument).unwrap_or(argument);
+        bind(&argument, expression.variables.take(argument.name))?;
 
         expression = expression.map(|expression| {
             Expression::new(
@@ -77,22 +113,72 @@ pub fn definition<'a>(
         });
     }
 
-    let (expression, definition) = expression.clear();
-    let variable = Variable::new(identifier.fragment, definition.kind(), Some(identifier));
+    Ok(expression)
+}
 
-    let mut context = Context::from(Expression::new(
-        ExpressionType::Variable(variable.clone()),
-        Some(identifier),
-    ));
+// Only top-level definitions are generalized, so arguments and local definitions have the same
+// kind wherever they are used.
+fn bind<'a>(variable: &Variable<'a>, uses: Vec<Variable<'a>>) -> Result<'a, ()> {
+    // Arguments start out with an unresolved kind, which is then inferred from their first use.
+    let mut inferred = match Kind::simplify(variable.kind.clone()).as_ref() {
+        Kind::Unresolved(_) => None,
+        _ => variable.span.map(|span| (span, "bound")),
+    };
 
-    let definition = Definition::new(variable.clone(), definition);
+    for used in uses {
+        if let Err(e) = variable.kind.clone().unify(used.kind.clone()) {
+            let sprint_error = SprintError::TypeError(used.name, e.sprint_error.unwrap().into());
+            let mut error = Error::from_sprint_error(sprint_error, used.span);
 
-    context.definitions.insert(identifier.fragment, definition);
-    context.variables.insert(variable);
+            if let Some((span, reason)) = inferred {
+                let label = format!(
+                    "\"{}\" was {} with kind {} here",
+                    used.name, reason, variable.kind
+                );
+                error = error.with_label(Some(span), label);
+            }
 
-    context.unify(expression).map_err(Err::Error)?;
+            return Err(Err::Error(error));
+        }
 
-    Ok(context)
+        inferred = inferred.or_else(|| used.span.map(|span| (span, "first used")));
+    }
+
+    Ok(())
+}
+
+// Local bindings are desugared into applications of abstractions, so "let x = 1 in x" becomes
+// "(\x -> x) 1". Each binding is in scope in the bindings after it and in the expression.
+pub fn local<'a>(
+    bindings: Vec<Binding<'a>>,
+    expression: Context<'a, Expression<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    bindings
+        .into_iter()
+        .rev()
+        .try_fold(expression, |expression, (identifier, arguments, value)| {
+            let (mut context, value) = abstraction(arguments, value)?.clear();
+            let (mut expression_context, expression) = expression.clear();
+
+            let variable = Variable::new(identifier.fragment, value.kind(), Some(identifier));
+
+            bind(&variable, expression_context.variables.take(variable.name))?;
+
+            context.unify(expression_context).map_err(Err::Error)?;
+
+            let span = expression.span;
+            let abstraction = Expression::new(
+                ExpressionType::Abstraction(variable, expression.into()),
+                span,
+            );
+
+            Ok(context.map(|_| {
+                Expression::new(
+                    ExpressionType::Application(abstraction.into(), value.into()),
+                    span,
+                )
+            }))
+        })
 }
 
 pub fn application<'a>(
@@ -100,30 +186,25 @@ pub fn application<'a>(
     arguments: Vec<Context<'a, Expression<'a>>>,
 ) -> Result<'a, Context<'a, Expression<'a>>> {
     let (contexts, arguments): (Vec<_>, Vec<_>) = arguments.into_iter().map(Context::clear).unzip();
-    let mut context = match PRIMITIVES.get(identifier.fragment) {
-        // Applications to primitives are handled here. Primitives cannot be
-        // implemented as abstractions in the AST as some require polymorphic
-        // kinds which is not currently supported.
-        Some(primitive) => primitive(arguments),
-        _ => {
-            let kind = arguments
-                .iter()
-                .rev()
-                .fold(Kind::default(), |kind, argument| {
-                    Kind::Abstraction(argument.kind(), kind.into())
-                });
-            let variable = Variable::new(identifier.fragment, kind.into(), Some(identifier));
-
-            let mut context = Context::from(Expression::new(
-                ExpressionType::Variable(variable.clone()),
-                Some(identifier),
-            ));
-            context.variables.insert(variable);
-            arguments
-                .into_iter()
-                .fold(Ok(context), map_arg_to_application)?
-        }
-    };
+    let kind = arguments
+        .iter()
+        .rev()
+        .fold(Kind::default(), |kind, argument| {
+            Kind::Abstraction(argument.kind(), kind.into())
+        });
+    // Qualified names are used by their unqualified names until modules are linked, when each use
+    // is resolved to the definition of its module.
+    let name = identifier.fragment.rsplit('.').next().unwrap_or_default();
+    let variable = Variable::new(name, kind.into(), Some(identifier));
+
+    let mut context = Context::from(Expression::new(
+        ExpressionType::Variable(variable.clone()),
+        Some(identifier),
+    ));
+    context.variables.insert(variable);
+    let mut context = arguments
+        .into_iter()
+        .fold(Ok(context), map_arg_to_application)?;
 
     for c in contexts {
         context.unify(c).map_err(Err::Error)?;
@@ -132,16 +213,630 @@ pub fn application<'a>(
     Ok(context)
 }
 
-fn unify_context<'a>(
-    context: Result<'a, Context<'a, ()>>,
-    definition: Context<'a, Expression<'a>>,
-) -> Result<'a, Context<'a, ()>> {
-    match context {
-        Err(_) => context,
-        Ok(mut context) => match context.unify(definition) {
-            Ok(_) => Ok(context),
-            Err(error) => Err(Err::Error(error)),
+pub fn conditional<'a>(
+    span: Span<'a>,
+    condition: Context<'a, Expression<'a>>,
+    then: Context<'a, Expression<'a>>,
+    otherwise: Context<'a, Expression<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (mut context, condition) = condition.clear();
+    let (then_context, then) = then.clear();
+    let (otherwise_context, otherwise) = otherwise.clear();
+    context.unify(then_context).map_err(Err::Error)?;
+    context.unify(otherwise_context).map_err(Err::Error)?;
+
+    // Nothing else can start with "if", so these are reported rather than backtracked over.
+    Rc::new(Kind::Boolean)
+        .unify(condition.kind())
+        .map_err(|error| Err::Failure(locate(error, condition.span.unwrap_or(span))))?;
+    then.kind().unify(otherwise.kind()).map_err(|error| {
+        let label = format!("the first branch has kind {}", then.kind());
+        Err::Failure(locate(error, otherwise.span.unwrap_or(span)).with_label(then.span, label))
+    })?;
+
+    Ok(context.map(|_| {
+        Expression::new(
+            ExpressionType::Conditional(condition.into(), then.into(), otherwise.into()),
+            Some(span),
+        )
+    }))
+}
+
+// Case expressions are desugared into nested conditionals, comparing the scrutinee with each
+// pattern in turn. Constructor patterns check which constructor made the scrutinee instead, and
+// bind its fields as though they were the arguments of a lambda.
+pub fn case<'a>(
+    span: Span<'a>,
+    scrutinee: Context<'a, Expression<'a>>,
+    alternatives: Vec<Alternative<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (mut context, scrutinee) = scrutinee.clear();
+    let mut branches: Vec<(Option<Expression>, Expression)> = Vec::new();
+    let mut booleans = Vec::new();
+    let mut constructors = Vec::new();
+
+    for (pattern, value) in alternatives {
+        let (condition, value) = match pattern {
+            Pattern::Wildcard => (None, value),
+            Pattern::Literal(pattern) => {
+                scrutinee.kind().unify(pattern.kind()).map_err(|error| {
+                    let label = format!("the scrutinee has kind {}", scrutinee.kind());
+                    let error = locate(error, pattern.span.unwrap_or(span));
+                    Err::Failure(error.with_label(scrutinee.span, label))
+                })?;
+
+                if let ExpressionType::Boolean(b) = pattern.expression {
+                    booleans.push(b);
+                }
+
+                let pattern_span = pattern.span;
+                let equal = Equatable::Equal(scrutinee.clone().into(), pattern.into());
+                let condition = Expression::new(Class::Equatable(equal).into(), pattern_span);
+
+                (Some(condition), value)
+            }
+            Pattern::Constructor(constructor, fields) => {
+                constructors.push(constructor.fragment);
+                let (condition, value) = destructure(&scrutinee, constructor, fields, value)?;
+
+                (Some(condition), value)
+            }
+            // Tuples and records only have one shape, so their patterns match anything.
+            Pattern::Record(mut fields) => {
+                fields.sort_by_key(|(field, _)| field.fragment);
+                let (names, bound): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+
+                let (kinds, value) = bind_parts(bound, value, |index, kind| {
+                    let component = Component::Name(names[index].fragment);
+                    let projection =
+                        ExpressionType::Projection(scrutinee.clone().into(), component, kind);
+                    Expression::new(projection, Some(names[index]))
+                })?;
+                let kinds = names
+                    .iter()
+                    .map(|name| name.fragment.to_string())
+                    .zip(kinds);
+
+                unify_pattern(&scrutinee, Kind::Record(kinds.collect()), span)?;
+                (None, value)
+            }
+            Pattern::Tuple(elements) => {
+                let spans = elements.clone();
+                let (kinds, value) = bind_parts(elements, value, |index, kind| {
+                    let component = Component::Index(index);
+                    let projection =
+                        ExpressionType::Projection(scrutinee.clone().into(), component, kind);
+                    Expression::new(projection, Some(spans[index]))
+                })?;
+
+                unify_pattern(&scrutinee, Kind::Tuple(kinds.into()), span)?;
+                (None, value)
+            }
+        };
+
+        let (value_context, value) = value.clear();
+        context.unify(value_context).map_err(Err::Error)?;
+
+        if let Some((_, first)) = branches.first() {
+            first.kind().unify(value.kind()).map_err(|error| {
+                let label = format!("the first alternative has kind {}", first.kind());
+                let error = locate(error, value.span.unwrap_or(span));
+                Err::Failure(error.with_label(first.span, label))
+            })?;
+        }
+
+        branches.push((condition, value));
+    }
+
+    // The last alternative is taken when no other pattern matches, so it must match anything.
+    // Which constructors there are is only known once the kind of the scrutinee is.
+    let exhaustive = match branches.last() {
+        Some((None, _)) => true,
+        Some(_) if !constructors.is_empty() => {
+            context.matches.push(Match {
+                span,
+                scrutinee: scrutinee.kind(),
+                constructors,
+            });
+            true
+        }
+        Some(_) => booleans.contains(&true) && booleans.contains(&false),
+        None => false,
+    };
+
+    // Nothing else can start with "case", so this is reported rather than backtracked over.
+    if !exhaustive {
+        return Err(Err::Failure(Error::from_sprint_error(
+            SprintError::NonExhaustivePatternsError,
+            Some(span),
+        )));
+    }
+
+    let mut branches = branches.into_iter().rev();
+    let (_, mut expression) = branches.next().unwrap();
+
+    for (condition, value) in branches {
+        expression = match condition {
+            // Any alternatives after a "_" can never be taken.
+            None => value,
+            Some(condition) => Expression::new(
+                ExpressionType::Conditional(condition.into(), value.into(), expression.into()),
+                Some(span),
+            ),
+        };
+    }
+
+    Ok(context.map(|_| expression))
+}
+
+// "case s of Fixed n -> v" becomes "if Fixed is s then (\n -> v) (field 0 of s)", and the
+// constructor is used with the kind of a function from the fields to the scrutinee.
+fn destructure<'a>(
+    scrutinee: &Expression<'a>,
+    constructor: Span<'a>,
+    fields: Vec<Span<'a>>,
+    value: Context<'a, Expression<'a>>,
+) -> Result<'a, (Expression<'a>, Context<'a, Expression<'a>>)> {
+    let (kinds, mut value) = bind_parts(fields, value, |index, kind| {
+        let field =
+            ExpressionType::Field(constructor.fragment, index, scrutinee.clone().into(), kind);
+        Expression::new(field, Some(constructor))
+    })?;
+
+    let kind = kinds
+        .into_iter()
+        .rev()
+        .fold(scrutinee.kind(), |kind, field| {
+            Kind::Abstraction(field, kind).into()
+        });
+    value
+        .variables
+        .insert(Variable::new(constructor.fragment, kind, Some(constructor)));
+
+    let condition = ExpressionType::Is(constructor.fragment, scrutinee.clone().into());
+    let condition = Expression::new(condition, Some(constructor));
+
+    Ok((condition, value))
+}
+
+// Binds names to the parts of a value as though they were the arguments of a lambda, which is
+// applied to each part in turn. Returns the kinds of the parts along with the application.
+fn bind_parts<'a>(
+    names: Vec<Span<'a>>,
+    value: Context<'a, Expression<'a>>,
+    part: impl Fn(usize, Rc<Kind>) -> Expression<'a>,
+) -> Result<'a, (Vec<Rc<Kind>>, Context<'a, Expression<'a>>)> {
+    let count = names.len();
+    let (context, lambda) = abstraction(names, value)?.clear();
+
+    let mut kinds = Vec::new();
+    let mut body = &lambda;
+
+    for _ in 0..count {
+        match &body.expression {
+            ExpressionType::Abstraction(name, e) => {
+                kinds.push(name.kind.clone());
+                body = e;
+            }
+            _ => unreachable!("there is a lambda for each name"),
+        }
+    }
+
+    let value = kinds
+        .iter()
+        .enumerate()
+        .fold(lambda, |value, (index, kind)| {
+            let part = part(index, kind.clone());
+            let span = value.span;
+
+            Expression::new(ExpressionType::Application(value.into(), part.into()), span)
+        });
+
+    Ok((kinds, context.map(|_| value)))
+}
+
+fn unify_pattern<'a>(scrutinee: &Expression<'a>, kind: Kind, span: Span<'a>) -> Result<'a, ()> {
+    scrutinee.kind().unify(kind.into()).map_err(|error| {
+        let label = format!("the scrutinee has kind {}", scrutinee.kind());
+        Err::Failure(locate(error, span).with_label(scrutinee.span, label))
+    })
+}
+
+pub fn tuple<'a>(
+    span: Span<'a>,
+    elements: Vec<Context<'a, Expression<'a>>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let mut context = Context::from(());
+    let mut expressions = Vec::new();
+
+    for element in elements {
+        let (element_context, element) = element.clear();
+        context.unify(element_context).map_err(Err::Error)?;
+        expressions.push(element);
+    }
+
+    Ok(context.map(|_| Expression::new(ExpressionType::Tuple(expressions), Some(span))))
+}
+
+// Fields are sorted by name, so that records with the same fields in a different order have
+// the same kind.
+pub fn record<'a>(
+    span: Span<'a>,
+    mut fields: Vec<(Span<'a>, Context<'a, Expression<'a>>)>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    fields.sort_by_key(|(name, _)| name.fragment);
+
+    for pair in fields.windows(2) {
+        let ((first, _), (name, _)) = (&pair[0], &pair[1]);
+
+        if first.fragment == name.fragment {
+            return Err(Err::Failure(
+                Error::from_sprint_error(
+                    SprintError::DuplicateDefinitionError(name.fragment),
+                    Some(*name),
+                )
+                .with_label(Some(*first), "first defined here"),
+            ));
+        }
+    }
+
+    let mut context = Context::from(());
+    let mut expressions = Vec::new();
+
+    for (name, field) in fields {
+        let (field_context, field) = field.clear();
+        context.unify(field_context).map_err(Err::Error)?;
+        expressions.push((name.fragment, field));
+    }
+
+    Ok(context.map(|_| Expression::new(ExpressionType::Record(expressions), Some(span))))
+}
+
+// Components can only be found once the kind of the tuple or record is known, which might not
+// be until the end of inference.
+pub fn projection<'a>(
+    value: Context<'a, Expression<'a>>,
+    component: Component<'a>,
+    span: Span<'a>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (mut context, value) = value.clear();
+    let access = Access {
+        span,
+        value: value.kind(),
+        component,
+        result: Kind::default().into(),
+    };
+
+    match Kind::simplify(value.kind()).as_ref() {
+        Kind::Unresolved(_) => context.accesses.push(access.clone()),
+        _ => self::access(&access).map_err(Err::Failure)?,
+    }
+
+    let projection = ExpressionType::Projection(value.into(), component, access.result);
+    Ok(context.map(|_| Expression::new(projection, Some(span))))
+}
+
+/// Gives a use of a component of a tuple or a record the kind of that component.
+pub fn access<'a>(access: &Access<'a>) -> result::Result<(), Error<'a>> {
+    let kind = Kind::simplify(access.value.clone());
+
+    let error = match Kind::component(kind.clone(), &access.component) {
+        Some((_, component)) => {
+            return component
+                .unify(access.result.clone())
+                .map_err(|error| locate(error, access.span))
+        }
+        None if matches!(kind.as_ref(), Kind::Unresolved(_)) => {
+            SprintError::UnknownComponentKindError(access.component)
+        }
+        None => {
+            let kind = Kind::resolve(access.value.clone()).as_ref().clone();
+            SprintError::MissingComponentError(access.component, kind)
+        }
+    };
+
+    Err(Error::from_sprint_error(error, Some(access.span)))
+}
+
+/// Reports the constructors of a data type which a case expression doesn't match.
+pub fn exhaustive<'a>(match_: &Match<'a>) -> result::Result<(), Error<'a>> {
+    let data = match Kind::simplify(match_.scrutinee.clone()).as_ref() {
+        Kind::Data(data) => data.clone(),
+        _ => return Ok(()),
+    };
+
+    let missing: Vec<_> = data
+        .constructors
+        .iter()
+        .map(|(constructor, _)| constructor.as_str())
+        .filter(|constructor| !match_.constructors.contains(constructor))
+        .collect();
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(Error::from_sprint_error(
+            SprintError::NonExhaustivePatternsError,
+            Some(match_.span),
+        )
+        .with_help(format!("{} is not matched", missing.join(", ")))),
+    }
+}
+
+// Constructors are definitions which make a value of their data type from its fields.
+pub fn constructor<'a>(name: Span<'a>, data: Rc<Kind>) -> Result<'a, Context<'a, Expression<'a>>> {
+    let fields = match data.as_ref() {
+        Kind::Data(d) => d.constructor(name.fragment).unwrap().1.to_vec(),
+        kind => unreachable!("{} is not a data type", kind),
+    };
+
+    let kind = fields
+        .into_iter()
+        .rev()
+        .fold(data, |kind, field| Kind::Abstraction(field, kind).into());
+    let expression = Expression::new(ExpressionType::Constructor(name.fragment, kind), Some(name));
+
+    definition(name, Vec::new(), Context::from(expression))
+}
+
+pub fn binary<'a>(
+    operator: Span<'a>,
+    left: Context<'a, Expression<'a>>,
+    right: Context<'a, Expression<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (mut context, left) = left.clear();
+    let (right_context, right) = right.clear();
+    context.unify(right_context).map_err(Err::Error)?;
+
+    // Additions and subtractions which might involve dates are checked once their kinds are known.
+    let plain = |e: &Expression| {
+        !matches!(
+            Kind::simplify(e.kind()).as_ref(),
+            Kind::Date | Kind::Duration | Kind::Unresolved(_)
+        )
+    };
+
+    if matches!(operator.fragment, "+" | "-") && !plain(&left) && !plain(&right) {
+        return dated(context, operator, left, right);
+    }
+
+    let kind = left.kind();
+    kind.clone().unify(right.kind()).map_err(|error| {
+        let label = format!("the left operand has kind {}", left.kind());
+        Err::Error(locate(error, operator).with_label(left.span, label))
+    })?;
+
+    let (left, right) = (left.into(), right.into());
+    let class = match operator.fragment {
+        "==" => Class::Equatable(Equatable::Equal(left, right)),
+        "/=" => Class::Equatable(Equatable::NotEqual(left, right)),
+        ">" => Class::Comparable(Comparable::Greater(left, right)),
+        "<" => Class::Comparable(Comparable::Less(left, right)),
+        ">=" => Class::Comparable(Comparable::GreaterEqual(left, right)),
+        "<=" => Class::Comparable(Comparable::LessEqual(left, right)),
+        "+" => Class::Numerable(Numerable::Add(left, right)),
+        "-" => Class::Numerable(Numerable::Subtract(left, right)),
+        "*" => Class::Numerable(Numerable::Multiply(left, right)),
+        "/" => Class::Numerable(Numerable::Divide(left, right)),
+        _ => unreachable!("unknown binary operator {}", operator.fragment),
+    };
+
+    constrain(&mut context, &class, kind, operator)?;
+
+    Ok(context.map(|_| fold(Expression::new(class.into(), Some(operator)))))
+}
+
+fn dated<'a>(
+    mut context: Context<'a, ()>,
+    operator: Span<'a>,
+    left: Expression<'a>,
+    right: Expression<'a>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let resolved = [left.kind(), right.kind()]
+        .iter()
+        .all(|kind| !matches!(Kind::simplify(kind.clone()).as_ref(), Kind::Unresolved(_)));
+
+    let kind = left.kind();
+    let (left, right) = (left.into(), right.into());
+    let (class, expression) = match operator.fragment {
+        "+" => {
+            let class = Class::Numerable(Numerable::Add(left, right));
+            (class.clone(), class.into())
+        }
+        _ => {
+            let class = Class::Numerable(Numerable::Subtract(left, right));
+            let subtraction = Expression::new(class.clone().into(), Some(operator));
+            let expression = ExpressionType::Difference(subtraction.into(), Kind::default().into());
+            (class, expression)
+        }
+    };
+
+    let expression = Expression::new(expression, Some(operator));
+    let arithmetic = Arithmetic {
+        class,
+        operator,
+        result: expression.kind(),
+    };
+
+    match resolved {
+        true => self::arithmetic(&arithmetic).map_err(Err::Failure)?,
+        false => {
+            context.instances.push(Instance {
+                class: arithmetic.class.clone(),
+                kind,
+                operator,
+            });
+            context.arithmetic.push(arithmetic);
+        }
+    }
+
+    Ok(context.map(|_| fold(expression)))
+}
+
+// Durations can be added to or subtracted from a date, and subtracting one date from another
+// gives the duration between them. Anything else subtracted from a date is taken to be a
+// duration, while operands which aren't dates must have the same kind.
+pub fn arithmetic<'a>(arithmetic: &Arithmetic<'a>) -> result::Result<(), Error<'a>> {
+    let operator = arithmetic.operator;
+    let (left, right) = match arithmetic.class.operands()[..] {
+        [left, right] => (left, right),
+        _ => unreachable!("arithmetic has two operands"),
+    };
+
+    let date = |e: &Expression| *Kind::simplify(e.kind()) == Kind::Date;
+    let elapsed = matches!(
+        arithmetic.class,
+        Class::Numerable(Numerable::Subtract(_, _))
+    ) && date(right);
+    let mismatch = |error| {
+        let label = format!("the left operand has kind {}", left.kind());
+        locate(error, operator).with_label(left.span, label)
+    };
+
+    if !date(left) && !date(right) {
+        left.kind().unify(right.kind()).map_err(mismatch)?;
+        arithmetic.result.clone().unify(left.kind())?;
+
+        // Deferred arithmetic on operands which are still unresolved has its instance checked
+        // along with those of other operators.
+        return match Kind::simplify(left.kind()).as_ref() {
+            Kind::Unresolved(_) => Ok(()),
+            _ => instance(&arithmetic.class, left.kind(), operator),
+        };
+    }
+
+    let (right_kind, result) = match elapsed {
+        true => (Kind::Date, Kind::Duration.into()),
+        false => (Kind::Duration, left.kind()),
+    };
+
+    Rc::new(Kind::Date)
+        .unify(left.kind())
+        .map_err(|error| locate(error, left.span.unwrap_or(operator)))?;
+    Rc::new(right_kind).unify(right.kind()).map_err(mismatch)?;
+    arithmetic.result.clone().unify(result)
+}
+
+// Arithmetic on date and duration literals is done at compile time. Results which are out of
+// range are left for the contract to abort on.
+fn fold(expression: Expression) -> Expression {
+    let literals =
+        |left: &Expression, right: &Expression| match (&left.expression, &right.expression) {
+            (ExpressionType::Date(left), ExpressionType::Date(right)) => {
+                Some((left.timestamp()?, right.timestamp()?, true))
+            }
+            (ExpressionType::Date(left), ExpressionType::Duration(right)) => {
+                Some((left.timestamp()?, *right, true))
+            }
+            (ExpressionType::Duration(left), ExpressionType::Duration(right)) => {
+                Some((*left, *right, false))
+            }
+            _ => None,
+        };
+
+    let folded = match &expression.expression {
+        ExpressionType::Class(Class::Numerable(Numerable::Add(left, right))) => {
+            literals(left, right)
+                .and_then(|(left, right, date)| Some((left.checked_add(right)?, date)))
+        }
+        ExpressionType::Difference(subtraction, _) => match &subtraction.expression {
+            ExpressionType::Class(Class::Numerable(Numerable::Subtract(left, right))) => {
+                let elapsed = matches!(right.expression, ExpressionType::Date(_));
+                literals(left, right).and_then(|(left, right, date)| {
+                    Some((left.checked_sub(right)?, date && !elapsed))
+                })
+            }
+            _ => None,
         },
+        _ => None,
+    };
+
+    let folded = match folded {
+        Some((timestamp, true)) => Date::from_timestamp(timestamp).map(ExpressionType::Date),
+        Some((seconds, false)) => Some(ExpressionType::Duration(seconds)),
+        None => None,
+    };
+
+    match folded {
+        Some(folded) => Expression::new(folded, expression.span),
+        None => expression,
+    }
+}
+
+pub fn negate<'a>(
+    operator: Span<'a>,
+    operand: Context<'a, Expression<'a>>,
+) -> Result<'a, Context<'a, Expression<'a>>> {
+    let (mut context, operand) = operand.clear();
+
+    let kind = operand.kind();
+    let class = Class::Negatable(Negatable::Negate(operand.into()));
+
+    constrain(&mut context, &class, kind, operator)?;
+
+    Ok(context.map(|_| Expression::new(class.into(), Some(operator))))
+}
+
+// Operands of an unresolved kind might still turn out to have an instance, so they are checked once
+// the kinds of their definition's group are known.
+fn constrain<'a>(
+    context: &mut Context<'a, ()>,
+    class: &Class<'a>,
+    kind: Rc<Kind>,
+    operator: Span<'a>,
+) -> Result<'a, ()> {
+    match Kind::simplify(kind.clone()).as_ref() {
+        Kind::Unresolved(_) => {
+            context.instances.push(Instance {
+                class: class.clone(),
+                kind,
+                operator,
+            });
+            Ok(())
+        }
+        _ => instance(class, kind, operator).map_err(Err::Failure),
+    }
+}
+
+pub fn instance<'a>(
+    class: &Class<'a>,
+    kind: Rc<Kind>,
+    span: Span<'a>,
+) -> result::Result<(), Error<'a>> {
+    let kind = Kind::simplify(kind);
+
+    if class.has_instance(&kind) {
+        return Ok(());
+    }
+
+    let instances: Vec<_> = [
+        Kind::Boolean,
+        Kind::Date,
+        Kind::Decimal,
+        Kind::Duration,
+        Kind::Int,
+        Kind::Word,
+    ]
+    .iter()
+    .filter(|kind| class.has_instance(kind))
+    .map(Kind::to_string)
+    .collect();
+    let help = format!(
+        "{} is only defined for {}",
+        class.name(),
+        instances.join(", ")
+    );
+
+    Err(Error::from_sprint_error(
+        SprintError::MissingInstanceError(class.name(), kind.as_ref().clone()),
+        Some(span),
+    )
+    .with_help(help))
+}
+
+fn locate<'a>(error: Error<'a>, span: Span<'a>) -> Error<'a> {
+    match error.sprint_error {
+        Some(sprint_error) => Error::from_sprint_error(sprint_error, Some(span)),
+        None => error,
     }
 }
 
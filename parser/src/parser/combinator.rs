@@ -1,11 +1,13 @@
-use super::IResult;
+use super::{
+    whitespace::{space0, Input},
+    IResult,
+};
 use nom::{
     character::complete::char,
-    character::complete::multispace0,
     error::ParseError,
     multi::{count, many1_count},
     sequence::{delimited, terminated},
-    AsBytes, AsChar, InputIter, InputTakeAtPosition, Slice,
+    AsBytes, AsChar, Err, InputIter, InputTakeAtPosition, Slice,
 };
 use nom_locate::LocatedSpan;
 use std::ops::RangeFrom;
@@ -21,14 +23,15 @@ where
     }
 }
 
-pub fn padding0<I, O, E, F>(f: F) -> impl Fn(I) -> nom::IResult<I, O, E>
+pub fn padding0<I, O, E, F>(layout: usize, f: F) -> impl Fn(I) -> nom::IResult<I, O, E>
 where
-    I: InputTakeAtPosition,
+    I: Input,
+    <I as InputIter>::Item: AsChar,
     <I as InputTakeAtPosition>::Item: AsChar + Clone,
     E: ParseError<I>,
     F: Fn(I) -> nom::IResult<I, O, E>,
 {
-    delimited(multispace0, f, multispace0)
+    delimited(space0(layout), f, space0(layout))
 }
 
 pub fn brackets1<I, O, E, F>(f: F) -> impl Fn(I) -> nom::IResult<I, O, E>
@@ -39,8 +42,21 @@ where
     F: Fn(I) -> nom::IResult<I, O, E> + Copy,
 {
     move |input: I| {
-        let (input, brackets) = many1_count(char('('))(input)?;
-        terminated(f, count(char(')'), brackets))(input)
+        let (_, brackets) = many1_count(char('('))(input.clone())?;
+        let mut error = None;
+
+        // Leading brackets may also belong to the contents, as in "((1 + 2) * 3)", so match
+        // as many as possible first and back off until the contents can be parsed.
+        for brackets in (1..=brackets).rev() {
+            let (contents, _) = count(char('('), brackets)(input.clone())?;
+
+            match terminated(f, count(char(')'), brackets))(contents) {
+                Err(Err::Error(e)) => error = Some(e),
+                result => return result,
+            }
+        }
+
+        Err(Err::Error(error.unwrap()))
     }
 }
 
@@ -78,10 +94,11 @@ mod tests {
                     nom_error: Some(NomError {
                         line: 1,
                         column: 3,
-                        input: &abd[2..],
+                        input: &abd[2..3],
                         kind: ErrorKind::Char,
                     }),
                     sprint_error: None,
+                    notes: vec![],
                 }
             ),
             _ => unreachable!(),
@@ -90,18 +107,21 @@ mod tests {
 
     #[test]
     fn parse_padding0() {
-        assert_eq!(padding0(parser)("abc"), Ok(("", "abc")));
-        assert_eq!(padding0(parser)(" abc"), Ok(("", "abc")));
-        assert_eq!(padding0(parser)("  abc"), Ok(("", "abc")));
-        assert_eq!(padding0(parser)("abc "), Ok(("", "abc")));
-        assert_eq!(padding0(parser)("abc  "), Ok(("", "abc")));
-        assert_eq!(padding0(parser)(" abc "), Ok(("", "abc")));
-        assert_eq!(padding0(parser)("  abc  "), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("abc"), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)(" abc"), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("  abc"), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("abc "), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("abc  "), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)(" abc "), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("  abc  "), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("{- a -} abc -- b"), Ok(("", "abc")));
+        assert_eq!(padding0(1, parser)("\n abc\n"), Ok(("\n", "abc")));
     }
 
     #[test]
     fn parse_brackets1() {
         assert_eq!(brackets1(parser)("(abc)"), Ok(("", "abc")));
+        assert_eq!(brackets1(parser)("((abc))"), Ok(("", "abc")));
         brackets1(parser)("(abc").unwrap_err();
         brackets1(parser)("((abc").unwrap_err();
         brackets1(parser)("((abc)").unwrap_err();
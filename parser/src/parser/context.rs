@@ -1,14 +1,60 @@
+use super::Span;
 use crate::{
-    ast::{Definition, Variable},
-    hash_count::HashCount,
+    ast::{
+        borrowed::{Class, Component, Definition, Variable},
+        Kind,
+    },
+    occurrences::Occurrences,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 #[derive(Clone, Debug)]
 pub struct Context<'a, T> {
     inner: T,
     pub definitions: HashMap<&'a str, Definition<'a>>,
-    pub variables: HashCount<Variable<'a>>,
+    pub signatures: HashMap<&'a str, Variable<'a>>,
+    pub variables: Occurrences<'a>,
+    pub arithmetic: Vec<Arithmetic<'a>>,
+    pub matches: Vec<Match<'a>>,
+    pub accesses: Vec<Access<'a>>,
+    pub instances: Vec<Instance<'a>>,
+}
+
+/// An addition or subtraction whose operands might be dates, which can only be checked once the
+/// kinds of its operands are known.
+#[derive(Clone, Debug)]
+pub struct Arithmetic<'a> {
+    pub class: Class<'a>,
+    pub operator: Span<'a>,
+    pub result: Rc<Kind>,
+}
+
+/// A case expression on a data type without a "_" alternative, which can only be checked for
+/// exhaustiveness once the data type is known.
+#[derive(Clone, Debug)]
+pub struct Match<'a> {
+    pub span: Span<'a>,
+    pub scrutinee: Rc<Kind>,
+    pub constructors: Vec<&'a str>,
+}
+
+/// A use of a component of a tuple or a record, which can only be checked once the kind of the
+/// tuple or record is known.
+#[derive(Clone, Debug)]
+pub struct Access<'a> {
+    pub span: Span<'a>,
+    pub value: Rc<Kind>,
+    pub component: Component<'a>,
+    pub result: Rc<Kind>,
+}
+
+/// A use of an operator on operands of an unresolved kind, which can only be checked for an
+/// instance of its class once the kind is known or defaulted.
+#[derive(Clone, Debug)]
+pub struct Instance<'a> {
+    pub class: Class<'a>,
+    pub kind: Rc<Kind>,
+    pub operator: Span<'a>,
 }
 
 impl<T> From<T> for Context<'_, T> {
@@ -16,7 +62,12 @@ impl<T> From<T> for Context<'_, T> {
         Self {
             inner,
             definitions: Default::default(),
+            signatures: Default::default(),
             variables: Default::default(),
+            arithmetic: Default::default(),
+            matches: Default::default(),
+            accesses: Default::default(),
+            instances: Default::default(),
         }
     }
 }
@@ -26,7 +77,12 @@ impl<'a, T> Context<'a, T> {
         Context {
             inner: f(self.inner),
             definitions: self.definitions,
+            signatures: self.signatures,
             variables: self.variables,
+            arithmetic: self.arithmetic,
+            matches: self.matches,
+            accesses: self.accesses,
+            instances: self.instances,
         }
     }
 
@@ -35,7 +91,12 @@ impl<'a, T> Context<'a, T> {
             Context {
                 inner: (),
                 definitions: self.definitions,
+                signatures: self.signatures,
                 variables: self.variables,
+                arithmetic: self.arithmetic,
+                matches: self.matches,
+                accesses: self.accesses,
+                instances: self.instances,
             },
             self.inner,
         )
@@ -1,6 +1,20 @@
-use super::{IResult, Span};
-use crate::ast::{Date, Expression, ExpressionType};
-use nom::{bytes::complete::tag, character::complete::digit1, combinator::opt};
+use super::{
+    error::{Error, SprintError},
+    IResult, Span,
+};
+use crate::ast::{
+    borrowed::{Expression, ExpressionType},
+    Date,
+};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1},
+    combinator::{map, opt},
+    Err, Slice,
+};
+use std::convert::TryFrom;
 
 pub fn date(input: Span) -> IResult<Span, Expression> {
     let span = input;
@@ -41,21 +55,124 @@ pub fn date(input: Span) -> IResult<Span, Expression> {
         (input, Some(second)) => (input, second),
         (input, None) => (input, 0),
     };
-    let (input, _) = tag("Z")(input)?;
+    let (input, offset) = alt((map(tag("Z"), |_| (1, 0, 0)), offset))(input)?;
+
+    let span = span.slice(..span.fragment.len() - input.fragment.len());
+    let date = utc([year, month, day, hour, minute, second], offset).map_err(|part| {
+        Err::Failure(Error::from_sprint_error(
+            SprintError::InvalidDateError(part),
+            Some(span),
+        ))
+    })?;
 
-    // Year is represented as an i32 (compared to u32).
     Ok((
         input,
         Expression::new(
-            ExpressionType::Date(Date::Date(year, month, day, hour, minute, second)),
+            ExpressionType::Date(Date::Date(
+                date.year() as u64,
+                date.month().into(),
+                date.day().into(),
+                date.hour().into(),
+                date.minute().into(),
+                date.second().into(),
+            )),
             Some(span),
         ),
     ))
 }
 
+// Offsets such as "+01:00" are the hours and minutes by which the time is ahead of UTC.
+fn offset(input: Span) -> IResult<Span, (i64, u64, u64)> {
+    let (input, sign) = alt((map(char('+'), |_| 1), map(char('-'), |_| -1)))(input)?;
+    let (input, hours) = digits(input)?;
+    let (input, _) = tag(":")(input)?;
+    let (input, minutes) = digits(input)?;
+
+    Ok((input, (sign, hours, minutes)))
+}
+
+// Dates are normalized to UTC, and are checked to be on the calendar and representable as a
+// timestamp. Otherwise the part of the date which is invalid is returned.
+fn utc(parts: [u64; 6], offset: (i64, u64, u64)) -> Result<NaiveDateTime, &'static str> {
+    let [year, month, day, hour, minute, second] = parts;
+    let (sign, offset_hours, offset_minutes) = offset;
+
+    let year = i32::try_from(year).map_err(|_| "year")?;
+
+    if !(1..=12).contains(&month) {
+        return Err("month");
+    }
+    let date = NaiveDate::from_ymd_opt(year, month as u32, day as u32).ok_or("day")?;
+
+    if hour >= 24 {
+        return Err("hour");
+    } else if minute >= 60 {
+        return Err("minute");
+    } else if second >= 60 {
+        return Err("second");
+    } else if offset_hours >= 24 || offset_minutes >= 60 {
+        return Err("offset");
+    }
+
+    let time = NaiveTime::from_hms(hour as u32, minute as u32, second as u32);
+    let offset = Duration::minutes(sign * (offset_hours * 60 + offset_minutes) as i64);
+    let date = date.and_time(time) - offset;
+
+    match date.timestamp() >= 0 {
+        true => Ok(date),
+        false => Err("year"),
+    }
+}
+
+// Numbers too large for a u64 are out of range for any part of a date, so they are left for utc
+// to report.
 fn digits(input: Span) -> IResult<Span, u64> {
     let (input, number) = digit1(input)?;
-    let number = number.fragment.parse::<u64>().unwrap();
+    let number = number.fragment.parse::<u64>().unwrap_or(u64::MAX);
 
     Ok((input, number))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Date, Option<SprintError>> {
+        match date(Span::new(input)) {
+            Ok((_, expression)) => match expression.expression {
+                ExpressionType::Date(date) => Ok(date),
+                _ => unreachable!(),
+            },
+            Err(Err::Failure(error)) => Err(error.sprint_error),
+            Err(_) => Err(None),
+        }
+    }
+
+    #[test]
+    fn parse_offset() {
+        match parse("2021-01-01T00:30:00+01:00") {
+            Ok(Date::Date(2020, 12, 31, 23, 30, 0)) => {}
+            date => panic!("expected the date in UTC, got {:?}", date),
+        }
+        match parse("2020-12-31T23:45:00-00:30") {
+            Ok(Date::Date(2021, 1, 1, 0, 15, 0)) => {}
+            date => panic!("expected the date in UTC, got {:?}", date),
+        }
+    }
+
+    #[test]
+    fn parse_invalid_date() {
+        let invalid = |part| Err(Some(SprintError::InvalidDateError(part)));
+
+        assert_eq!(parse("2020-13-01T00:00:00Z"), invalid("month"));
+        assert_eq!(parse("2019-02-29T00:00:00Z"), invalid("day"));
+        assert_eq!(parse("2020-01-01T24:00:00Z"), invalid("hour"));
+        assert_eq!(parse("2020-01-01T00:00:00+01:60"), invalid("offset"));
+        assert_eq!(parse("1970-01-01T00:00:00+01:00"), invalid("year"));
+        assert_eq!(
+            parse("99999999999999999999-01-01T00:00:00Z"),
+            invalid("year")
+        );
+        assert_eq!(parse("2020-01-01T00:00:00"), Err(None));
+    }
+}
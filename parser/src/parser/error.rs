@@ -1,68 +1,243 @@
-use super::Span;
-use crate::ast::Kind;
+use super::{lint::Lint, Span};
+use crate::ast::{borrowed::Component, Kind, DECIMAL_PLACES};
 use nom::error::{ErrorKind, ParseError};
 
 #[derive(PartialEq, Debug)]
 pub struct Error<'a> {
     pub nom_error: Option<NomError<'a>>,
     pub sprint_error: Option<SprintError<'a>>,
+    pub notes: Vec<Note<'a>>,
 }
 
+/// A finding of a lint at the warn level, which is reported without stopping compilation.
+#[derive(PartialEq, Debug)]
+pub struct Warning<'a>(pub Error<'a>);
+
 #[derive(PartialEq, Debug)]
 pub struct NomError<'a> {
     pub line: usize,
     pub column: usize,
+    /// The code which the error is about.
     pub input: &'a str,
     pub kind: ErrorKind,
 }
 
+/// Explains an error, either by labelling a secondary location, such as where a kind was first
+/// inferred, or by suggesting a fix.
+#[derive(PartialEq, Debug)]
+pub enum Note<'a> {
+    Label(NomError<'a>, String),
+    Help(String),
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum SprintError<'a> {
     TypeError(&'a str, Box<SprintError<'a>>),
     MismatchedKinds(Kind, Kind),
     UnknownIdentifierError(&'a str, Kind),
+    UnknownKindError(&'a str),
+    AliasVariableError(&'a str),
+    MissingComponentError(Component<'a>, Kind),
+    UnknownComponentKindError(Component<'a>),
+    MissingInstanceError(&'a str, Kind),
+    DecimalPlacesError(usize),
+    LiteralOverflowError(Kind),
+    InvalidDateError(&'a str),
     DuplicateDefinitionError(&'a str),
+    UnknownModuleError(&'a str),
+    ModuleNameError(&'a str, &'a str),
+    MissingExportError(&'a str, &'a str),
+    NotImportedError(&'a str, &'a str),
+    UnusedImportError(&'a str),
+    CyclicImportError(Vec<&'a str>),
+    InfiniteContractError(&'a str),
+    LintError(SprintWarning<'a>),
+    NonExhaustivePatternsError,
     InvalidNumberArgsError,
     UndefinedMainError,
 }
 
+/// Code which compiles, but is likely to be a mistake. Each warning belongs to a [`Lint`], whose
+/// level decides whether it is a warning, an error or ignored.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SprintWarning<'a> {
+    UnusedDefinition(&'a str),
+    UnusedArgument(&'a str),
+    Shadowing(&'a str),
+    ScaleByZero,
+    DoubleGive,
+    RedundantOr,
+}
+
 impl<'a> Error<'a> {
-    pub fn pretty(&self, original: &str) -> String {
-        let nom_error = match &self.nom_error {
-            Some(err) => err.pretty(original),
-            None => String::from(""),
-        };
-        let sprint_error = match &self.sprint_error {
+    /// Renders the error as a diagnostic, underlining the offending code in the `files`, which
+    /// are given as pairs of names and sources. Errors without a location refer to the first file.
+    pub fn pretty(&self, files: &[(&str, &str)]) -> String {
+        self.render("error", files)
+    }
+
+    fn render(&self, severity: &str, files: &[(&str, &str)]) -> String {
+        let message = match &self.sprint_error {
             Some(err) => err.clone().pretty(),
-            None => String::from(""),
+            None => String::from("Unable to parse the input"),
         };
+        let file_of = |location: &NomError| {
+            files
+                .iter()
+                .position(|(_, source)| location.is_within(source))
+        };
+
+        let primary = self
+            .nom_error
+            .as_ref()
+            .and_then(|e| Some((file_of(e)?, e, '^', "")));
+        let labels = self.notes.iter().filter_map(|note| match note {
+            Note::Label(location, message) => {
+                Some((file_of(location)?, location, '-', message.as_str()))
+            }
+            _ => None,
+        });
+        let annotations: Vec<_> = primary.into_iter().chain(labels).collect();
+
+        let default_help = self.sprint_error.as_ref().and_then(SprintError::help);
+        let help: Vec<_> = default_help
+            .iter()
+            .chain(self.notes.iter().filter_map(|note| match note {
+                Note::Help(help) => Some(help),
+                _ => None,
+            }))
+            .collect();
+
+        let mut lines: Vec<(usize, usize)> = Vec::new();
+
+        for (file, location, ..) in &annotations {
+            if !lines.contains(&(*file, location.line)) {
+                lines.push((*file, location.line));
+            }
+        }
+
+        let width = lines.iter().map(|(_, line)| *line).max().unwrap_or(0);
+        let width = width.to_string().len();
+        let gutter = format!("{:w$} |\n", "", w = width);
+        let name = |file: usize| files.get(file).map_or("", |(name, _)| name);
+
+        let mut output = format!("{}: {}\n", severity, message);
+
+        match primary {
+            Some((file, error, ..)) => {
+                let (name, line, column) = (name(file), error.line, error.column);
+                output += &format!("{:w$}--> {}:{}:{}\n", "", name, line, column, w = width);
+            }
+            None => output += &format!("{:w$}--> {}\n", "", name(0), w = width),
+        }
+
+        let mut current = primary.map_or(0, |(file, ..)| file);
+
+        // Each line of code is shown once, followed by the annotations on it. Lines in other
+        // files are introduced by their location.
+        for (file, line) in lines {
+            let (_, source) = files[file];
+            let code = source.lines().nth(line - 1).unwrap_or("");
+            let on_line: Vec<_> = annotations
+                .iter()
+                .filter(|(f, location, ..)| *f == file && location.line == line)
+                .collect();
+
+            if file != current {
+                let column = on_line[0].1.column;
+                let (name, w) = (name(file), width);
+                output += &format!("{:w$}::: {}:{}:{}\n", "", name, line, column, w = w);
+                current = file;
+            }
 
-        format!("{}{}\n", sprint_error, nom_error)
+            output += &gutter;
+            output += &format!("{:>w$} | {}\n", line, code, w = width);
+
+            for (_, location, marker, message) in on_line {
+                output += &location.underline(code, *marker, message, width);
+            }
+        }
+
+        if !help.is_empty() {
+            output += &gutter;
+        }
+
+        for help in help {
+            output += &format!("{:w$} = help: {}\n", "", help, w = width);
+        }
+
+        output
     }
 
     pub fn from_sprint_error(sprint_error: SprintError<'a>, input: Option<Span<'a>>) -> Self {
-        match input {
-            Some(span) => Error {
-                nom_error: Some(NomError::from_span(span)),
-                sprint_error: Some(sprint_error),
-            },
-            None => Error {
-                nom_error: None,
-                sprint_error: Some(sprint_error),
-            },
+        Error {
+            nom_error: input.map(NomError::from_span),
+            sprint_error: Some(sprint_error),
+            notes: Vec::new(),
         }
     }
+
+    pub fn from_sprint_warning(warning: SprintWarning<'a>, input: Option<Span<'a>>) -> Self {
+        Self::from_sprint_error(SprintError::LintError(warning), input)
+    }
+
+    pub fn with_label(mut self, span: Option<Span<'a>>, message: impl Into<String>) -> Self {
+        if let Some(span) = span {
+            let location = NomError::from_span(span);
+            self.notes.push(Note::Label(location, message.into()));
+        }
+
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.notes.push(Note::Help(help.into()));
+        self
+    }
+}
+
+impl Warning<'_> {
+    /// Renders the warning like [`Error::pretty`].
+    pub fn pretty(&self, files: &[(&str, &str)]) -> String {
+        self.0.render("warning", files)
+    }
 }
 
 impl<'a> NomError<'a> {
-    pub fn pretty(&self, original: &str) -> String {
-        let line = self.line;
-        let code = print_code_line(original, line);
-        if code.is_empty() {
-            code
-        } else {
-            format!("\nOn line {}: \n\t{}", line, code)
-        }
+    // Primitives are defined with spans which are not part of the source, so they have no code to
+    // point at.
+    pub fn is_within(&self, source: &str) -> bool {
+        let start = source.as_ptr() as usize;
+        let input = self.input.as_ptr() as usize;
+
+        start <= input && input <= start + source.len()
+    }
+
+    // Underlines the code at this location in its line of code with `marker`. Code which
+    // continues onto later lines is underlined up to the end of the first one.
+    fn underline(&self, code: &str, marker: char, message: &str, width: usize) -> String {
+        let before = code.get(..self.column - 1).unwrap_or("");
+        let indent: String = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let underlined = self.input.lines().next().unwrap_or("");
+        let underline = marker.to_string().repeat(underlined.chars().count().max(1));
+
+        let message = match message {
+            "" => String::new(),
+            message => format!(" {}", message),
+        };
+
+        format!(
+            "{:w$} | {}{}{}\n",
+            "",
+            indent,
+            underline,
+            message,
+            w = width
+        )
     }
 
     fn from_span(input: Span<'a>) -> Self {
@@ -80,27 +255,181 @@ impl<'a> SprintError<'a> {
     pub fn pretty(self) -> String {
         match self {
             Self::TypeError(definition, mismatched_kinds) => format!(
-                "Type Error: From definition of \"{}\" {} ",
+                "Type Error: From definition of \"{}\" {}",
                 definition,
                 mismatched_kinds.pretty()
             ),
-            Self::MismatchedKinds(actual, expected) => {
-                format!("expected {}, got {}", actual, expected)
+            Self::MismatchedKinds(expected, actual) => {
+                format!("expected {}, got {}", expected, actual)
             }
             Self::UnknownIdentifierError(id, kind) => {
                 format!("Unknown identifier: {} :: {}", id, kind)
             }
+            Self::UnknownKindError(kind) => format!("Unknown kind \"{}\"", kind),
+            Self::AliasVariableError(alias) => {
+                format!("Type \"{}\" has a kind variable", alias)
+            }
+            Self::MissingComponentError(component, kind) => {
+                format!("Kind {} has no component \"{}\"", kind, component)
+            }
+            Self::UnknownComponentKindError(component) => format!(
+                "The kind of the value whose component \"{}\" is used is not known",
+                component
+            ),
+            Self::MissingInstanceError(class, kind) => {
+                format!("No instance of {} for kind {}", class, kind)
+            }
+            Self::DecimalPlacesError(places) => {
+                format!("Decimals can have at most {} decimal places", places)
+            }
+            Self::LiteralOverflowError(kind) => format!("{} literal is out of range", kind),
+            Self::InvalidDateError(part) => format!("Invalid {} in date", part),
             Self::InvalidNumberArgsError => {
                 String::from("Invalid number of arguments in primitive application")
             }
             Self::DuplicateDefinitionError(name) => {
                 format!("Duplicate definition for \"{}\"", name)
             }
+            Self::UnknownModuleError(module) => format!("Unknown module \"{}\"", module),
+            Self::ModuleNameError(expected, actual) => {
+                format!("Module \"{}\" is imported as \"{}\"", actual, expected)
+            }
+            Self::MissingExportError(module, name) => {
+                format!("Module \"{}\" does not define \"{}\"", module, name)
+            }
+            Self::NotImportedError(name, module) => format!(
+                "\"{}\" is defined in module \"{}\", but is not imported",
+                name, module
+            ),
+            Self::UnusedImportError(module) => {
+                format!("Module \"{}\" is imported but never used", module)
+            }
+            Self::CyclicImportError(modules) => format!(
+                "Modules import each other in a cycle: {}",
+                modules.join(" -> ")
+            ),
+            Self::InfiniteContractError(name) => {
+                format!(
+                    "Infinite contract: the recursion in \"{}\" might never end",
+                    name
+                )
+            }
+            Self::LintError(warning) => warning.pretty(),
+            Self::NonExhaustivePatternsError => {
+                String::from("Patterns in case expression are not exhaustive")
+            }
             Self::UndefinedMainError => {
                 String::from("No valid definition of the \"main\" contract was found")
             }
         }
     }
+
+    pub fn help(&self) -> Option<String> {
+        match self {
+            Self::TypeError(_, sprint_error) => sprint_error.help(),
+            Self::UnknownIdentifierError(id, _) => Some(format!(
+                "define \"{}\" at the top level, or bind it as an argument or with let or where",
+                id
+            )),
+            Self::UnknownKindError(kind) => Some(format!(
+                "declare the kind with \"type {} = ...\", or use a kind such as Word",
+                kind
+            )),
+            Self::AliasVariableError(_) => Some(String::from(
+                "type aliases and data types have no parameters, so they can only use kinds \
+                 such as Observable Word",
+            )),
+            Self::UnknownComponentKindError(_) => Some(String::from(
+                "give the kind of the tuple or record in a signature",
+            )),
+            Self::LiteralOverflowError(kind) => match kind {
+                Kind::Decimal => Some(format!(
+                    "the largest Decimal is {}.{:09}",
+                    u64::MAX / 10u64.pow(DECIMAL_PLACES as u32),
+                    u64::MAX % 10u64.pow(DECIMAL_PLACES as u32)
+                )),
+                Kind::Duration => Some(format!("the longest Duration is {}s", u64::MAX)),
+                Kind::Int => Some(format!("Ints range from -{0} to +{0}", i64::MAX)),
+                Kind::Word => Some(format!("the largest Word is {}", u64::MAX)),
+                _ => None,
+            },
+            Self::InvalidDateError(_) => Some(String::from(
+                "dates are written like 2020-12-25T09:30:00Z, or with an offset from UTC like \
+                 2020-12-25T09:30:00+01:00, and can't be before 1970",
+            )),
+            Self::DuplicateDefinitionError(_) => {
+                Some(String::from("rename or remove one of the definitions"))
+            }
+            Self::ModuleNameError(expected, _) => Some(format!(
+                "rename the module to \"{}\", or import it by its own name",
+                expected
+            )),
+            Self::NotImportedError(name, module) => Some(format!(
+                "use \"{}.{}\", or import it with \"import {} ({})\"",
+                module, name, module, name
+            )),
+            Self::UnusedImportError(_) => Some(String::from("remove the import")),
+            Self::CyclicImportError(_) => Some(String::from(
+                "move the definitions which the modules share into a module of their own",
+            )),
+            Self::InfiniteContractError(_) => Some(String::from(
+                "subtract a literal from a Word argument in each recursive use, as in \
+                 \"payments (n - 1)\", or make each one wait for a date with before or after",
+            )),
+            Self::LintError(warning) => warning.help(),
+            Self::NonExhaustivePatternsError => {
+                Some(String::from("add a \"_\" case to match any other value"))
+            }
+            Self::UndefinedMainError => {
+                Some(String::from("define the contract, such as \"main = one\""))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl SprintWarning<'_> {
+    pub fn lint(&self) -> Lint {
+        match self {
+            Self::UnusedDefinition(_) => Lint::UnusedDefinition,
+            Self::UnusedArgument(_) => Lint::UnusedArgument,
+            Self::Shadowing(_) => Lint::Shadowing,
+            Self::ScaleByZero => Lint::ScaleByZero,
+            Self::DoubleGive => Lint::DoubleGive,
+            Self::RedundantOr => Lint::RedundantOr,
+        }
+    }
+
+    pub fn pretty(&self) -> String {
+        let message = match self {
+            Self::UnusedDefinition(name) => format!("\"{}\" is never used", name),
+            Self::UnusedArgument(name) => format!("Argument \"{}\" is never used", name),
+            Self::Shadowing(name) => {
+                format!(
+                    "\"{}\" shadows the top-level definition of the same name",
+                    name
+                )
+            }
+            Self::ScaleByZero => String::from("Scaling by konst 0 leaves nothing to acquire"),
+            Self::DoubleGive => String::from("Giving a contract twice gives it back"),
+            Self::RedundantOr => String::from("Both choices of or are the same contract"),
+        };
+
+        format!("{} [{}]", message, self.lint())
+    }
+
+    pub fn help(&self) -> Option<String> {
+        let help = match self {
+            Self::UnusedDefinition(_) => "remove the definition",
+            Self::UnusedArgument(_) => "remove the argument",
+            Self::Shadowing(_) => "rename the argument or local definition",
+            Self::ScaleByZero => "use zero instead",
+            Self::DoubleGive => "remove both uses of give",
+            Self::RedundantOr => "use the contract on its own",
+        };
+
+        Some(String::from(help))
+    }
 }
 
 impl<'a> ParseError<Span<'a>> for Error<'a> {
@@ -108,6 +437,7 @@ impl<'a> ParseError<Span<'a>> for Error<'a> {
         Error {
             nom_error: Some(NomError::from_error_kind(input, kind)),
             sprint_error: None,
+            notes: Vec::new(),
         }
     }
 
@@ -117,11 +447,15 @@ impl<'a> ParseError<Span<'a>> for Error<'a> {
 }
 
 impl<'a> ParseError<Span<'a>> for NomError<'a> {
+    // Parsing fails at the first character which can't be parsed, which is all that is known
+    // about where the error is.
     fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+        let length = input.fragment.chars().next().map_or(0, char::len_utf8);
+
         NomError {
             line: input.line as usize,
             column: input.get_column(),
-            input: input.fragment,
+            input: &input.fragment[..length],
             kind,
         }
     }
@@ -131,16 +465,6 @@ impl<'a> ParseError<Span<'a>> for NomError<'a> {
     }
 }
 
-fn print_code_line(input: &str, line: usize) -> String {
-    let lines: std::vec::Vec<String> = input.lines().map(String::from).collect();
-    if lines.is_empty() {
-        "".to_string()
-    } else {
-        // -1 to compensate for offset between line and index numbering.
-        lines[line - 1].clone()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,5 +479,60 @@ mod tests {
 
         assert_eq!(error.line, 1);
         assert_eq!(error.column, 4);
+        assert_eq!(error.input, " ");
+    }
+
+    #[test]
+    fn pretty_diagnostic() {
+        let source = "x = 1\ny = True + x\n";
+        let span = |offset, length| {
+            let (rest, _) = LocatedSpan::new(source).take_split(offset);
+            let (_, token) = rest.take_split(length);
+            token
+        };
+
+        let mismatch = SprintError::MismatchedKinds(Kind::Boolean, Kind::Word);
+        let error = Error::from_sprint_error(mismatch, Some(span(17, 1)))
+            .with_label(Some(span(10, 4)), "the left operand has kind Bool")
+            .with_label(Some(span(0, 1)), "\"x\" is defined here")
+            .with_label(Some(LocatedSpan::new("primitive")), "not in the source")
+            .with_help("compare values of the same kind");
+
+        assert_eq!(
+            error.pretty(&[("example.sprint", source)]),
+            [
+                "error: expected Bool, got Word",
+                " --> example.sprint:2:12",
+                "  |",
+                "2 | y = True + x",
+                "  |            ^",
+                "  |     ---- the left operand has kind Bool",
+                "  |",
+                "1 | x = 1",
+                "  | - \"x\" is defined here",
+                "  |",
+                "  = help: compare values of the same kind",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn underline_span() {
+        let source = "main = give (fooo)\nf = (1 +\n  2)";
+        let span = |offset, length| {
+            let (rest, _) = LocatedSpan::new(source).take_split(offset);
+            let (_, token) = rest.take_split(length);
+            token
+        };
+
+        let unknown = SprintError::UnknownIdentifierError("fooo", Kind::State);
+        let error = Error::from_sprint_error(unknown, Some(span(13, 4)))
+            .with_label(Some(span(23, 9)), "spans two lines");
+        let pretty = error.pretty(&[("example.sprint", source)]);
+
+        assert!(pretty.contains("\n  |              ^^^^\n"));
+        assert!(pretty.contains("\n  |     ---- spans two lines\n"));
     }
 }
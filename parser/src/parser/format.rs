@@ -0,0 +1,436 @@
+use crate::cst::{Element, Node, NodeKind, Token, TokenKind};
+
+// Lines are only broken where the layout needs them, or where a definition or a conditional
+// would otherwise run past this width.
+const WIDTH: usize = 100;
+
+// Prints a program in the standard layout: one item per line, with a signature directly above its
+// definition and other items separated by blank lines. Comments between items are kept, while
+// items with comments inside them are kept as they are written, since there is no telling which
+// expression such a comment is about.
+pub fn module(module: &Node) -> String {
+    let mut printer = Printer::default();
+    let mut previous: Option<&Node> = None;
+    // Comments before the next item, along with whether a blank line comes before each one.
+    let mut comments: Vec<(&Token, bool)> = Vec::new();
+    let mut newlines = 0;
+
+    for child in &module.children {
+        match child {
+            Element::Token(token) if token.kind == TokenKind::Newline => newlines += 1,
+            Element::Token(token) if token.kind == TokenKind::Comment => {
+                if previous.is_some() && comments.is_empty() && newlines == 0 {
+                    printer.push(" ");
+                    printer.push(token.text());
+                } else {
+                    comments.push((token, newlines > 1));
+                }
+                newlines = 0;
+            }
+            Element::Token(_) => {}
+            Element::Node(item) => {
+                if let Some(previous) = previous {
+                    let adjacent = comments.is_empty() && adjacent(previous, item);
+                    printer.push(if adjacent { "\n" } else { "\n\n" });
+                }
+
+                for (i, (comment, blank)) in comments.iter().enumerate() {
+                    if i > 0 {
+                        printer.push(if *blank { "\n\n" } else { "\n" });
+                    }
+                    printer.push(comment.text());
+                }
+                if !comments.is_empty() {
+                    printer.push(if newlines > 1 { "\n\n" } else { "\n" });
+                }
+
+                printer.item(item);
+                previous = Some(item);
+                comments.clear();
+                newlines = 0;
+            }
+        }
+    }
+
+    for (i, (comment, _)) in comments.iter().enumerate() {
+        if i > 0 || previous.is_some() {
+            printer.push(if previous.is_some() && i == 0 {
+                "\n\n"
+            } else {
+                "\n"
+            });
+        }
+        printer.push(comment.text());
+    }
+
+    if !printer.output.is_empty() {
+        printer.push("\n");
+    }
+
+    printer.output
+}
+
+// Imports go together, as does a signature with the definition that it is for.
+fn adjacent(previous: &Node, item: &Node) -> bool {
+    fn name<'a>(node: &Node<'a>) -> Option<&'a str> {
+        node.tokens().next().map(Token::text)
+    }
+
+    match (previous.kind, item.kind) {
+        (NodeKind::Import, NodeKind::Import) => true,
+        (NodeKind::Signature, NodeKind::Binding) => name(previous) == name(item),
+        _ => false,
+    }
+}
+
+fn commented(node: &Node) -> bool {
+    node.descendants()
+        .iter()
+        .any(|token| token.kind == TokenKind::Comment)
+}
+
+// Kinds, patterns and the other parts of items which are a single line are printed by spacing
+// out their tokens.
+fn spaced(node: &Node) -> String {
+    let mut output = String::new();
+    let mut previous = None;
+
+    for token in node.descendants() {
+        if token.is_trivia() {
+            continue;
+        }
+
+        let text = token.text();
+        let tight = matches!(previous, None | Some("(")) || [")", ","].contains(&text);
+        if !tight {
+            output.push(' ');
+        }
+
+        output.push_str(text);
+        previous = Some(text);
+    }
+
+    output
+}
+
+// How tightly an expression binds, from the expressions which extend as far as they can, such as
+// lambdas, up to terms, which never need brackets.
+fn precedence(node: &Node) -> usize {
+    match node.kind {
+        NodeKind::Binary => match operator(node) {
+            "+" | "-" => 2,
+            "*" | "/" => 3,
+            _ => 1,
+        },
+        NodeKind::Negation => 4,
+        NodeKind::Application => 5,
+        NodeKind::Lambda | NodeKind::Let | NodeKind::Conditional | NodeKind::Case => 0,
+        _ => 6,
+    }
+}
+
+fn operator<'a>(node: &Node<'a>) -> &'a str {
+    node.tokens().next().map_or("", Token::text)
+}
+
+// Brackets around an expression are left out, and put back if they are needed where it is used.
+fn unbracketed<'a, 'b>(mut node: &'b Node<'a>) -> &'b Node<'a> {
+    while node.kind == NodeKind::Parentheses {
+        node = node.nodes().next().unwrap();
+    }
+
+    node
+}
+
+// Whether an expression can follow a "-" directly. Otherwise "-" and a literal would become an
+// Int, and two "-" a comment.
+fn plain(node: &Node) -> bool {
+    match node.kind {
+        NodeKind::Literal | NodeKind::Negation => false,
+        NodeKind::Projection => plain(node.nodes().next().unwrap()),
+        _ => true,
+    }
+}
+
+fn signed(node: &Node) -> bool {
+    node.kind == NodeKind::Literal
+        && node
+            .tokens()
+            .any(|token| token.text().starts_with(&['+', '-'][..]))
+}
+
+#[derive(Default)]
+struct Printer {
+    output: String,
+    // The column of the innermost block, which lines have to be indented past to continue it.
+    layout: usize,
+}
+
+impl Printer {
+    fn push(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn line(&self) -> &str {
+        let start = self.output.rfind('\n').map_or(0, |i| i + 1);
+        &self.output[start..]
+    }
+
+    fn column(&self) -> usize {
+        self.line().chars().count() + 1
+    }
+
+    fn indentation(&self) -> usize {
+        self.line().len() - self.line().trim_start().len()
+    }
+
+    // The indentation for a line which continues the current one.
+    fn continuation(&self) -> usize {
+        (self.indentation() + 2).max(self.layout)
+    }
+
+    fn newline(&mut self, indentation: usize) {
+        let end = self.output.trim_end_matches(' ').len();
+        self.output.truncate(end);
+        self.output.push('\n');
+        self.output.push_str(&" ".repeat(indentation));
+    }
+
+    // The expression on a single line, if it fits on one.
+    fn flat(&self, f: impl FnOnce(&mut Self)) -> Option<String> {
+        let mut printer = Printer {
+            output: String::new(),
+            layout: self.layout,
+        };
+        f(&mut printer);
+
+        match printer.output.contains('\n') {
+            true => None,
+            false => Some(printer.output),
+        }
+    }
+
+    fn item(&mut self, item: &Node) {
+        if commented(item) {
+            let text = item.to_string();
+            let lines: Vec<_> = text.trim().lines().map(str::trim_end).collect();
+            self.push(&lines.join("\n"));
+            return;
+        }
+
+        match item.kind {
+            NodeKind::Binding => {
+                self.layout = 1;
+                self.binding(item, true);
+            }
+            _ => self.push(&spaced(item)),
+        }
+    }
+
+    fn binding(&mut self, binding: &Node, top: bool) {
+        let start = self.column();
+        let names: Vec<_> = binding.tokens().map(Token::text).collect();
+        // The names are followed by "=".
+        self.push(&names.join(" "));
+
+        let mut nodes = binding.nodes();
+        let body = nodes.next().unwrap();
+
+        // Long definitions continue on the next line.
+        let flat = self.flat(|p| p.expression(body, 0));
+        let long = flat.map_or(false, |flat| self.column() + flat.len() > WIDTH);
+        match top && long {
+            true => self.newline(2),
+            false => self.push(" "),
+        }
+
+        self.expression(body, 0);
+
+        if let Some(clause) = nodes.next() {
+            let layout = self.layout;
+            self.newline(start + 1);
+            self.push("where");
+            self.newline(start + 3);
+            self.layout = start + 4;
+            self.block(clause.nodes().next().unwrap(), |p, b| p.binding(b, false));
+            self.layout = layout;
+        }
+    }
+
+    // Prints the items of a block at the current column, each on its own line.
+    fn block(&mut self, block: &Node, f: impl Fn(&mut Self, &Node)) {
+        let column = self.column();
+
+        for (i, item) in block.nodes().enumerate() {
+            if i > 0 {
+                self.newline(column - 1);
+            }
+            f(self, item);
+        }
+    }
+
+    fn expression(&mut self, node: &Node, minimum: usize) {
+        let node = unbracketed(node);
+
+        if precedence(node) < minimum {
+            self.push("(");
+            self.expression(node, 0);
+            self.push(")");
+            return;
+        }
+
+        let mut nodes = node.nodes();
+        let mut next = || nodes.next().unwrap();
+
+        match node.kind {
+            NodeKind::Lambda => {
+                let names: Vec<_> = node.tokens().map(Token::text).collect();
+                // The names are between "\" and "->".
+                self.push("\\");
+                self.push(&names[1..names.len() - 1].join(" "));
+                self.push(" -> ");
+                self.expression(next(), 0);
+            }
+            NodeKind::Let => self.local(node),
+            NodeKind::Conditional => self.conditional(node),
+            NodeKind::Case => {
+                let indentation = self.continuation();
+                self.push("case ");
+                self.expression(next(), 0);
+                self.push(" of");
+                self.newline(indentation);
+
+                let layout = self.layout;
+                self.layout = indentation + 1;
+                self.block(next(), |p, alternative| {
+                    let mut nodes = alternative.nodes();
+                    p.push(&spaced(nodes.next().unwrap()));
+                    p.push(" -> ");
+                    p.expression(nodes.next().unwrap(), 0);
+                });
+                self.layout = layout;
+            }
+            NodeKind::Binary => {
+                let precedence = precedence(node);
+                // Comparisons don't chain, while other operators are left associative.
+                let left = if precedence == 1 { 2 } else { precedence };
+                self.expression(next(), left);
+                self.push(&format!(" {} ", operator(node)));
+                self.expression(next(), precedence + 1);
+            }
+            NodeKind::Negation => {
+                let operand = unbracketed(next());
+                self.push("-");
+                match plain(operand) {
+                    true => self.expression(operand, 4),
+                    false => {
+                        self.push("(");
+                        self.expression(operand, 0);
+                        self.push(")");
+                    }
+                }
+            }
+            NodeKind::Application => {
+                self.expression(next(), 6);
+                for argument in nodes {
+                    self.push(" ");
+                    // Ints are bracketed as arguments, so that "f (-1)" doesn't read as a
+                    // subtraction.
+                    match signed(unbracketed(argument)) {
+                        true => self.expression(argument, 7),
+                        false => self.expression(argument, 6),
+                    }
+                }
+            }
+            NodeKind::Projection => {
+                self.expression(next(), 6);
+                self.push(".");
+                self.push(node.tokens().nth(1).unwrap().text());
+            }
+            NodeKind::Tuple => {
+                self.push("(");
+                for (i, element) in node.nodes().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.expression(element, 0);
+                }
+                self.push(")");
+            }
+            NodeKind::Record => {
+                self.push("{ ");
+                for (i, field) in node.nodes().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.push(field.tokens().next().unwrap().text());
+                    self.push(" = ");
+                    self.expression(field.nodes().next().unwrap(), 0);
+                }
+                self.push(" }");
+            }
+            _ => self.push(&spaced(node)),
+        }
+    }
+
+    fn local(&mut self, node: &Node) {
+        let mut nodes = node.nodes();
+        let block = nodes.next().unwrap();
+        let body = nodes.next().unwrap();
+        let column = self.column();
+
+        let single = block.nodes().count() == 1;
+        let flat = self.flat(|p| p.binding(block.nodes().next().unwrap(), false));
+
+        match flat {
+            Some(flat) if single => {
+                self.push("let ");
+                self.push(&flat);
+                self.push(" in ");
+            }
+            _ => {
+                self.push("let ");
+                let layout = self.layout;
+                self.layout = self.column();
+                self.block(block, |p, b| p.binding(b, false));
+                self.layout = layout;
+                self.newline(column - 1);
+                self.push("in ");
+            }
+        }
+
+        self.expression(body, 0);
+    }
+
+    fn conditional(&mut self, node: &Node) {
+        let parts: Vec<_> = node.nodes().collect();
+        let flat = self.flat(|p| {
+            p.push("if ");
+            p.expression(parts[0], 0);
+            p.push(" then ");
+            p.expression(parts[1], 0);
+            p.push(" else ");
+            p.expression(parts[2], 0);
+        });
+
+        match flat {
+            Some(flat) if self.column() + flat.len() <= WIDTH => self.push(&flat),
+            _ => {
+                // Blocks within the conditional are indented past "then" and "else".
+                let indentation = self.continuation();
+                let layout = self.layout;
+                self.layout = indentation + 2;
+
+                self.push("if ");
+                self.expression(parts[0], 0);
+                self.newline(indentation);
+                self.push("then ");
+                self.expression(parts[1], 0);
+                self.newline(indentation);
+                self.push("else ");
+                self.expression(parts[2], 0);
+                self.layout = layout;
+            }
+        }
+    }
+}
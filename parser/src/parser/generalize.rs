@@ -0,0 +1,441 @@
+use super::{
+    builder,
+    context::{Context, Instance},
+    error::{Error, SprintError},
+    recursion,
+    unify::Unify,
+};
+use crate::{
+    ast::{
+        borrowed::{Expression, Variable},
+        visit_mut::{self, VisitMut},
+        Borrowed, Kind, SCALE_DECIMAL,
+    },
+    occurrences::Occurrences,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+// Definitions are inferred one group of mutually recursive definitions at a time, after the
+// definitions which they use. Uses within a group share the kind of the definition, while other
+// uses each get a fresh instance of it, so that the definition can be used polymorphically.
+//
+// Errors are collected rather than returned straight away. Uses of definitions which have errors
+// are skipped, since they would only lead to more errors.
+pub fn program<'a>(
+    definitions: impl Iterator<Item = Context<'a, Expression<'a>>>,
+    broken: &[&'a str],
+) -> Result<Context<'a, ()>, Vec<Error<'a>>> {
+    let mut program = Context::from(());
+    let mut uses = HashMap::new();
+    let mut arithmetic = HashMap::new();
+    let mut matches = HashMap::new();
+    let mut accesses = HashMap::new();
+    let mut instances = HashMap::new();
+    let mut errors = Vec::new();
+    let mut broken: HashSet<_> = broken.iter().copied().collect();
+
+    for definition in definitions {
+        let (mut definition, _) = definition.clear();
+        let duplicate = definition
+            .definitions
+            .iter()
+            .find(|(name, _)| program.definitions.contains_key(*name));
+
+        // It isn't known which of the definitions each use refers to, so they are all skipped.
+        if let Some((name, duplicate)) = duplicate {
+            let original = program.definitions[name].variable.span;
+            errors.push(
+                Error::from_sprint_error(
+                    SprintError::DuplicateDefinitionError(name),
+                    duplicate.variable.span,
+                )
+                .with_label(original, "first defined here"),
+            );
+            broken.insert(name);
+            continue;
+        }
+
+        for &name in definition.definitions.keys() {
+            uses.insert(name, definition.variables.clone());
+            arithmetic.insert(name, std::mem::take(&mut definition.arithmetic));
+            matches.insert(name, std::mem::take(&mut definition.matches));
+            accesses.insert(name, std::mem::take(&mut definition.accesses));
+            instances.insert(name, std::mem::take(&mut definition.instances));
+        }
+
+        if let Err(error) = program.unify(definition) {
+            errors.push(error);
+        }
+    }
+
+    for (&name, signature) in &program.signatures {
+        if !program.definitions.contains_key(name) && !broken.contains(name) {
+            errors.push(match name {
+                "main" => Error::from_sprint_error(SprintError::UndefinedMainError, None),
+                _ => unknown_identifier(signature),
+            });
+        }
+    }
+
+    for group in groups(&uses) {
+        let count = errors.len();
+
+        for name in &group {
+            for used in uses[name].iter().filter(|used| !broken.contains(used.name)) {
+                let definition = match program.definitions.get(used.name) {
+                    Some(definition) => &definition.variable,
+                    None => {
+                        errors.push(unknown_identifier(used));
+                        continue;
+                    }
+                };
+
+                let kind = match group.contains(&used.name) {
+                    true => definition.kind.clone(),
+                    false => Kind::instantiate(definition.kind.clone()),
+                };
+
+                if let Err(e) = kind.unify(used.kind.clone()) {
+                    let sprint_error =
+                        SprintError::TypeError(used.name, e.sprint_error.unwrap().into());
+                    let label = format!("\"{}\" is defined here", used.name);
+                    errors.push(
+                        Error::from_sprint_error(sprint_error, used.span)
+                            .with_label(definition.span, label),
+                    );
+                }
+            }
+        }
+
+        if errors.len() == count {
+            for name in &group {
+                if let Some(signature) = program.signatures.get(name) {
+                    if let Err(error) =
+                        check_signature(signature, &program.definitions[name].variable)
+                    {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+
+        // Components of tuples and records are found first, since arithmetic might use them.
+        if errors.len() == count {
+            for name in &group {
+                for access in accesses.get(name).into_iter().flatten() {
+                    if let Err(error) = builder::access(access) {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+
+        // Like scalars, arithmetic is only checked once signatures have had a chance to give the
+        // kinds of its operands.
+        if errors.len() == count {
+            for name in &group {
+                for arithmetic in arithmetic.get(name).into_iter().flatten() {
+                    if let Err(error) = builder::arithmetic(arithmetic) {
+                        errors.push(error);
+                    }
+                }
+
+                // The constructors which a case expression has to match are only known once
+                // the kind of its scrutinee is.
+                for match_ in matches.get(name).into_iter().flatten() {
+                    if let Err(error) = builder::exhaustive(match_) {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+
+        // Operators are only checked for instances once arithmetic has had a chance to give the
+        // kinds of their operands, and kinds which are still unresolved are defaulted first.
+        if errors.len() == count {
+            let instances: Vec<_> = group
+                .iter()
+                .flat_map(|name| instances.get(name).into_iter().flatten())
+                .collect();
+            let polymorphic: Vec<_> = group
+                .iter()
+                .filter_map(|name| program.signatures.get(name))
+                .flat_map(|signature| Kind::variables(signature.kind.clone()))
+                .map(Kind::simplify)
+                .collect();
+
+            for instance in &instances {
+                if let Err(error) = default_instance(instance, &instances, &polymorphic) {
+                    errors.push(error);
+                }
+            }
+
+            for instance in &instances {
+                let Instance {
+                    class,
+                    kind,
+                    operator,
+                } = instance;
+                if let Err(error) = builder::instance(class, kind.clone(), *operator) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        // Scalars are only defaulted once signatures have had a chance to give their kinds.
+        if errors.len() == count {
+            for name in &group {
+                for used in uses[name].iter().filter(|used| !broken.contains(used.name)) {
+                    if let Err(error) = check_scalar(used) {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+
+        // Recursion is only checked once the kinds of arguments are known, since only Words
+        // count down.
+        if errors.len() == count {
+            if let Err(error) = recursion::check(&group, &program.definitions) {
+                errors.push(error);
+            }
+        }
+
+        if errors.len() > count {
+            broken.extend(group);
+        }
+    }
+
+    match errors.is_empty() {
+        true => {
+            scale_decimals(&mut program);
+            Ok(program)
+        }
+        false => Err(errors),
+    }
+}
+
+// A signature can give a definition a less polymorphic kind than the one inferred for it, but
+// not a more polymorphic one.
+fn check_signature<'a>(
+    signature: &Variable<'a>,
+    definition: &Variable<'a>,
+) -> Result<(), Error<'a>> {
+    let expected = Kind::instantiate(signature.kind.clone());
+    let inferred = Kind::instantiate(definition.kind.clone());
+    let variables = Kind::variables(signature.kind.clone());
+
+    let unified = signature
+        .kind
+        .clone()
+        .unify(definition.kind.clone())
+        .is_ok();
+    let variables: Vec<_> = variables.into_iter().map(Kind::simplify).collect();
+    let general = variables.iter().enumerate().all(|(i, variable)| {
+        matches!(variable.as_ref(), Kind::Unresolved(_))
+            && !variables[..i].iter().any(|v| Rc::ptr_eq(v, variable))
+    });
+
+    if unified && general {
+        return Ok(());
+    }
+
+    let label = format!("the definition has kind {}", inferred);
+    let mismatch =
+        SprintError::MismatchedKinds(expected.as_ref().clone(), inferred.as_ref().clone());
+    let error = Error::from_sprint_error(
+        SprintError::TypeError(signature.name, mismatch.into()),
+        signature.span,
+    )
+    .with_label(definition.span, label);
+
+    Err(match unified {
+        true => error.with_help("the signature is more general than the definition"),
+        false => error,
+    })
+}
+
+// Operands of a kind which is still unresolved default to Words, or to Ints if they are negated,
+// so that `add x y = x + y` can't be used with Booleans. Kind variables of signatures are left
+// alone, since a signature can't make a definition more polymorphic than its operators allow.
+fn default_instance<'a>(
+    instance: &Instance<'a>,
+    instances: &[&Instance<'a>],
+    polymorphic: &[Rc<Kind>],
+) -> Result<(), Error<'a>> {
+    let kind = Kind::simplify(instance.kind.clone());
+
+    if !matches!(kind.as_ref(), Kind::Unresolved(_))
+        || polymorphic
+            .iter()
+            .any(|variable| Rc::ptr_eq(variable, &kind))
+    {
+        return Ok(());
+    }
+
+    let classes: Vec<_> = instances
+        .iter()
+        .filter(|other| Rc::ptr_eq(&Kind::simplify(other.kind.clone()), &kind))
+        .map(|other| &other.class)
+        .collect();
+
+    match [Kind::Word, Kind::Int]
+        .iter()
+        .find(|default| classes.iter().all(|class| class.has_instance(default)))
+    {
+        Some(default) => kind.unify(default.clone().into()),
+        None => Ok(()),
+    }
+}
+
+// scale accepts Words and Decimals, and defaults to Words for scalars of an unknown kind so that
+// it is known which primitive each use refers to. Uses are only those of top-level definitions,
+// so a use of scale is one of the primitive rather than of a local binding.
+fn check_scalar<'a>(used: &Variable<'a>) -> Result<(), Error<'a>> {
+    let scalar = match scalar(used) {
+        Some(scalar) if used.name == "scale" => scalar,
+        _ => return Ok(()),
+    };
+
+    match scalar.as_ref() {
+        Kind::Unresolved(_) => scalar.unify(Kind::Word.into()),
+        Kind::Decimal | Kind::Word => Ok(()),
+        kind => {
+            let mismatch = SprintError::MismatchedKinds(
+                Kind::Observable(Kind::Word.into()),
+                Kind::Observable(kind.clone().into()),
+            );
+
+            Err(Error::from_sprint_error(
+                SprintError::TypeError(used.name, mismatch.into()),
+                used.span,
+            )
+            .with_help("scale also accepts an Observable Decimal"))
+        }
+    }
+}
+
+// The kind of value which a use of a scaling function scales by.
+fn scalar(used: &Variable) -> Option<Rc<Kind>> {
+    let from = match Kind::simplify(used.kind.clone()).as_ref() {
+        Kind::Abstraction(from, _) => Kind::simplify(from.clone()),
+        _ => return None,
+    };
+
+    match from.as_ref() {
+        Kind::Observable(k) => Some(Kind::simplify(k.clone())),
+        _ => None,
+    }
+}
+
+fn decimal(used: &Variable) -> bool {
+    matches!(scalar(used).as_deref(), Some(Kind::Decimal))
+}
+
+// Uses of scale with Decimals refer to a primitive of their own, both in the program and among
+// the uses which decide which definitions are kept. Local bindings named scale are left alone.
+fn scale_decimals(program: &mut Context<'_, ()>) {
+    for mut used in program.variables.take("scale") {
+        if decimal(&used) {
+            used.name = SCALE_DECIMAL;
+        }
+        program.variables.insert(used);
+    }
+
+    for definition in program.definitions.values_mut() {
+        let mut scales = ScaleDecimals { bound: Vec::new() };
+        scales.visit_expression_mut(&mut definition.expression);
+    }
+}
+
+struct ScaleDecimals<'a> {
+    bound: Vec<&'a str>,
+}
+
+impl<'a> VisitMut<Borrowed<'a>> for ScaleDecimals<'a> {
+    fn visit_abstraction_mut(&mut self, argument: &mut Variable<'a>, body: &mut Expression<'a>) {
+        self.bound.push(argument.name);
+        visit_mut::visit_expression_mut(self, body);
+        self.bound.pop();
+    }
+
+    fn visit_variable_mut(&mut self, variable: &mut Variable<'a>) {
+        if variable.name == "scale" && !self.bound.contains(&variable.name) && decimal(variable) {
+            variable.name = SCALE_DECIMAL;
+        }
+    }
+}
+
+fn unknown_identifier<'a>(variable: &Variable<'a>) -> Error<'a> {
+    Error::from_sprint_error(
+        SprintError::UnknownIdentifierError(
+            variable.name,
+            Kind::simplify(variable.kind.clone()).as_ref().clone(),
+        ),
+        variable.span,
+    )
+}
+
+// Groups of mutually recursive definitions, each of which comes after the groups that it uses.
+fn groups<'a>(uses: &HashMap<&'a str, Occurrences<'a>>) -> Vec<Vec<&'a str>> {
+    let mut search = Search {
+        uses,
+        indices: Default::default(),
+        stack: Default::default(),
+        groups: Default::default(),
+    };
+
+    let mut names: Vec<_> = uses.keys().copied().collect();
+    names.sort_unstable();
+
+    for name in names {
+        if !search.indices.contains_key(name) {
+            search.visit(name);
+        }
+    }
+
+    search.groups
+}
+
+// Tarjan's algorithm, which finds each strongly connected component after all of the components
+// reachable from it.
+struct Search<'a, 'b> {
+    uses: &'b HashMap<&'a str, Occurrences<'a>>,
+    indices: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    groups: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Search<'a, '_> {
+    fn visit(&mut self, name: &'a str) -> usize {
+        let index = self.indices.len();
+        let mut lowest = index;
+
+        self.indices.insert(name, index);
+        self.stack.push(name);
+
+        let uses = self.uses;
+
+        for used in uses[name].iter() {
+            if !uses.contains_key(used.name) {
+                continue;
+            }
+
+            match self.indices.get(used.name) {
+                None => lowest = lowest.min(self.visit(used.name)),
+                Some(&i) if self.stack.contains(&used.name) => lowest = lowest.min(i),
+                Some(_) => {}
+            }
+        }
+
+        if lowest == index {
+            let start = self.stack.iter().rposition(|&n| n == name).unwrap();
+            self.groups.push(self.stack.split_off(start));
+        }
+
+        lowest
+    }
+}
@@ -0,0 +1,310 @@
+use super::{
+    error::{Error, SprintWarning, Warning},
+    Span,
+};
+use crate::ast::{
+    borrowed::{Definition, Expression, ExpressionType, Variable},
+    visit::{self, Visit},
+    Borrowed, SCALE_DECIMAL,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+/// A check for code which compiles, but is likely to be a mistake.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lint {
+    UnusedDefinition,
+    UnusedArgument,
+    Shadowing,
+    ScaleByZero,
+    DoubleGive,
+    RedundantOr,
+}
+
+impl Lint {
+    pub const ALL: [Self; 6] = [
+        Self::UnusedDefinition,
+        Self::UnusedArgument,
+        Self::Shadowing,
+        Self::ScaleByZero,
+        Self::DoubleGive,
+        Self::RedundantOr,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnusedDefinition => "unused-definition",
+            Self::UnusedArgument => "unused-argument",
+            Self::Shadowing => "shadowing",
+            Self::ScaleByZero => "scale-by-zero",
+            Self::DoubleGive => "double-give",
+            Self::RedundantOr => "redundant-or",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == name)
+            .ok_or_else(|| format!("Unknown lint `{}`", name))
+    }
+}
+
+/// What is done with the findings of a lint.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The level of each lint, which is `Warn` unless it is set otherwise.
+#[derive(Clone, Default, Debug)]
+pub struct Lints(HashMap<Lint, Level>);
+
+impl Lints {
+    pub fn set(&mut self, lint: Lint, level: Level) -> &mut Self {
+        self.0.insert(lint, level);
+        self
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.0.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+}
+
+// Lints the definitions which were parsed from the sources, splitting the findings into warnings
+// and errors by their level. Only definitions in the program itself can be unused, since modules
+// define things for others to use.
+pub(super) fn check<'a>(
+    definitions: &HashMap<&'a str, Definition<'a>>,
+    used: &HashSet<&str>,
+    program: &str,
+    sources: &[&str],
+    lints: &Lints,
+) -> (Vec<Warning<'a>>, Vec<Error<'a>>) {
+    let mut search = Search {
+        definitions,
+        bound: Vec::new(),
+        findings: Vec::new(),
+    };
+
+    let mut names: Vec<_> = definitions.keys().copied().collect();
+    names.sort_unstable();
+
+    for name in names {
+        let definition = &definitions[name];
+        let span = match definition.variable.span {
+            Some(span) if sources.iter().any(|source| within(span, source)) => span,
+            _ => continue,
+        };
+
+        let constructor = matches!(
+            definition.expression.expression,
+            ExpressionType::Constructor(..)
+        );
+        if !constructor && !used.contains(name) && within(span, program) {
+            let warning = SprintWarning::UnusedDefinition(name);
+            search.findings.push((warning, Some(span)));
+        }
+
+        search.visit_expression(&definition.expression);
+    }
+
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+
+    for (warning, span) in search.findings {
+        let level = lints.level(warning.lint());
+        let error = Error::from_sprint_warning(warning, span);
+
+        match level {
+            Level::Allow => {}
+            Level::Warn => warnings.push(Warning(error)),
+            Level::Deny => errors.push(error),
+        }
+    }
+
+    (warnings, errors)
+}
+
+// Primitives are defined with spans which are not part of any source.
+fn within(span: Span, source: &str) -> bool {
+    let start = source.as_ptr() as usize;
+    let input = span.fragment.as_ptr() as usize;
+
+    start <= input && input <= start + source.len()
+}
+
+struct Search<'a, 'b> {
+    definitions: &'b HashMap<&'a str, Definition<'a>>,
+    bound: Vec<&'a str>,
+    findings: Vec<(SprintWarning<'a>, Option<Span<'a>>)>,
+}
+
+impl<'a, 'c> Visit<'c, Borrowed<'a>> for Search<'a, '_> {
+    fn visit_expression(&mut self, expression: &'c Expression<'a>) {
+        match &expression.expression {
+            // Local definitions are desugared into applications of lambdas. Names bound by
+            // patterns can't be left out, so they aren't linted.
+            ExpressionType::Application(f, value) => match &f.expression {
+                ExpressionType::Abstraction(variable, body) => {
+                    let pattern = matches!(
+                        value.expression,
+                        ExpressionType::Field(..) | ExpressionType::Projection(..)
+                    );
+
+                    if !pattern && !occurs(variable.name, body) {
+                        let warning = SprintWarning::UnusedDefinition(variable.name);
+                        self.findings.push((warning, variable.span));
+                    }
+
+                    self.visit_expression(value);
+                    self.bind(variable.name, variable.span, body);
+                }
+                _ => self.application(expression),
+            },
+            _ => visit::visit_expression(self, expression),
+        }
+    }
+
+    fn visit_abstraction(&mut self, argument: &'c Variable<'a>, body: &'c Expression<'a>) {
+        if !occurs(argument.name, body) {
+            let warning = SprintWarning::UnusedArgument(argument.name);
+            self.findings.push((warning, argument.span));
+        }
+
+        self.bind(argument.name, argument.span, body);
+    }
+}
+
+impl<'a> Search<'a, '_> {
+    fn bind(&mut self, name: &'a str, span: Option<Span<'a>>, body: &Expression<'a>) {
+        if self.definitions.contains_key(name) {
+            let warning = SprintWarning::Shadowing(name);
+            self.findings.push((warning, span));
+        }
+
+        self.bound.push(name);
+        self.visit_expression(body);
+        self.bound.pop();
+    }
+
+    fn application(&mut self, expression: &Expression<'a>) {
+        let (head, arguments) = spine(expression);
+        let primitive = match &head.expression {
+            ExpressionType::Variable(v) if !self.bound.contains(&v.name) => Some(v.name),
+            _ => None,
+        };
+
+        let warning = match (primitive, arguments.as_slice()) {
+            (Some("scale" | SCALE_DECIMAL), [scalar, _]) if self.konst_zero(scalar) => {
+                Some(SprintWarning::ScaleByZero)
+            }
+            (Some("give"), [contract]) if self.applies(contract, "give") => {
+                Some(SprintWarning::DoubleGive)
+            }
+            (Some("or"), [left, right]) if same(left, right) => Some(SprintWarning::RedundantOr),
+            _ => None,
+        };
+
+        if let Some(warning) = warning {
+            self.findings.push((warning, head.span));
+        }
+
+        self.visit_expression(head);
+        arguments.into_iter().for_each(|e| self.visit_expression(e));
+    }
+
+    // Whether the expression is an application of the named primitive.
+    fn applies(&self, expression: &Expression, name: &str) -> bool {
+        let (head, arguments) = spine(expression);
+
+        match &head.expression {
+            ExpressionType::Variable(v) => {
+                v.name == name && !arguments.is_empty() && !self.bound.contains(&v.name)
+            }
+            _ => false,
+        }
+    }
+
+    fn konst_zero(&self, expression: &Expression) -> bool {
+        let (_, arguments) = spine(expression);
+        let zero = matches!(
+            arguments.as_slice(),
+            [e] if matches!(
+                e.expression,
+                ExpressionType::Word(0) | ExpressionType::Int(0) | ExpressionType::Decimal(0)
+            )
+        );
+
+        zero && self.applies(expression, "konst")
+    }
+}
+
+// The function of an application, along with the arguments which it is applied to.
+fn spine<'a, 'b>(
+    mut expression: &'b Expression<'a>,
+) -> (&'b Expression<'a>, Vec<&'b Expression<'a>>) {
+    let mut arguments = Vec::new();
+
+    while let ExpressionType::Application(f, argument) = &expression.expression {
+        arguments.push(argument.as_ref());
+        expression = f;
+    }
+
+    arguments.reverse();
+    (expression, arguments)
+}
+
+// Whether the name is used in the expression, other than where it is shadowed.
+fn occurs(name: &str, expression: &Expression) -> bool {
+    struct Occurs<'n>(&'n str, bool);
+
+    impl<'a> Visit<'_, Borrowed<'a>> for Occurs<'_> {
+        fn visit_abstraction(&mut self, argument: &Variable<'a>, body: &Expression<'a>) {
+            if argument.name != self.0 {
+                visit::visit_abstraction(self, argument, body);
+            }
+        }
+
+        fn visit_variable(&mut self, variable: &Variable<'a>) {
+            self.1 |= variable.name == self.0;
+        }
+    }
+
+    let mut occurs = Occurs(name, false);
+    occurs.visit_expression(expression);
+    occurs.1
+}
+
+// Whether two expressions are written the same way, for the simple expressions which are usually
+// passed to or. Anything else is treated as different.
+fn same(left: &Expression, right: &Expression) -> bool {
+    match (&left.expression, &right.expression) {
+        (ExpressionType::Application(f, a), ExpressionType::Application(g, b)) => {
+            same(f, g) && same(a, b)
+        }
+        (ExpressionType::Boolean(a), ExpressionType::Boolean(b)) => a == b,
+        (ExpressionType::Decimal(a), ExpressionType::Decimal(b)) => a == b,
+        (ExpressionType::Duration(a), ExpressionType::Duration(b)) => a == b,
+        (ExpressionType::Int(a), ExpressionType::Int(b)) => a == b,
+        (ExpressionType::Variable(a), ExpressionType::Variable(b)) => a.name == b.name,
+        (ExpressionType::Word(a), ExpressionType::Word(b)) => a == b,
+        _ => false,
+    }
+}
@@ -3,48 +3,611 @@ mod combinator;
 mod context;
 mod date;
 mod error;
+mod format;
+mod generalize;
+pub mod lint;
+mod module;
 mod primitive;
 mod program;
+mod recursion;
+mod syntax;
 mod unify;
+mod whitespace;
 
-use self::{combinator::span, error::Error, program::program};
-use crate::ast::Definitions;
-use nom::{
-    combinator::{all_consuming, complete},
-    Err,
+use self::{
+    error::{Error, Warning},
+    lint::Lints,
 };
+use crate::{ast, cst::Node};
 use nom_locate::LocatedSpan;
-use std::result;
+use std::{
+    collections::{HashMap, HashSet},
+    iter, result,
+};
 
 pub type Span<'a> = LocatedSpan<&'a str>;
 
-type Result<'a, T> = result::Result<T, Err<Error<'a>>>;
+type Result<'a, T> = result::Result<T, nom::Err<Error<'a>>>;
 
 type IResult<'a, I, O> = nom::IResult<I, O, Error<'a>>;
 
-pub fn contract<'a>(input: &'a str) -> result::Result<Definitions<'a>, Error> {
-    match span(all_consuming(complete(program)))(input) {
-        Ok((_, context)) => {
-            let variables = &context.variables;
-            Ok(context
-                .definitions
-                .into_iter()
-                .map(|(_, d)| d)
-                // TODO: giving "main" an initial count of 1 would be nicer.
-                .filter(|d| d.variable.name == "main" || variables.count(&d.variable) > 1)
-                .collect())
-        }
-        Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => Err(error),
-        _ => unreachable!(),
+pub fn contract(input: &str) -> result::Result<ast::Definitions, Vec<Error>> {
+    modules(input, &HashMap::new())
+}
+
+/// The modules imported by a program, which are passed to [`modules`] along with the modules
+/// that they import in turn.
+pub fn imports(input: &str) -> Vec<&str> {
+    let imports = module::parse(input).imports;
+    imports
+        .iter()
+        .map(|import| import.module.fragment)
+        .collect()
+}
+
+/// Parses a program along with the sources of the modules which it imports, by name.
+pub fn modules<'a>(
+    input: &'a str,
+    sources: &HashMap<&'a str, &'a str>,
+) -> result::Result<ast::Definitions, Vec<Error<'a>>> {
+    linted(input, sources, &Lints::default()).map(|(definitions, _)| definitions)
+}
+
+/// Parses a program like [`modules`], also checking it for the `lints`. Lints at the warn level
+/// are returned as warnings alongside the definitions, while denied lints are errors.
+pub fn linted<'a>(
+    input: &'a str,
+    sources: &HashMap<&'a str, &'a str>,
+    lints: &Lints,
+) -> result::Result<(ast::Definitions, Vec<Warning<'a>>), Vec<Error<'a>>> {
+    let mut names: Vec<_> = sources.keys().collect();
+    names.sort_unstable();
+
+    let files: Vec<_> = iter::once(input)
+        .chain(names.into_iter().map(|name| sources[name]))
+        .collect();
+
+    let context =
+        module::program(module::parse(input), sources).map_err(|errors| sorted(errors, &files))?;
+    // "main" is where a contract starts, so it counts as used even though nothing refers to it.
+    let mut used: HashSet<_> = context.variables.iter().map(|v| v.name).collect();
+    used.insert("main");
+    let (mut warnings, errors) = lint::check(&context.definitions, &used, input, &files, lints);
+    warnings.sort_by_key(|warning| location(&warning.0, &files));
+
+    if !errors.is_empty() {
+        return Err(sorted(errors, &files));
     }
+
+    let definitions = context
+        .definitions
+        .into_iter()
+        .map(|(_, d)| d)
+        .filter(|d| used.contains(d.variable.name))
+        .map(|d| ast::Definition::from(&d))
+        .collect();
+
+    Ok((definitions, warnings))
+}
+
+/// Parses a program into a concrete syntax tree, which prints back to exactly the input.
+pub fn syntax(input: &str) -> result::Result<Node, Error> {
+    syntax::tree(input)
+}
+
+/// Prints a program in the standard layout, which doesn't change what it means.
+pub fn format(input: &str) -> result::Result<String, Error> {
+    syntax::tree(input).map(|tree| format::module(&tree))
+}
+
+fn sorted<'a>(mut errors: Vec<Error<'a>>, files: &[&str]) -> Vec<Error<'a>> {
+    errors.sort_by_key(|error| location(error, files));
+    errors
+}
+
+fn location(error: &Error, files: &[&str]) -> Option<(Option<usize>, usize, usize)> {
+    error.nom_error.as_ref().map(|e| {
+        let file = files.iter().position(|file| e.is_within(file));
+        (file, e.line, e.column)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::{self, visit::Visit, Component, Kind};
+    use error::SprintError;
+    use std::rc::Rc;
 
     #[test]
     fn parse_contract() {
         assert!(contract("main = zero").is_ok());
+        assert!(contract("main = scale (konst (2 * 3 + 1)) one").is_ok());
+    }
+
+    #[test]
+    fn parse_comments() {
+        assert!(contract("-- a\nmain = zero -- b\n-- c").is_ok());
+        assert!(contract("main {- a -} = {- b {- c -} -} zero").is_ok());
+        assert!(contract("main :: Contract {- a\nb -}\n\nmain = one --zero").is_ok());
+        assert!(contract("main = one {- zero").is_err());
+
+        let errors = contract("{- a\n-}\n-- b\nmain = unknown").unwrap_err();
+        let nom_error = errors[0].nom_error.as_ref().unwrap();
+        assert_eq!(nom_error.line, 4);
+        assert_eq!(nom_error.column, 8);
+    }
+
+    #[test]
+    fn parse_layout() {
+        assert!(contract("main =\n  and\n    one\n\n    -- b\n    (give one)").is_ok());
+        assert!(contract("main ::\n  Contract\nmain = scale\n  (konst 2)\n  one\n").is_ok());
+        assert!(contract("main = and\none one").is_err());
+        assert!(contract("main\n= one").is_err());
+
+        let errors = contract("main =\n  and one\n    unknown").unwrap_err();
+        let nom_error = errors[0].nom_error.as_ref().unwrap();
+        assert_eq!(nom_error.line, 3);
+        assert_eq!(nom_error.column, 5);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors = contract(
+            "f x = x +\n\
+             main = and (f one) (g 1)\n\
+             g :: Word -> Bool\n\
+             g x =\n  x * 2\n\
+             h = f True\n\
+             k = 1 + True\n\
+             g = 3",
+        )
+        .unwrap_err();
+
+        let lines: Vec<_> = errors
+            .iter()
+            .map(|e| e.nom_error.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, vec![1, 3, 7, 8]);
+        assert_eq!(
+            errors[3].sprint_error,
+            Some(SprintError::DuplicateDefinitionError("g"))
+        );
+
+        let errors = contract("main = one\nmain = zero\nf = unknown").unwrap_err();
+        assert_eq!(errors.len(), 2);
+
+        let errors = contract(
+            "f = (\n\
+             -- a comment\n\
+             {- another\n\
+             \x20  comment -}\n\
+             main = one",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+
+        // A comment within a broken definition doesn't end it.
+        let errors = contract(
+            "f = (1 +\n\
+             -- a comment\n\
+             \x20 2\n\
+             {- another comment -}\n\
+             \x20 3\n\
+             main = one",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_polymorphism() {
+        let definitions = contract(
+            "twice :: (a -> a) -> a -> a\n\
+             twice f x = f (f x)\n\
+             flag :: Observable Bool\n\
+             flag = konst (twice (\\b -> b == False) True)\n\
+             main = twice give (scale (konst (twice (\\x -> x * 2) 1)) one)",
+        )
+        .unwrap();
+
+        let kind = |name| {
+            let definition = definitions.iter().find(|d| &*d.variable.name == name);
+            definition.unwrap().variable.kind.to_string()
+        };
+        assert_eq!(kind("twice"), "(a -> a) -> a -> a");
+        assert_eq!(kind("konst"), "a -> Observable a");
+
+        assert!(contract("main = give 1").is_err());
+        assert!(contract("f :: a -> b\nf x = x\nmain = f one").is_err());
+        assert!(contract("f :: a -> a\nf x = x + 1\nmain = scale (konst (f 1)) one").is_err());
+        assert!(contract("f :: Word -> Word\nf x = x\nmain = scale (konst (f 1)) one").is_ok());
+        assert!(contract("main = let f x = x in scale (konst (f 1)) (f one)").is_err());
+    }
+
+    #[test]
+    fn parse_instances() {
+        let definitions = contract(
+            "add x y = x + y\n\
+             neg x = -x\n\
+             main = scale (konst (add (toWord (neg (+1))) 2)) one",
+        )
+        .unwrap();
+
+        let kind = |name| {
+            let definition = definitions.iter().find(|d| &*d.variable.name == name);
+            definition.unwrap().variable.kind.to_string()
+        };
+        assert_eq!(kind("add"), "Word -> Word -> Word");
+        assert_eq!(kind("neg"), "Int -> Int");
+
+        let add = "add x y = x + y\nmain = ";
+        assert!(contract(&format!("{}if add True False then one else zero", add)).is_err());
+        assert!(contract(&format!("{}add one one", add)).is_err());
+        let negate = "neg :: Word -> Word\nneg x = -x\n";
+        assert!(contract(&format!("{}main = scale (konst (neg 1)) one", negate)).is_err());
+        assert!(contract("f :: a -> a -> Bool\nf x y = x < y\nmain = one").is_err());
+        assert!(contract("f x y = x == y\nmain = if f one one then one else zero").is_err());
+    }
+
+    #[test]
+    fn parse_aliases() {
+        let definitions = contract(
+            "buy :: Strike -> Notional -> Contract\n\
+             buy k n = scale k (scale (konst n) one)\n\
+             type Notional = Word\n\
+             type Strike = Observable Notional\n\
+             main = buy (konst 5) 10",
+        )
+        .unwrap();
+
+        let buy = definitions.iter().find(|d| &*d.variable.name == "buy");
+        let kind = buy.unwrap().variable.kind.to_string();
+        assert_eq!(kind, "Observable Notional -> Notional -> Contract");
+
+        let errors = contract(
+            "type Notional = Word\n\
+             f :: Notional -> Contract\n\
+             f n = scale (konst n) one\n\
+             main = f 1.5",
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::TypeError(
+                "f",
+                SprintError::MismatchedKinds(
+                    Kind::Alias(String::from("Notional").into(), Kind::Word.into()),
+                    Kind::Decimal
+                )
+                .into()
+            ))
+        );
+
+        let errors = contract("type A = B\ntype B = a\nmain = one").unwrap_err();
+        let errors: Vec<_> = errors.into_iter().map(|e| e.sprint_error).collect();
+        assert_eq!(
+            errors,
+            vec![
+                Some(SprintError::UnknownKindError("B")),
+                Some(SprintError::AliasVariableError("B"))
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_data() {
+        let definitions = contract(
+            "data OptionType = Call | Put\n\
+             data Leg = Fixed Word | Floating OptionType\n\
+             pay l = case l of\n  \
+               Fixed n -> scale (konst n) one\n  \
+               Floating o -> if o == Call then one else zero\n\
+             main = pay (Floating Put)",
+        )
+        .unwrap();
+
+        let kind = |name| {
+            let definition = definitions.iter().find(|d| &*d.variable.name == name);
+            definition.unwrap().variable.kind.to_string()
+        };
+        assert_eq!(kind("Floating"), "OptionType -> Leg");
+        assert_eq!(kind("pay"), "Leg -> Contract");
+
+        let errors = contract(
+            "data Leg = Fixed Word | Floating\n\
+             pay l = case l of\n  \
+               Fixed n -> scale (konst n) one\n\
+             main = pay Floating",
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::NonExhaustivePatternsError)
+        );
+
+        let errors = contract("data A = B | C\ndata D = C Word\nmain = one").unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::DuplicateDefinitionError("C"))
+        );
+    }
+
+    #[test]
+    fn parse_projection() {
+        let definitions = contract(
+            "type Leg = { strike :: Word, expiry :: Date }\n\
+             option :: Leg -> Contract\n\
+             option leg = after leg.expiry (scale (konst leg.strike) one)\n\
+             main = option { expiry = 2020-01-01T00:00:00Z, strike = 5 }",
+        )
+        .unwrap();
+
+        let option = definitions.iter().find(|d| &*d.variable.name == "option");
+        assert_eq!(option.unwrap().variable.kind.to_string(), "Leg -> Contract");
+
+        let errors = contract("first p = p.0\nmain = zero").unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::UnknownComponentKindError(Component::Index(0)))
+        );
+    }
+
+    #[test]
+    fn parse_recursion() {
+        assert!(contract(
+            "payments n = if n == 0 then zero else and one (payments (n - 1))\n\
+             main = payments 12"
+        )
+        .is_ok());
+        assert!(contract(
+            "monthly d = after d (and one (monthly (d + 30d)))\n\
+             main = monthly 2020-01-01T00:00:00Z"
+        )
+        .is_ok());
+
+        let errors = contract("main = and one main").unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::InfiniteContractError("main"))
+        );
+
+        let errors = contract(
+            "payments n = and one (payments (n + 1))\n\
+             main = payments 0",
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(SprintError::InfiniteContractError("payments"))
+        );
+    }
+
+    #[test]
+    fn parse_lints() {
+        use error::SprintWarning;
+        use lint::{Level, Lint};
+
+        let input = "helper x = one\n\
+                     main = let one = zero in or (give (give one)) (scale (konst 0) zero)\n\
+                     choice = or one one";
+        let lint = |lints: &Lints| {
+            linted(input, &HashMap::new(), lints).map(|(_, warnings)| {
+                let warnings = warnings.into_iter().map(|w| w.0.sprint_error.unwrap());
+                warnings.collect::<Vec<_>>()
+            })
+        };
+
+        let warning = SprintError::LintError;
+        assert_eq!(
+            lint(&Lints::default()).unwrap(),
+            vec![
+                warning(SprintWarning::UnusedDefinition("helper")),
+                warning(SprintWarning::UnusedArgument("x")),
+                warning(SprintWarning::Shadowing("one")),
+                warning(SprintWarning::DoubleGive),
+                warning(SprintWarning::ScaleByZero),
+                warning(SprintWarning::UnusedDefinition("choice")),
+                warning(SprintWarning::RedundantOr),
+            ]
+        );
+
+        let mut lints = Lints::default();
+        for &lint in &Lint::ALL {
+            lints.set(lint, Level::Allow);
+        }
+        assert!(lint(&lints).unwrap().is_empty());
+
+        lints.set(Lint::DoubleGive, Level::Deny);
+        let errors = lint(&lints).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].sprint_error,
+            Some(warning(SprintWarning::DoubleGive))
+        );
+    }
+
+    #[test]
+    fn parse_scale() {
+        let definitions = contract(
+            "half :: Contract -> Contract\n\
+             half c = scale (konst 0.5) c\n\
+             twice c = scale (konst 2) c\n\
+             main = half (twice one)",
+        )
+        .unwrap();
+
+        let names: Vec<_> = definitions.iter().map(|d| &*d.variable.name).collect();
+        assert!(names.contains(&"scale") && names.contains(&ast::SCALE_DECIMAL));
+
+        assert!(contract("main = scale (konst True) one").is_err());
+        assert!(contract(
+            "f n = scale (konst n) one\n\
+             main = f 0.5"
+        )
+        .is_err());
+        assert!(contract(
+            "f :: Decimal -> Contract\n\
+             f n = scale (konst n) one\n\
+             main = f 0.5"
+        )
+        .is_ok());
+
+        // Uses of a local binding named scale are its own, whatever they scale by.
+        struct Scales(Vec<Rc<str>>);
+
+        impl Visit<'_> for Scales {
+            fn visit_variable(&mut self, variable: &ast::Variable) {
+                if variable.name.starts_with("scale") {
+                    self.0.push(variable.name.clone());
+                }
+            }
+        }
+
+        let definitions =
+            contract("main = let scale = \\x c -> c in scale (konst 1.5) one").unwrap();
+        let names: Vec<_> = definitions.iter().map(|d| &*d.variable.name).collect();
+        assert!(!names.contains(&"scale") && !names.contains(&ast::SCALE_DECIMAL));
+
+        let mut scales = Scales(Vec::new());
+        definitions.iter().for_each(|d| scales.visit_definition(d));
+        assert_eq!(scales.0, [Rc::from("scale"), Rc::from("scale")]);
+
+        assert!(contract("main = let scale = \\x c -> c in scale (konst True) one").is_ok());
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert!(contract(
+            "issue = 2020-01-01T00:00:00Z\n\
+             notice = 1w\n\
+             main = before (issue + 30d) (after (issue - notice) one)"
+        )
+        .is_ok());
+        assert!(contract(
+            "f :: Date -> Date -> Contract\n\
+             f a b = if a - b > 2d then one else zero\n\
+             main = f 2020-01-03T00:00:00Z 2020-01-01T00:00:00Z"
+        )
+        .is_ok());
+        assert!(
+            contract("issue = 2020-01-01T00:00:00Z\nmain = before (issue + issue) one").is_err()
+        );
+    }
+
+    #[test]
+    fn parse_modules() {
+        let sources: HashMap<_, _> = vec![
+            ("Dates", "module Dates\nmaturity = 2030-01-01T00:00:00Z"),
+            ("Bonds", "module Bonds\nimport Dates (maturity)\nzcb n c = after maturity (scale (konst n) c)"),
+            ("Loop", "module Loop\nimport Main\nf = zero"),
+            ("Swaps", "module Swaps\nzcb = give one"),
+        ]
+        .into_iter()
+        .collect();
+
+        let definitions = modules("import Bonds (zcb)\nmain = zcb 1 one", &sources).unwrap();
+        assert!(definitions
+            .iter()
+            .any(|d| &*d.variable.name == "Dates.maturity"));
+        assert!(modules("import Bonds\nmain = Bonds.zcb 1 one", &sources).is_ok());
+        assert!(modules(
+            "import Bonds\nmain = let zcb = one in Bonds.zcb 1 zcb",
+            &sources
+        )
+        .is_ok());
+
+        // Modules can define the same names as each other and as the root module.
+        let definitions = modules(
+            "import Bonds\nimport Swaps (zcb)\nmain = and zcb (Bonds.zcb 1 one)",
+            &sources,
+        )
+        .unwrap();
+        let names: Vec<_> = definitions.iter().map(|d| &*d.variable.name).collect();
+        assert!(names.contains(&"Bonds.zcb") && names.contains(&"Swaps.zcb"));
+        assert!(modules(
+            "import Swaps\nzcb = one\nmain = and zcb Swaps.zcb",
+            &sources
+        )
+        .is_ok());
+
+        let errors = |input| {
+            let errors = modules(input, &sources).unwrap_err();
+            errors
+                .into_iter()
+                .map(|e| e.sprint_error.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("import Bonds\nmain = zcb 1 one"),
+            vec![SprintError::NotImportedError("zcb", "Bonds")]
+        );
+        assert_eq!(
+            errors("import Bonds\nimport Dates\nmain = Bonds.zcb 1 one"),
+            vec![SprintError::UnusedImportError("Dates")]
+        );
+        assert_eq!(
+            errors("import Bond\nmain = Bond.zcb 1 one"),
+            vec![SprintError::UnknownModuleError("Bond")]
+        );
+        assert_eq!(
+            errors("import Bonds\nmain = Bonds.maturity"),
+            vec![SprintError::MissingExportError("Bonds", "maturity")]
+        );
+        assert_eq!(
+            errors("import Loop\nmain = Loop.f"),
+            vec![
+                SprintError::CyclicImportError(vec!["Main", "Loop", "Main"]),
+                SprintError::UnusedImportError("Main"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_syntax() {
+        let inputs = [
+            "-- a\nmain = zero -- b\n-- c",
+            "main {- a -} = {- b {- c -} -} zero",
+            "module Main\r\nimport Bonds (zcb)\n\nmain = Bonds.zcb 1  one ;f x=x",
+            "f p = case p of\n  (a, b) -> a - -5\n  _ -> p.0.1\n  where c = 1",
+        ];
+
+        for input in &inputs {
+            assert_eq!(syntax(input).unwrap().to_string(), *input);
+        }
+
+        assert!(syntax("main = and\none one").is_err());
+        assert!(syntax("main = one {- zero").is_err());
+    }
+
+    #[test]
+    fn format_program() {
+        let input = "import Bonds(zcb)\nf::Word->Word\nf x=((x))*(2+1); main = Bonds.zcb (f 1) one";
+        let output = "import Bonds (zcb)\n\nf :: Word -> Word\nf x = x * (2 + 1)\n\n\
+                      main = Bonds.zcb (f 1) one\n";
+        assert_eq!(format(input).unwrap(), output);
+
+        let input =
+            "-- a\n\nmain = -(-x)   -- b\n  where x = if True then\n          1 else 2\n-- c\n";
+        let output =
+            "-- a\n\nmain = -(-x)   -- b\n  where x = if True then\n          1 else 2\n\n-- c\n";
+        assert_eq!(format(input).unwrap(), output);
+
+        let input = "f n = let a = case n of\n              0 -> 1\n              _ -> 2\n          b = a in a + b";
+        let output = [
+            "f n = let a = case n of",
+            "           0 -> 1",
+            "           _ -> 2",
+            "          b = a",
+            "      in a + b\n",
+        ];
+        assert_eq!(format(input).unwrap(), output.join("\n"));
+
+        let input = "g n = if case n of\n         0 -> True\n         _ -> False then 1 else 2";
+        let output = "g n = if case n of\n    0 -> True\n    _ -> False\n  then 1\n  else 2\n";
+        assert_eq!(format(input).unwrap(), output);
+        assert_eq!(format(output).unwrap(), output);
+
+        assert!(format("main = and\none one").is_err());
     }
 }
@@ -0,0 +1,388 @@
+use super::{
+    builder,
+    combinator::span,
+    context::Context,
+    error::{Error, SprintError},
+    program, Span,
+};
+use crate::ast::{
+    borrowed::{Expression, Variable},
+    visit_mut::VisitMut,
+    Borrowed,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+// Modules without a "module" declaration can only be the root module.
+const ROOT: &str = "Main";
+
+pub struct Module<'a> {
+    pub name: Option<Span<'a>>,
+    pub imports: Vec<Import<'a>>,
+    pub definitions: Vec<Context<'a, Expression<'a>>>,
+    pub broken: Vec<&'a str>,
+    pub errors: Vec<Error<'a>>,
+}
+
+pub struct Import<'a> {
+    pub module: Span<'a>,
+    pub names: Option<Vec<Span<'a>>>,
+}
+
+pub fn parse(source: &str) -> Module {
+    match span(program::program)(source) {
+        Ok((_, module)) => module,
+        Err(_) => unreachable!("programs recover from errors in their definitions"),
+    }
+}
+
+// The root module is linked with the modules which it imports, and the modules which they import
+// in turn. Each module can only use its own definitions, the primitives and the definitions which
+// it imports. Definitions of the root module keep their names, while those of other modules are
+// qualified by their module, as in "Bonds.zero", so that modules can define the same names.
+pub fn program<'a>(
+    root: Module<'a>,
+    sources: &HashMap<&'a str, &'a str>,
+) -> Result<Context<'a, ()>, Vec<Error<'a>>> {
+    let root_name = root.name.map_or(ROOT, |name| name.fragment);
+    let mut modules = vec![(root_name, root)];
+    let mut missing = HashSet::new();
+    let mut errors = Vec::new();
+    let mut i = 0;
+
+    while i < modules.len() {
+        let imports: Vec<_> = modules[i].1.imports.iter().map(|i| i.module).collect();
+
+        for import in imports {
+            let name = import.fragment;
+
+            if modules.iter().any(|(n, _)| *n == name) || missing.contains(name) {
+                continue;
+            }
+
+            match sources.get(name) {
+                Some(source) => {
+                    let module = parse(source);
+
+                    if let Some(declared) = module.name.filter(|n| n.fragment != name) {
+                        errors.push(Error::from_sprint_error(
+                            SprintError::ModuleNameError(name, declared.fragment),
+                            Some(declared),
+                        ));
+                    }
+
+                    modules.push((name, module));
+                }
+                None => {
+                    let help = format!("modules are read from \"{}.sprint\"", name);
+                    errors.push(
+                        Error::from_sprint_error(
+                            SprintError::UnknownModuleError(name),
+                            Some(import),
+                        )
+                        .with_help(help),
+                    );
+                    missing.insert(name);
+                }
+            }
+        }
+
+        i += 1;
+    }
+
+    for (import, cycle) in cycles(&modules) {
+        errors.push(Error::from_sprint_error(
+            SprintError::CyclicImportError(cycle),
+            Some(import),
+        ));
+    }
+
+    let mut defined: HashMap<_, Vec<_>> = HashMap::new();
+
+    for (name, module) in &modules {
+        let definitions = module.definitions.iter().flat_map(|c| c.definitions.keys());
+
+        for &definition in definitions.chain(&module.broken) {
+            defined.entry(definition).or_default().push(*name);
+        }
+    }
+
+    let mut definitions = Vec::new();
+    let mut broken = Vec::new();
+
+    for (name, module) in modules {
+        let mut scope = Scope {
+            name,
+            root: root_name,
+            imports: &module.imports,
+            used: vec![false; module.imports.len()],
+            defined: &defined,
+            missing: &missing,
+        };
+
+        for mut context in module.definitions {
+            let mut rename = Rename(HashMap::new());
+
+            for mut used in std::mem::take(&mut context.variables).iter().cloned() {
+                match scope.resolve(&used) {
+                    Ok(Some(resolved)) => {
+                        if let Some(span) = used.span.filter(|_| resolved != used.name) {
+                            rename.0.insert(span.offset, resolved);
+                        }
+                        used.name = resolved;
+                        context.variables.insert(used);
+                    }
+                    Ok(None) => {}
+                    Err(error) => errors.push(error),
+                }
+            }
+
+            context.definitions = std::mem::take(&mut context.definitions)
+                .into_values()
+                .map(|mut definition| {
+                    definition.variable.name = scope.qualify(name, definition.variable.name);
+                    rename.visit_definition_mut(&mut definition);
+                    (definition.variable.name, definition)
+                })
+                .collect();
+            context.signatures = std::mem::take(&mut context.signatures)
+                .into_values()
+                .map(|mut signature| {
+                    signature.name = scope.qualify(name, signature.name);
+                    (signature.name, signature)
+                })
+                .collect();
+
+            definitions.push(context);
+        }
+
+        errors.extend(scope.unused());
+        errors.extend(module.errors);
+        broken.extend(
+            module
+                .broken
+                .iter()
+                .map(|&broken| scope.qualify(name, broken)),
+        );
+
+        // Names imported from missing modules are already reported along with the module.
+        for import in module.imports.iter() {
+            if missing.contains(import.module.fragment) {
+                broken.extend(import.names.iter().flatten().map(|n| n.fragment));
+            }
+        }
+    }
+
+    match builder::program(definitions, &broken) {
+        Ok(program) if errors.is_empty() => Ok(program),
+        Ok(_) => Err(errors),
+        Err(type_errors) => {
+            errors.extend(type_errors);
+            Err(errors)
+        }
+    }
+}
+
+// The definitions visible within a module, along with which of its imports have been used.
+struct Scope<'a, 'b> {
+    name: &'a str,
+    root: &'a str,
+    imports: &'b [Import<'a>],
+    used: Vec<bool>,
+    defined: &'b HashMap<&'a str, Vec<&'a str>>,
+    missing: &'b HashSet<&'a str>,
+}
+
+impl<'a> Scope<'a, '_> {
+    // The name of the definition which the use refers to, or None if it should be skipped because
+    // its module is missing.
+    fn resolve(&mut self, used: &Variable<'a>) -> Result<Option<&'a str>, Error<'a>> {
+        let defined = self.defined.get(used.name).map_or(&[][..], Vec::as_slice);
+
+        let module = match used.qualifier() {
+            Some(qualifier) => qualifier,
+            // Unqualified names defined nowhere, such as primitives, need no import. Unknown names
+            // are reported along with their kinds.
+            None if defined.is_empty() => return Ok(Some(used.name)),
+            None if defined.contains(&self.name) => {
+                return Ok(Some(self.qualify(self.name, used.name)))
+            }
+            None => defined
+                .iter()
+                .copied()
+                .find(|&module| self.imports(module, used).is_some())
+                .unwrap_or(defined[0]),
+        };
+
+        let import = match (self.imports(module, used), used.qualifier()) {
+            (Some(import), _) => import,
+            (None, Some(_)) => {
+                let help = format!("import it with \"import {}\"", module);
+                let error = SprintError::UnknownModuleError(module);
+                return Err(Error::from_sprint_error(error, used.span).with_help(help));
+            }
+            (None, None) => {
+                // The likely mistake is not listing the name, rather than an unused import.
+                let imported = self
+                    .imports
+                    .iter()
+                    .position(|i| i.module.fragment == module);
+                if let Some(import) = imported {
+                    self.used[import] = true;
+                }
+
+                let error = SprintError::NotImportedError(used.name, module);
+                return Err(Error::from_sprint_error(error, used.span));
+            }
+        };
+
+        self.used[import] = true;
+
+        if self.missing.contains(module) {
+            return Ok(None);
+        }
+
+        match defined.contains(&module) {
+            true => Ok(Some(self.qualify(module, used.name))),
+            false => Err(Error::from_sprint_error(
+                SprintError::MissingExportError(module, used.name),
+                used.span,
+            )),
+        }
+    }
+
+    // The name of a definition of the module, which is only qualified outside of the root module.
+    fn qualify(&self, module: &'a str, name: &'a str) -> &'a str {
+        match module == self.root {
+            true => name,
+            false => qualified(module, name),
+        }
+    }
+
+    // The import which makes the name available, qualified or not.
+    fn imports(&self, module: &str, used: &Variable) -> Option<usize> {
+        let listed = |import: &Import| {
+            import
+                .names
+                .iter()
+                .flatten()
+                .any(|n| n.fragment == used.name)
+        };
+
+        self.imports.iter().position(|import| {
+            import.module.fragment == module && (used.qualifier().is_some() || listed(import))
+        })
+    }
+
+    // Imports which are never used, and names imported from modules which don't define them.
+    fn unused(&self) -> Vec<Error<'a>> {
+        let mut errors = Vec::new();
+
+        for (import, &used) in self.imports.iter().zip(&self.used) {
+            let module = import.module.fragment;
+
+            if self.missing.contains(module) {
+                continue;
+            }
+
+            for name in import.names.iter().flatten() {
+                let defined = self.defined.get(name.fragment);
+
+                if !defined.map_or(false, |modules| modules.contains(&module)) {
+                    errors.push(Error::from_sprint_error(
+                        SprintError::MissingExportError(module, name.fragment),
+                        Some(*name),
+                    ));
+                }
+            }
+
+            if !used {
+                errors.push(Error::from_sprint_error(
+                    SprintError::UnusedImportError(module),
+                    Some(import.module),
+                ));
+            }
+        }
+
+        errors
+    }
+}
+
+// Qualified names have to live as long as the sources which the other names borrow from, so each
+// one is made once and then kept for as long as the program runs.
+fn qualified(module: &str, name: &str) -> &'static str {
+    thread_local! {
+        static NAMES: RefCell<HashSet<&'static str>> = Default::default();
+    }
+
+    let qualified = format!("{}.{}", module, name);
+
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+
+        match names.get(qualified.as_str()) {
+            Some(&name) => name,
+            None => {
+                let name: &'static str = Box::leak(qualified.into_boxed_str());
+                names.insert(name);
+                name
+            }
+        }
+    })
+}
+
+// Renames the uses of top-level definitions, which are found by where they are in the source, so
+// that local bindings of the same names are left alone.
+struct Rename<'a>(HashMap<usize, &'a str>);
+
+impl<'a> VisitMut<Borrowed<'a>> for Rename<'a> {
+    fn visit_variable_mut(&mut self, variable: &mut Variable<'a>) {
+        if let Some(&name) = variable.span.and_then(|span| self.0.get(&span.offset)) {
+            variable.name = name;
+        }
+    }
+}
+
+// Each import which closes a cycle, along with the modules in the cycle.
+fn cycles<'a>(modules: &[(&'a str, Module<'a>)]) -> Vec<(Span<'a>, Vec<&'a str>)> {
+    fn visit<'a>(
+        index: usize,
+        modules: &[(&'a str, Module<'a>)],
+        stack: &mut Vec<&'a str>,
+        visited: &mut HashSet<&'a str>,
+        cycles: &mut Vec<(Span<'a>, Vec<&'a str>)>,
+    ) {
+        let (name, module) = &modules[index];
+        stack.push(name);
+        visited.insert(name);
+
+        for import in &module.imports {
+            let imported = import.module.fragment;
+
+            if let Some(start) = stack.iter().position(|&n| n == imported) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(imported);
+                cycles.push((import.module, cycle));
+            } else if !visited.contains(imported) {
+                if let Some(i) = modules.iter().position(|(n, _)| *n == imported) {
+                    visit(i, modules, stack, visited, cycles);
+                }
+            }
+        }
+
+        stack.pop();
+    }
+
+    let mut cycles = Vec::new();
+    visit(
+        0,
+        modules,
+        &mut Vec::new(),
+        &mut HashSet::new(),
+        &mut cycles,
+    );
+
+    cycles
+}
@@ -1,47 +1,22 @@
-#![allow(unused_parens)]
-
-use super::{
-    builder::definition, context::Context, error::SprintError, unify::Unify, Error, Result, Span,
-};
+use super::{builder, context::Context, Span};
 use crate::ast::{
-    state::{Effect, State, Transition},
-    {Class, Comparable, Date, Expression, ExpressionType, Kind, Observable, Variable},
-};
-use nom::Err;
-use phf::phf_map;
-
-type Primitive = fn(Vec<Expression>) -> Context<Expression>;
-
-pub static PRIMITIVES: phf::Map<&'static str, Primitive> = phf_map! {
-    "konst" => konst,
+    borrowed::{
+        Class, Comparable, Effect, Expression, ExpressionType, Observable, State, Transition,
+        Variable,
+    },
+    Date, Kind, Rounding, SCALE_DECIMAL,
 };
+use std::rc::Rc;
 
-macro_rules! arguments {
-    ($arguments:expr $(, $kind:expr)*) => {
-        {
-            let mut arguments = $arguments.into_iter();
-            let expressions = ($(argument(&mut arguments, $kind)),*);
-
-            assert!(
-                arguments.next().is_none(),
-                "invalid number of arguments in primitive application"
-            );
-
-            expressions
-        }
-    };
-}
-
-pub fn zero() -> Context<'static, Expression<'static>> {
+pub fn zero<'a>() -> Context<'a, Expression<'a>> {
     definition(
-        Span::new("zero"),
+        "zero",
         vec![],
-        Expression::new(ExpressionType::from(State::default()), None).into(),
+        Expression::new(ExpressionType::from(State::default()), None),
     )
-    .unwrap()
 }
 
-pub fn one() -> Context<'static, Expression<'static>> {
+pub fn one<'a>() -> Context<'a, Expression<'a>> {
     let mut transition = Transition::default();
     transition.add_effect(Effect::Withdraw);
 
@@ -49,77 +24,60 @@ pub fn one() -> Context<'static, Expression<'static>> {
     state.add_transition(transition);
 
     definition(
-        Span::new("one"),
+        "one",
         vec![],
-        Expression::new(ExpressionType::from(state), None).into(),
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn give() -> Context<'static, Expression<'static>> {
-    let next = Expression::new(
-        ExpressionType::from(Variable::new("next", Kind::State.into(), None)),
-        None,
-    );
+pub fn give<'a>() -> Context<'a, Expression<'a>> {
+    let next = Variable::new("next", Kind::State.into(), None);
 
     let mut transition = Transition::default();
-    transition.add_effect(Effect::Flip).set_next(next);
+    transition.add_effect(Effect::Flip).set_next(use_of(&next));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("give"),
-        vec![Span::new("next")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "give",
+        vec![next],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn and() -> Context<'static, Expression<'static>> {
-    let left = Expression::new(
-        ExpressionType::from(Variable::new("left", Kind::State.into(), None)),
-        None,
-    );
-
-    let right = Expression::new(
-        ExpressionType::from(Variable::new("right", Kind::State.into(), None)),
-        None,
-    );
+pub fn and<'a>() -> Context<'a, Expression<'a>> {
+    let left = Variable::new("left", Kind::State.into(), None);
+    let right = Variable::new("right", Kind::State.into(), None);
 
     let mut transition = Transition::default();
-    transition.add_effect(Effect::Spawn(right)).set_next(left);
+    transition
+        .add_effect(Effect::Spawn(use_of(&right)))
+        .set_next(use_of(&left));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("and"),
-        vec![Span::new("left"), Span::new("right")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "and",
+        vec![left, right],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn or() -> Context<'static, Expression<'static>> {
-    let left = Expression::new(
-        ExpressionType::from(Variable::new("left", Kind::State.into(), None)),
-        None,
-    );
-    let right = Expression::new(
-        ExpressionType::from(Variable::new("right", Kind::State.into(), None)),
-        None,
-    );
+pub fn or<'a>() -> Context<'a, Expression<'a>> {
+    let left = Variable::new("left", Kind::State.into(), None);
+    let right = Variable::new("right", Kind::State.into(), None);
 
     let mut left_transition = Transition::default();
     left_transition
-        .add_condition(Expression::new(Observable::IsParty.into(), left.span))
-        .set_next(left);
+        .add_condition(Expression::new(Observable::IsParty.into(), None))
+        .set_next(use_of(&left));
 
     let mut right_transition = Transition::default();
     right_transition
-        .add_condition(Expression::new(Observable::IsParty.into(), right.span))
-        .set_next(right);
+        .add_condition(Expression::new(Observable::IsParty.into(), None))
+        .set_next(use_of(&right));
 
     let mut state = State::default();
     state
@@ -127,155 +85,185 @@ pub fn or() -> Context<'static, Expression<'static>> {
         .add_transition(right_transition);
 
     definition(
-        Span::new("or"),
-        vec![Span::new("left"), Span::new("right")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "or",
+        vec![left, right],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn before() -> Context<'static, Expression<'static>> {
+pub fn before<'a>() -> Context<'a, Expression<'a>> {
     let now = Expression::new(ExpressionType::Date(Date::Now), None);
-
-    let date = Expression::new(
-        ExpressionType::from(Variable::new("date", Kind::Date.into(), None)),
-        None,
-    );
-
-    let next = Expression::new(
-        ExpressionType::from(Variable::new("next", Kind::State.into(), None)),
-        None,
-    );
+    let date = Variable::new("date", Kind::Date.into(), None);
+    let next = Variable::new("next", Kind::State.into(), None);
 
     let mut transition = Transition::default();
     transition
         .add_condition(Expression::new(
-            ExpressionType::Class(Class::Comparable(Comparable::Less(now.into(), date.into()))),
+            ExpressionType::Class(Class::Comparable(Comparable::Less(
+                now.into(),
+                use_of(&date).into(),
+            ))),
             None,
         ))
-        .set_next(next);
+        .set_next(use_of(&next));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("before"),
-        vec![Span::new("date"), Span::new("next")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "before",
+        vec![date, next],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn after() -> Context<'static, Expression<'static>> {
+pub fn after<'a>() -> Context<'a, Expression<'a>> {
     let now = Expression::new(ExpressionType::Date(Date::Now), None);
-
-    let date = Expression::new(
-        ExpressionType::from(Variable::new("date", Kind::Date.into(), None)),
-        None,
-    );
-
-    let next = Expression::new(
-        ExpressionType::from(Variable::new("next", Kind::State.into(), None)),
-        None,
-    );
+    let date = Variable::new("date", Kind::Date.into(), None);
+    let next = Variable::new("next", Kind::State.into(), None);
 
     let mut transition = Transition::default();
     transition
         .add_condition(Expression::new(
             ExpressionType::Class(Class::Comparable(Comparable::Greater(
                 now.into(),
-                date.into(),
+                use_of(&date).into(),
             ))),
             None,
         ))
-        .set_next(next);
+        .set_next(use_of(&next));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("after"),
-        vec![Span::new("date"), Span::new("next")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "after",
+        vec![date, next],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn scale() -> Context<'static, Expression<'static>> {
-    let scalar = Expression::new(
-        ExpressionType::from(Variable::new(
-            "scalar",
-            Kind::Observable(Kind::Word.into()).into(),
-            None,
-        )),
-        None,
-    );
+// scale :: Observable a -> Contract -> Contract, for Words and Decimals
+pub fn scale<'a>() -> Context<'a, Expression<'a>> {
+    scaling("scale", Default::default())
+}
 
-    let next = Expression::new(
-        ExpressionType::from(Variable::new("next", Kind::State.into(), None)),
-        None,
-    );
+// Uses of scale with Decimals refer to this primitive, which can't be named in programs.
+pub fn scale_decimal<'a>() -> Context<'a, Expression<'a>> {
+    scaling(SCALE_DECIMAL, Kind::Decimal.into())
+}
+
+fn scaling<'a>(name: &'static str, kind: Rc<Kind>) -> Context<'a, Expression<'a>> {
+    let scalar = Variable::new("scalar", Kind::Observable(kind).into(), None);
+    let next = Variable::new("next", Kind::State.into(), None);
 
     let mut transition = Transition::default();
-    transition.add_effect(Effect::Scale(scalar)).set_next(next);
+    transition
+        .add_effect(Effect::Scale(use_of(&scalar)))
+        .set_next(use_of(&next));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("scale"),
-        vec![Span::new("scalar"), Span::new("next")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        name,
+        vec![scalar, next],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn anytime() -> Context<'static, Expression<'static>> {
-    let next = Expression::new(
-        ExpressionType::from(Variable::new("next", Kind::State.into(), None)),
-        None,
-    );
+pub fn anytime<'a>() -> Context<'a, Expression<'a>> {
+    let next = Variable::new("next", Kind::State.into(), None);
 
     let mut transition = Transition::default();
     transition
-        .add_condition(Expression::new(Observable::IsParty.into(), next.span))
-        .set_next(next);
+        .add_condition(Expression::new(Observable::IsParty.into(), None))
+        .set_next(use_of(&next));
 
     let mut state = State::default();
     state.add_transition(transition);
 
     definition(
-        Span::new("anytime"),
-        vec![Span::new("next")],
-        Expression::new(ExpressionType::from(state), None).into(),
+        "anytime",
+        vec![next],
+        Expression::new(ExpressionType::from(state), None),
     )
-    .unwrap()
 }
 
-pub fn konst(arguments: Vec<Expression>) -> Context<Expression> {
-    let value = arguments!(arguments, Kind::default()).unwrap();
+// konst :: a -> Observable a
+pub fn konst<'a>() -> Context<'a, Expression<'a>> {
+    let value = Variable::new("value", Default::default(), None);
+    let konst = Observable::from(use_of(&value));
+
+    definition(
+        "konst",
+        vec![value],
+        Expression::new(ExpressionType::from(konst), None),
+    )
+}
+
+// toInt :: Word -> Int
+pub fn to_int<'a>() -> Context<'a, Expression<'a>> {
+    conversion("toInt", Kind::Word, Kind::Int)
+}
+
+// toWord :: Int -> Word
+pub fn to_word<'a>() -> Context<'a, Expression<'a>> {
+    conversion("toWord", Kind::Int, Kind::Word)
+}
+
+// Conversions fail when the value is out of the range of the other kind.
+fn conversion<'a>(name: &'static str, from: Kind, to: Kind) -> Context<'a, Expression<'a>> {
+    let value = Variable::new("value", from.into(), None);
+    let conversion = ExpressionType::Conversion(to.into(), use_of(&value).into());
+
+    definition(name, vec![value], Expression::new(conversion, None))
+}
+
+// toDecimal :: Word -> Decimal
+pub fn to_decimal<'a>() -> Context<'a, Expression<'a>> {
+    conversion("toDecimal", Kind::Word, Kind::Decimal)
+}
+
+// floor :: Decimal -> Word
+pub fn floor<'a>() -> Context<'a, Expression<'a>> {
+    rounding("floor", Rounding::Down)
+}
+
+// ceiling :: Decimal -> Word
+pub fn ceiling<'a>() -> Context<'a, Expression<'a>> {
+    rounding("ceiling", Rounding::Up)
+}
+
+// round :: Decimal -> Word, rounding halves up
+pub fn round<'a>() -> Context<'a, Expression<'a>> {
+    rounding("round", Rounding::Nearest)
+}
+
+fn rounding<'a>(name: &'static str, rounding: Rounding) -> Context<'a, Expression<'a>> {
+    let value = Variable::new("value", Kind::Decimal.into(), None);
+    let round = ExpressionType::Round(rounding, use_of(&value).into());
+
+    definition(name, vec![value], Expression::new(round, None))
+}
 
-    Expression::new(ExpressionType::Observable(value.clone().into()), value.span).into()
+fn use_of<'a>(variable: &Variable<'a>) -> Expression<'a> {
+    Expression::new(ExpressionType::from(variable.clone()), None)
 }
 
-fn argument<'a>(
-    arguments: &mut impl Iterator<Item = Expression<'a>>,
-    kind: Kind,
-) -> Result<'a, Expression<'a>> {
-    let argument = match arguments.next() {
-        Some(argument) => argument,
-        None => {
-            return Err(Err::Error(Error::from_sprint_error(
-                SprintError::InvalidNumberArgsError,
-                None,
-            )));
-        }
-    };
-    argument
-        .expression
-        .kind()
-        .unify(kind.into())
-        .map_err(Err::Error)?;
-
-    Ok(argument)
+// The arguments of primitives are used within their states rather than parsed, so their uses
+// are added here to give the arguments the right kinds.
+fn definition<'a>(
+    name: &'a str,
+    arguments: Vec<Variable<'a>>,
+    expression: Expression<'a>,
+) -> Context<'a, Expression<'a>> {
+    let spans = arguments.iter().map(|a| Span::new(a.name)).collect();
+    let mut context = Context::from(expression);
+
+    for argument in arguments {
+        context.variables.insert(argument);
+    }
+
+    builder::definition(Span::new(name), spans, context).unwrap()
 }
@@ -1,112 +1,1205 @@
 use super::{
-    builder,
+    builder::{self, Alternative, Binding, Pattern},
     combinator::{brackets1, padding0},
     context::Context,
     date::date,
-    IResult, Span,
+    error::{Error, SprintError},
+    module::{Import, Module},
+    whitespace::{aligned, comment, multispace0, multispace1, space0, space1},
+    IResult, Result, Span,
+};
+use crate::ast::{
+    borrowed::{Component, Expression, ExpressionType},
+    Data, Kind, DECIMAL_PLACES,
 };
-use crate::ast::{Expression, ExpressionType, Kind};
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while1},
-    character::complete::{alphanumeric0, digit1, line_ending, multispace1, space1},
-    combinator::{map, map_res, peek, recognize},
-    multi::{many0, many1, separated_list},
-    sequence::{pair, preceded, separated_pair},
+    character::complete::{
+        alphanumeric0, alphanumeric1, anychar, char, digit1, hex_digit1, line_ending,
+    },
+    combinator::{map, map_res, not, opt, peek, recognize, verify},
+    multi::{many0, many1, separated_nonempty_list},
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
+    Err, Slice,
 };
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+const MICROLIBRA: u64 = 1_000_000;
+
+// Top-level definitions and declarations start at the beginning of a line.
+const TOP_LEVEL: usize = 1;
+
+pub(super) const KEYWORDS: [&str; 12] = [
+    "case", "data", "else", "if", "import", "in", "let", "module", "of", "then", "type", "where",
+];
+
+// Definitions which can't be parsed are skipped, so that the errors in all of the definitions
+// are reported at once.
+pub fn program(input: Span) -> IResult<Span, Module> {
+    let (input, _) = multispace0(input)?;
+    let header = preceded(
+        terminated(keyword("module"), space1(TOP_LEVEL)),
+        module_name,
+    );
+    let (input, name) = opt(terminated(header, end))(input)?;
+    let (mut input, imports) = many0(terminated(import, end))(input)?;
+
+    let (aliases, constructors, mut errors) = declarations(input);
+    let mut contexts = Vec::new();
+    let mut broken = Vec::new();
+
+    for (name, data) in constructors {
+        match builder::constructor(name, data) {
+            Ok(context) => contexts.push(context),
+            Err(Err::Error(error)) | Err(Err::Failure(error)) => errors.push(error),
+            Err(Err::Incomplete(_)) => unreachable!(),
+        }
+    }
+
+    while !input.fragment.is_empty() {
+        // Type aliases and data types have already been parsed, along with any errors in them.
+        if let Ok((rest, _)) = terminated(|input| declaration(input, &aliases), end)(input) {
+            input = rest;
+            continue;
+        } else if alt((keyword("type"), keyword("data")))(input).is_ok() {
+            input = recover(input);
+            continue;
+        }
+
+        let signature = |input| signature(input, &aliases);
+        match terminated(alt((signature, definition)), end)(input) {
+            Ok((rest, context)) => {
+                contexts.push(context);
+                input = rest;
+            }
+            Err(Err::Error(error)) | Err(Err::Failure(error)) => {
+                if let Ok((_, name)) = identifier(input) {
+                    broken.push(name.fragment);
+                }
+
+                errors.push(error);
+                input = recover(input);
+            }
+            Err(Err::Incomplete(_)) => unreachable!(),
+        }
+    }
+
+    let module = Module {
+        name,
+        imports,
+        definitions: contexts,
+        broken,
+        errors,
+    };
+
+    Ok((input, module))
+}
+
+fn import(input: Span) -> IResult<Span, Import> {
+    let (input, _) = terminated(keyword("import"), space1(TOP_LEVEL))(input)?;
+    let (input, module) = module_name(input)?;
+
+    let names = delimited(
+        padding0(TOP_LEVEL, char('(')),
+        separated_nonempty_list(padding0(TOP_LEVEL, char(',')), identifier),
+        padding0(TOP_LEVEL, char(')')),
+    );
+    let (input, names) = opt(names)(input)?;
+
+    Ok((input, Import { module, names }))
+}
+
+// Definitions are separated by line endings or ";", and the last one can be followed by
+// anything which would separate them.
+fn end(input: Span) -> IResult<Span, Span> {
+    let separator = alt((
+        preceded(space0(TOP_LEVEL), line_ending),
+        padding0(TOP_LEVEL, tag(";")),
+    ));
+    let last = terminated(space0(TOP_LEVEL), not(anychar));
+
+    terminated(
+        alt((recognize(many1(separator)), last)),
+        many0(alt((multispace1, tag(";")))),
+    )(input)
+}
+
+// Skips to the start of the next line which isn't indented, which starts the next definition.
+// Comments at the start of a line may still be within the broken definition, so they are
+// skipped along with blank lines.
+fn recover(mut input: Span) -> Span {
+    loop {
+        let fragment = input.fragment;
+        let next = fragment.find('\n').map_or(fragment.len(), |i| i + 1);
+        input = input.slice(next..);
+
+        match input.fragment.chars().next() {
+            None => return input,
+            Some(c) if c.is_whitespace() => continue,
+            Some(_) => {}
+        }
+
+        match comment::<_, Error>(input) {
+            Ok((rest, _)) => input = rest,
+            Err(_) => return input,
+        }
+    }
+}
+
+// Type aliases and data types can be used anywhere in a module, so they are parsed before
+// anything else. Each one can use those declared before it, which rules out cycles.
+fn declarations(mut input: Span) -> (Aliases, Constructors, Vec<Error>) {
+    let mut aliases = Aliases::new();
+    let mut constructors = Constructors::new();
+    let mut errors = Vec::new();
+
+    while !input.fragment.is_empty() {
+        if alt((keyword("type"), keyword("data")))(input).is_err() {
+            input = recover(input);
+            continue;
+        }
+
+        let parsed = terminated(|input| declaration(input, &aliases), end)(input);
+        match parsed {
+            Ok((rest, (name, kind, names))) => {
+                let builtin = KINDS.contains(&name.fragment);
+                match aliases.get(name.fragment) {
+                    Some((original, _)) => errors.push(duplicate(name, Some(*original))),
+                    None if builtin => errors.push(duplicate(name, None)),
+                    None => {
+                        aliases.insert(name.fragment, (name, kind.clone()));
+                    }
+                }
 
-pub fn program(input: Span) -> IResult<Span, Context<()>> {
-    let separator = alt((line_ending, padding0(tag(";"))));
-    let (input, contexts) = separated_list(many1(separator), alt((signature, definition)))(input)?;
-    let (input, _) = many0(alt((multispace1, tag(";"))))(input)?;
+                for name in names {
+                    let original = constructors
+                        .iter()
+                        .find(|(c, _)| c.fragment == name.fragment);
+                    match original {
+                        Some((original, _)) => errors.push(duplicate(name, Some(*original))),
+                        None => constructors.push((name, kind.clone())),
+                    }
+                }
 
-    Ok((input, builder::program(contexts)?))
+                input = rest;
+            }
+            Err(Err::Error(error)) | Err(Err::Failure(error)) => {
+                errors.push(error);
+                input = recover(input);
+            }
+            Err(Err::Incomplete(_)) => unreachable!(),
+        }
+    }
+
+    (aliases, constructors, errors)
+}
+
+fn duplicate<'a>(name: Span<'a>, original: Option<Span<'a>>) -> Error<'a> {
+    let error = Error::from_sprint_error(
+        SprintError::DuplicateDefinitionError(name.fragment),
+        Some(name),
+    );
+
+    match original {
+        Some(original) => error.with_label(Some(original), "first defined here"),
+        None => error,
+    }
+}
+
+// A type alias or a data type, along with the constructors of a data type.
+fn declaration<'a>(
+    input: Span<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, (Span<'a>, Rc<Kind>, Vec<Span<'a>>)> {
+    alt((
+        map(
+            |input| alias(input, aliases),
+            |(name, kind)| (name, kind, Vec::new()),
+        ),
+        |input| data(input, aliases),
+    ))(input)
 }
 
-pub fn signature(input: Span) -> IResult<Span, Context<Expression>> {
+fn alias<'a>(
+    input: Span<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, (Span<'a>, Rc<Kind>)> {
+    let (input, _) = terminated(keyword("type"), space1(TOP_LEVEL))(input)?;
+    let (input, name) = module_name(input)?;
+    let (input, _) = padding0(TOP_LEVEL, tag("="))(input)?;
+
+    let variables = KindVariables::default();
+    let (input, kind) = kind(input, &variables, aliases)?;
+
+    if !variables.borrow().is_empty() {
+        return Err(Err::Failure(Error::from_sprint_error(
+            SprintError::AliasVariableError(name.fragment),
+            Some(name),
+        )));
+    }
+
+    let alias = Kind::Alias(name.fragment.to_string().into(), kind);
+    Ok((input, (name, alias.into())))
+}
+
+// "data Leg = Fixed Word | Floating" declares a data type with a constructor for each
+// alternative, which is followed by the kinds of its fields.
+fn data<'a>(
+    input: Span<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, (Span<'a>, Rc<Kind>, Vec<Span<'a>>)> {
+    let (input, _) = terminated(keyword("data"), space1(TOP_LEVEL))(input)?;
+    let (input, name) = module_name(input)?;
+    let (input, _) = padding0(TOP_LEVEL, tag("="))(input)?;
+
+    let variables = KindVariables::default();
+    let field = preceded(space1(TOP_LEVEL), |input| {
+        kind_primitive(input, &variables, aliases)
+    });
+    let alternative = pair(constructor, many0(field));
+    let separator = padding0(TOP_LEVEL, tag("|"));
+    let (input, alternatives) = separated_nonempty_list(separator, alternative)(input)?;
+
+    if !variables.borrow().is_empty() {
+        return Err(Err::Failure(Error::from_sprint_error(
+            SprintError::AliasVariableError(name.fragment),
+            Some(name),
+        )));
+    }
+
+    let names = alternatives
+        .iter()
+        .map(|(constructor, _)| *constructor)
+        .collect();
+    let data = Data {
+        name: name.fragment.to_string(),
+        constructors: alternatives
+            .into_iter()
+            .map(|(constructor, fields)| (constructor.fragment.to_string(), fields))
+            .collect(),
+    };
+
+    Ok((input, (name, Kind::Data(data.into()).into(), names)))
+}
+
+pub fn signature<'a>(
+    input: Span<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, Context<'a, Expression<'a>>> {
     let (input, identifier) = identifier(input)?;
-    let (input, _) = padding0(tag("::"))(input)?;
-    let (input, kind) = kind(input)?;
+    let (input, _) = padding0(TOP_LEVEL, tag("::"))(input)?;
+    let (input, kind) = kind(input, &Default::default(), aliases)?;
 
     Ok((input, builder::signature(identifier, kind)?))
 }
 
-pub fn kind(input: Span) -> IResult<Span, Kind> {
+// Lowercase names in a kind are variables, which are the same kind wherever they appear in it.
+type KindVariables<'a> = RefCell<HashMap<&'a str, Rc<Kind>>>;
+
+// Type aliases and data types by name, along with where they are declared.
+type Aliases<'a> = HashMap<&'a str, (Span<'a>, Rc<Kind>)>;
+
+// Constructors of data types, along with the data type which each one makes.
+type Constructors<'a> = Vec<(Span<'a>, Rc<Kind>)>;
+
+const KINDS: [&str; 8] = [
+    "Bool",
+    "Contract",
+    "Date",
+    "Decimal",
+    "Duration",
+    "Int",
+    "Observable",
+    "Word",
+];
+
+pub fn kind<'a>(
+    input: Span<'a>,
+    variables: &KindVariables<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, Rc<Kind>> {
+    let kind_primitive = |input| kind_primitive(input, variables, aliases);
+    let kind = |input| kind(input, variables, aliases);
+
     alt((
         map(
-            separated_pair(kind_primitive, padding0(tag("->")), kind),
-            |(from, to)| Kind::Abstraction(from.into(), to.into()),
+            separated_pair(kind_primitive, padding0(TOP_LEVEL, tag("->")), kind),
+            |(from, to)| Kind::Abstraction(from, to).into(),
         ),
         kind_primitive,
     ))(input)
 }
 
-pub fn kind_primitive(input: Span) -> IResult<Span, Kind> {
+pub fn kind_primitive<'a>(
+    input: Span<'a>,
+    variables: &KindVariables<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, Rc<Kind>> {
+    let kind_primitive = |input| kind_primitive(input, variables, aliases);
+    let kind = |input| kind(input, variables, aliases);
+
     alt((
         brackets1(kind),
-        map(tag("Bool"), |_| Kind::Boolean),
-        map(preceded(pair(tag("Observable"), separator), kind), |k| {
-            Kind::Observable(k.into())
+        map(
+            delimited(
+                char('('),
+                separated_list2(padding0(TOP_LEVEL, char(',')), padding0(TOP_LEVEL, kind)),
+                char(')'),
+            ),
+            |kinds| Kind::Tuple(kinds.into()).into(),
+        ),
+        |input| record_kind(input, variables, aliases),
+        map(keyword("Bool"), |_| Kind::Boolean.into()),
+        map(
+            preceded(
+                pair(tag("Observable"), |input| separator(input, TOP_LEVEL)),
+                kind_primitive,
+            ),
+            |k| Kind::Observable(k).into(),
+        ),
+        map(keyword("Contract"), |_| Kind::State.into()),
+        map(keyword("Date"), |_| Kind::Date.into()),
+        map(keyword("Decimal"), |_| Kind::Decimal.into()),
+        map(keyword("Duration"), |_| Kind::Duration.into()),
+        map(keyword("Int"), |_| Kind::Int.into()),
+        map(keyword("Word"), |_| Kind::Word.into()),
+        |input| {
+            let (input, name) = module_name(input)?;
+            match aliases.get(name.fragment) {
+                Some((_, alias)) => Ok((input, alias.clone())),
+                None => Err(Err::Failure(Error::from_sprint_error(
+                    SprintError::UnknownKindError(name.fragment),
+                    Some(name),
+                ))),
+            }
+        },
+        map(identifier, |name| {
+            let mut variables = variables.borrow_mut();
+            variables.entry(name.fragment).or_default().clone()
         }),
-        map(tag("Contract"), |_| Kind::State),
-        map(tag("Word"), |_| Kind::Word),
     ))(input)
 }
 
+// "{ strike :: Word, expiry :: Date }" is the kind of a record, whose fields are sorted by name.
+fn record_kind<'a>(
+    input: Span<'a>,
+    variables: &KindVariables<'a>,
+    aliases: &Aliases<'a>,
+) -> IResult<'a, Span<'a>, Rc<Kind>> {
+    let kind = |input| kind(input, variables, aliases);
+    let field = separated_pair(
+        padding0(TOP_LEVEL, identifier),
+        tag("::"),
+        padding0(TOP_LEVEL, kind),
+    );
+    let (input, mut fields) = delimited(
+        terminated(char('{'), not(char('-'))),
+        separated_nonempty_list(char(','), field),
+        char('}'),
+    )(input)?;
+
+    fields.sort_by_key(|(name, _)| name.fragment);
+    unique(fields.iter().map(|(name, _)| *name))?;
+
+    let fields = fields
+        .into_iter()
+        .map(|(name, kind)| (name.fragment.to_string(), kind));
+    Ok((input, Kind::Record(fields.collect()).into()))
+}
+
+// Reports the second of any two adjacent names which are the same.
+fn unique<'a>(names: impl Iterator<Item = Span<'a>>) -> Result<'a, ()> {
+    let mut previous: Option<Span> = None;
+
+    for name in names {
+        match previous {
+            Some(first) if first.fragment == name.fragment => {
+                return Err(Err::Failure(duplicate(name, Some(first))))
+            }
+            _ => previous = Some(name),
+        }
+    }
+
+    Ok(())
+}
+
 pub fn definition(input: Span) -> IResult<Span, Context<Expression>> {
+    let (input, (id, arguments, expression)) = binding(input, TOP_LEVEL)?;
+
+    Ok((input, builder::definition(id, arguments, expression)?))
+}
+
+// A binding in the block whose lines start at `layout`, which is continued by any lines indented
+// further than that.
+fn binding(input: Span, layout: usize) -> IResult<Span, Binding> {
     let (input, id) = identifier(input)?;
 
-    let argument = preceded(multispace1, identifier);
+    let argument = preceded(space1(layout), identifier);
     let (input, arguments) = many0(argument)(input)?;
 
-    let (input, _) = padding0(tag("="))(input)?;
-    let (input, expression) = expression(input)?;
+    let (input, _) = padding0(layout, tag("="))(input)?;
+    let (input, expression) = expression(input, layout)?;
 
-    Ok((input, builder::definition(id, arguments, expression)?))
+    let (input, bindings) = opt(preceded(
+        delimited(space1(layout), keyword("where"), space1(layout)),
+        bindings,
+    ))(input)?;
+
+    let expression = match bindings {
+        Some(bindings) => builder::local(bindings, expression)?,
+        None => expression,
+    };
+
+    Ok((input, (id, arguments, expression)))
 }
 
-pub fn expression(input: Span) -> IResult<Span, Context<Expression>> {
-    alt((application, term))(input)
+fn bindings(input: Span) -> IResult<Span, Vec<Binding>> {
+    // Bindings after the first one must start in the same column, unless separated by ";".
+    let column = input.get_column();
+    let separator = alt((aligned(column), padding0(column, tag(";"))));
+    separated_nonempty_list(separator, move |input| binding(input, column))(input)
 }
 
-pub fn application(input: Span) -> IResult<Span, Context<Expression>> {
-    let (input, identifier) = identifier(input)?;
+pub fn expression(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    alt((
+        |input| lambda(input, layout),
+        |input| local(input, layout),
+        |input| conditional(input, layout),
+        |input| case(input, layout),
+        |input| comparison(input, layout),
+    ))(input)
+}
+
+fn lambda(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, _) = terminated(char('\\'), space0(layout))(input)?;
+    let (input, arguments) = separated_nonempty_list(space1(layout), identifier)(input)?;
+    let (input, _) = padding0(layout, tag("->"))(input)?;
+    let (input, expression) = expression(input, layout)?;
+
+    Ok((input, builder::abstraction(arguments, expression)?))
+}
+
+fn local(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, _) = terminated(keyword("let"), space1(layout))(input)?;
+    let (input, bindings) = bindings(input)?;
+    let (input, _) = delimited(space1(layout), keyword("in"), space1(layout))(input)?;
+    let (input, expression) = expression(input, layout)?;
+
+    Ok((input, builder::local(bindings, expression)?))
+}
+
+fn conditional(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, span) = terminated(keyword("if"), space1(layout))(input)?;
+    let (input, condition) = expression(input, layout)?;
+    let (input, _) = delimited(space1(layout), keyword("then"), space1(layout))(input)?;
+    let (input, then) = expression(input, layout)?;
+    let (input, _) = delimited(space1(layout), keyword("else"), space1(layout))(input)?;
+    let (input, otherwise) = expression(input, layout)?;
+
+    Ok((
+        input,
+        builder::conditional(span, condition, then, otherwise)?,
+    ))
+}
+
+fn case(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, span) = terminated(keyword("case"), space1(layout))(input)?;
+    let (input, scrutinee) = expression(input, layout)?;
+    let (input, _) = delimited(space1(layout), keyword("of"), space1(layout))(input)?;
+    let (input, alternatives) = alternatives(input)?;
+
+    Ok((input, builder::case(span, scrutinee, alternatives)?))
+}
+
+fn alternatives(input: Span) -> IResult<Span, Vec<Alternative>> {
+    // Like bindings, alternatives are aligned in a block or separated by ";".
+    let column = input.get_column();
+    let fields = many0(preceded(space1(column), identifier));
+    let pattern = alt((
+        map(tag("_"), |_| Pattern::Wildcard),
+        map(alt((boolean, word)), Pattern::Literal),
+        map(pair(constructor, fields), |(constructor, fields)| {
+            Pattern::Constructor(constructor, fields)
+        }),
+        map(
+            delimited(
+                char('('),
+                separated_list2(padding0(column, char(',')), padding0(column, identifier)),
+                char(')'),
+            ),
+            Pattern::Tuple,
+        ),
+        map(
+            delimited(
+                terminated(char('{'), not(char('-'))),
+                separated_nonempty_list(
+                    char(','),
+                    separated_pair(
+                        padding0(column, identifier),
+                        char('='),
+                        padding0(column, identifier),
+                    ),
+                ),
+                char('}'),
+            ),
+            Pattern::Record,
+        ),
+    ));
+    let alternative = separated_pair(pattern, padding0(column, tag("->")), move |input| {
+        expression(input, column)
+    });
+
+    let separator = alt((aligned(column), padding0(column, tag(";"))));
+    separated_nonempty_list(separator, alternative)(input)
+}
+
+fn comparison(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    // Comparisons are non-associative, so at most one is allowed without brackets.
+    let operator = alt((
+        tag("=="),
+        tag("/="),
+        tag(">="),
+        tag("<="),
+        tag(">"),
+        tag("<"),
+    ));
+
+    let (input, left) = sum(input, layout)?;
+    let operator = delimited(space0(layout), operator, space0(layout));
+
+    match opt(pair(operator, |input| sum(input, layout)))(input)? {
+        (input, Some((operator, right))) => Ok((input, builder::binary(operator, left, right)?)),
+        (input, None) => Ok((input, left)),
+    }
+}
+
+fn sum(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let product = |input| product(input, layout);
+
+    // A sign after a space and before a digit starts an Int literal, as in "x -5", which is an
+    // application, so "1 -5" isn't a subtraction either.
+    let sign = || alt((tag("+"), tag("-")));
+    let operator = alt((
+        preceded(space1(layout), terminated(sign(), not(digit1))),
+        sign(),
+    ));
+    infix(layout, product, operator)(input)
+}
+
+fn product(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let negation = |input| negation(input, layout);
+    let operator = preceded(space0(layout), alt((tag("*"), tag("/"))));
+    infix(layout, negation, operator)(input)
+}
+
+fn negation(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    // A second "-" would start a comment rather than a nested negation, and a digit would start
+    // an Int literal.
+    let operator = terminated(tag("-"), not(alt((tag("-"), digit1))));
 
-    let argument = preceded(separator, term);
+    match opt(terminated(operator, space0(layout)))(input)? {
+        (input, Some(operator)) => {
+            let (input, operand) = negation(input, layout)?;
+            Ok((input, builder::negate(operator, operand)?))
+        }
+        (input, None) => alt((
+            |input| application(input, layout),
+            |input| term(input, layout),
+        ))(input),
+    }
+}
+
+fn infix<'a>(
+    layout: usize,
+    operand: impl Fn(Span<'a>) -> IResult<'a, Span<'a>, Context<'a, Expression<'a>>>,
+    operator: impl Fn(Span<'a>) -> IResult<'a, Span<'a>, Span<'a>>,
+) -> impl Fn(Span<'a>) -> IResult<'a, Span<'a>, Context<'a, Expression<'a>>> {
+    move |input| {
+        let (mut input, mut left) = operand(input)?;
+
+        // Operators of the same precedence are left associative. The operator parses any
+        // whitespace before it.
+        while let (rest, Some((operator, right))) =
+            opt(pair(terminated(&operator, space0(layout)), &operand))(input)?
+        {
+            input = rest;
+            left = builder::binary(operator, left, right)?;
+        }
+
+        Ok((input, left))
+    }
+}
+
+pub fn application(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    // A name followed by "." is a projection, which is a term.
+    let (input, identifier) = terminated(qualified, not(char('.')))(input)?;
+
+    let argument = preceded(
+        |input| separator(input, layout),
+        |input| term(input, layout),
+    );
     let (input, arguments) = many0(argument)(input)?;
     Ok((input, builder::application(identifier, arguments)?))
 }
 
-pub fn term(input: Span) -> IResult<Span, Context<Expression>> {
+// Components of tuples and records are projected with ".", as in "leg.strike" or "pair.0".
+pub fn term(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let component = alt((
+        map_res(digit1, |index: Span| {
+            index.fragment.parse().map(Component::Index)
+        }),
+        map(identifier, |name| Component::Name(name.fragment)),
+    ));
+    let (mut input, mut term) = primary(input, layout)?;
+
+    while let (rest, Some((span, component))) = opt(pair(tag("."), &component))(input)? {
+        input = rest;
+        term = builder::projection(term, component, span)?;
+    }
+
+    Ok((input, term))
+}
+
+fn primary(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
     alt((
-        brackets1(expression),
-        map_res(identifier, |identifier| {
+        brackets1(move |input| expression(input, layout)),
+        |input| tuple(input, layout),
+        |input| record(input, layout),
+        map_res(qualified, |identifier| {
             builder::application(identifier, Vec::new())
         }),
+        map(boolean, Context::from),
+        map(date, Context::from),
+        map(decimal, Context::from),
+        map(duration, Context::from),
+        map(int, Context::from),
+        map(libra, Context::from),
+        map(word, Context::from),
+    ))(input)
+}
+
+fn tuple(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, span) = tag("(")(input)?;
+    let elements = separated_list2(
+        padding0(layout, char(',')),
+        padding0(layout, |input| expression(input, layout)),
+    );
+    let (input, elements) = terminated(elements, char(')'))(input)?;
+
+    Ok((input, builder::tuple(span, elements)?))
+}
+
+fn record(input: Span, layout: usize) -> IResult<Span, Context<Expression>> {
+    let (input, span) = terminated(tag("{"), not(char('-')))(input)?;
+    let field = separated_pair(
+        padding0(layout, identifier),
+        char('='),
+        padding0(layout, |input| expression(input, layout)),
+    );
+    let (input, fields) = terminated(separated_nonempty_list(char(','), field), char('}'))(input)?;
+
+    Ok((input, builder::record(span, fields)?))
+}
+
+// Two or more items, as in the elements of a tuple.
+fn separated_list2<'a, O>(
+    separator: impl Fn(Span<'a>) -> IResult<'a, Span<'a>, char>,
+    item: impl Fn(Span<'a>) -> IResult<'a, Span<'a>, O>,
+) -> impl Fn(Span<'a>) -> IResult<'a, Span<'a>, Vec<O>> {
+    verify(separated_nonempty_list(separator, item), |items: &[O]| {
+        items.len() >= 2
+    })
+}
+
+fn boolean(input: Span) -> IResult<Span, Expression> {
+    alt((
         map(tag("True"), |span| {
-            Expression::new(ExpressionType::from(true), Some(span)).into()
+            Expression::new(ExpressionType::from(true), Some(span))
         }),
         map(tag("False"), |span| {
-            Expression::new(ExpressionType::from(false), Some(span)).into()
-        }),
-        map(date, Context::from),
-        map(digit1, |n: Span| {
-            Expression::new(
-                ExpressionType::from(n.fragment.parse::<u64>().unwrap()),
-                Some(n),
-            )
-            .into()
+            Expression::new(ExpressionType::from(false), Some(span))
         }),
     ))(input)
 }
 
+fn decimal(input: Span) -> IResult<Span, Expression> {
+    let (input, span) = recognize(separated_pair(digits, char('.'), digits))(input)?;
+    let (whole, fraction) = span.fragment.split_once('.').unwrap();
+    let fraction = fraction.replace('_', "");
+
+    if fraction.len() > DECIMAL_PLACES {
+        return Err(Err::Failure(Error::from_sprint_error(
+            SprintError::DecimalPlacesError(DECIMAL_PLACES),
+            Some(span),
+        )));
+    }
+
+    let fraction = format!("{:0<1$}", fraction, DECIMAL_PLACES);
+    let decimal = number(whole, span, Kind::Decimal)?
+        .checked_mul(10u64.pow(DECIMAL_PLACES as u32))
+        .and_then(|whole| whole.checked_add(fraction.parse().unwrap()))
+        .ok_or_else(|| overflow(span, Kind::Decimal))?;
+
+    Ok((
+        input,
+        Expression::new(ExpressionType::Decimal(decimal), Some(span)),
+    ))
+}
+
+// Durations are a number of weeks, days, hours, minutes or seconds, and are stored in seconds.
+fn duration(input: Span) -> IResult<Span, Expression> {
+    let unit = alt((char('w'), char('d'), char('h'), char('m'), char('s')));
+    let (input, span) = recognize(terminated(pair(digits, unit), not(alphanumeric1)))(input)?;
+    let (count, unit) = span.fragment.split_at(span.fragment.len() - 1);
+
+    let seconds = match unit {
+        "w" => 7 * 24 * 60 * 60,
+        "d" => 24 * 60 * 60,
+        "h" => 60 * 60,
+        "m" => 60,
+        _ => 1,
+    };
+    let duration = number(count, span, Kind::Duration)?
+        .checked_mul(seconds)
+        .ok_or_else(|| overflow(span, Kind::Duration))?;
+
+    Ok((
+        input,
+        Expression::new(ExpressionType::Duration(duration), Some(span)),
+    ))
+}
+
+// Amounts of Libra are Words of microLibra.
+fn libra(input: Span) -> IResult<Span, Expression> {
+    let (input, span) =
+        recognize(terminated(pair(digits, tag("libra")), not(alphanumeric1)))(input)?;
+    let count = &span.fragment[..span.fragment.len() - "libra".len()];
+    let amount = number(count, span, Kind::Word)?
+        .checked_mul(MICROLIBRA)
+        .ok_or_else(|| overflow(span, Kind::Word))?;
+
+    Ok((
+        input,
+        Expression::new(ExpressionType::from(amount), Some(span)),
+    ))
+}
+
+fn word(input: Span) -> IResult<Span, Expression> {
+    let (input, span) = alt((hexadecimal, digits))(input)?;
+    let word = number(span.fragment, span, Kind::Word)?;
+
+    Ok((
+        input,
+        Expression::new(ExpressionType::from(word), Some(span)),
+    ))
+}
+
+// Ints are written with a sign, to tell them apart from Words.
+fn int(input: Span) -> IResult<Span, Expression> {
+    let sign = alt((char('+'), char('-')));
+    let (input, span) = recognize(pair(sign, alt((hexadecimal, digits))))(input)?;
+
+    // The sign is parsed along with the digits, since the smallest Int has no positive
+    // counterpart.
+    let (sign, digits) = span.fragment.split_at(1);
+    let digits = digits.replace('_', "");
+    let int = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(&format!("{}{}", sign, hex), 16),
+        None => format!("{}{}", sign, digits).parse(),
+    };
+    let int = int.map_err(|_| overflow(span, Kind::Int))?;
+
+    Ok((
+        input,
+        Expression::new(ExpressionType::from(int), Some(span)),
+    ))
+}
+
+// Digits can be separated by underscores, as in "1_000_000".
+pub(super) fn digits(input: Span) -> IResult<Span, Span> {
+    recognize(pair(digit1, many0(pair(char('_'), digit1))))(input)
+}
+
+pub(super) fn hexadecimal(input: Span) -> IResult<Span, Span> {
+    let hex_digits = pair(hex_digit1, many0(pair(char('_'), hex_digit1)));
+    recognize(pair(tag("0x"), hex_digits))(input)
+}
+
+// The value of some digits, which fails for the whole of the literal if it is too large.
+fn number<'a>(digits: &str, literal: Span<'a>, kind: Kind) -> Result<'a, u64> {
+    let digits = digits.replace('_', "");
+    let number = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+
+    number.map_err(|_| overflow(literal, kind))
+}
+
+fn overflow(literal: Span, kind: Kind) -> Err<Error> {
+    Err::Failure(Error::from_sprint_error(
+        SprintError::LiteralOverflowError(kind),
+        Some(literal),
+    ))
+}
+
 pub fn identifier(input: Span) -> IResult<Span, Span> {
     let lowercase1 = take_while1(|c: char| c.is_ascii_lowercase());
-    let (input, identifier) = recognize(pair(lowercase1, alphanumeric0))(input)?;
+    let (input, identifier) = verify(recognize(pair(lowercase1, alphanumeric0)), |s: &Span| {
+        !KEYWORDS.contains(&s.fragment)
+    })(input)?;
 
     Ok((input, identifier))
 }
 
-fn separator(input: Span) -> IResult<Span, Span> {
-    alt((space1, peek(tag("("))))(input)
+// A name which may be qualified by the module which defines it, as in "Bonds.zero".
+fn qualified(input: Span) -> IResult<Span, Span> {
+    let name = alt((identifier, constructor));
+    recognize(pair(opt(terminated(module_name, char('.'))), name))(input)
+}
+
+fn constructor(input: Span) -> IResult<Span, Span> {
+    verify(module_name, |s: &Span| {
+        !["True", "False"].contains(&s.fragment)
+    })(input)
+}
+
+pub(super) fn module_name(input: Span) -> IResult<Span, Span> {
+    let uppercase1 = take_while1(|c: char| c.is_ascii_uppercase());
+    recognize(pair(uppercase1, alphanumeric0))(input)
+}
+
+fn keyword<'a>(keyword: &'static str) -> impl Fn(Span<'a>) -> IResult<'a, Span<'a>, Span<'a>> {
+    terminated(tag(keyword), not(alphanumeric1))
+}
+
+fn separator(input: Span, layout: usize) -> IResult<Span, Span> {
+    alt((space1(layout), peek(tag("("))))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Class, Comparable, Date, Numerable};
+
+    // Arithmetic which might involve dates is checked as it would be at the end of inference.
+    fn parse(input: &str) -> Expression<'_> {
+        let (input, context) = expression(Span::new(input), TOP_LEVEL).unwrap();
+        assert!(input.fragment.is_empty());
+
+        for arithmetic in &context.arithmetic {
+            builder::arithmetic(arithmetic).unwrap();
+        }
+
+        context.as_ref().clone()
+    }
+
+    #[test]
+    fn parse_precedence() {
+        match parse("1 + 2 * 3").expression {
+            ExpressionType::Class(Class::Numerable(Numerable::Add(left, right))) => {
+                match (&left.expression, &right.expression) {
+                    (
+                        ExpressionType::Word(1),
+                        ExpressionType::Class(Class::Numerable(Numerable::Multiply(_, _))),
+                    ) => {}
+                    _ => panic!("multiplication should bind tighter than addition"),
+                }
+            }
+            _ => panic!("expected an addition"),
+        }
+    }
+
+    #[test]
+    fn parse_left_associativity() {
+        match parse("1 - 2 - 3").expression {
+            ExpressionType::Class(Class::Numerable(Numerable::Subtract(left, right))) => {
+                match (&left.expression, &right.expression) {
+                    (
+                        ExpressionType::Class(Class::Numerable(Numerable::Subtract(_, _))),
+                        ExpressionType::Word(3),
+                    ) => {}
+                    _ => panic!("subtraction should be left associative"),
+                }
+            }
+            _ => panic!("expected a subtraction"),
+        }
+    }
+
+    #[test]
+    fn parse_comparison() {
+        let comparison = parse("((+4 - +1) * +2) >= -2");
+
+        match &comparison.expression {
+            ExpressionType::Class(Class::Comparable(Comparable::GreaterEqual(_, _))) => {}
+            _ => panic!("expected a comparison"),
+        }
+        assert_eq!(*comparison.kind(), Kind::Boolean);
+
+        let (input, _) = expression(Span::new("1 < 2 < 3"), TOP_LEVEL).unwrap();
+        assert_eq!(input.fragment, " < 3");
+    }
+
+    #[test]
+    fn parse_decimal() {
+        let decimal = parse("1.5 * 0.025");
+
+        match &decimal.expression {
+            ExpressionType::Class(Class::Numerable(Numerable::Multiply(left, right))) => {
+                match (&left.expression, &right.expression) {
+                    (
+                        ExpressionType::Decimal(1_500_000_000),
+                        ExpressionType::Decimal(25_000_000),
+                    ) => {}
+                    _ => panic!("expected decimals of 1.5 and 0.025"),
+                }
+            }
+            _ => panic!("expected a multiplication"),
+        }
+        assert_eq!(*Kind::simplify(decimal.kind()), Kind::Decimal);
+
+        match expression(Span::new("0.0000000001"), TOP_LEVEL) {
+            Err(Err::Failure(error)) => assert_eq!(
+                error.sprint_error,
+                Some(SprintError::DecimalPlacesError(DECIMAL_PLACES))
+            ),
+            _ => panic!("expected too many decimal places to fail"),
+        }
+        assert!(expression(Span::new("1.5 + 1"), TOP_LEVEL).is_err());
+    }
+
+    #[test]
+    fn parse_duration() {
+        let date = Date::Date(2020, 1, 1, 22, 0, 0);
+        let folded = parse("2020-01-01T00:00:00Z + 1d - 2h");
+        assert!(matches!(folded.expression, ExpressionType::Date(d) if d == date));
+
+        let elapsed = parse("2020-01-08T00:00:00Z - 2020-01-01T00:00:00Z");
+        assert!(matches!(
+            elapsed.expression,
+            ExpressionType::Duration(604_800)
+        ));
+        assert!(matches!(
+            parse("1w").expression,
+            ExpressionType::Duration(604_800)
+        ));
+
+        let elapsed = parse("x - 2020-01-01T00:00:00Z");
+        assert!(matches!(
+            elapsed.expression,
+            ExpressionType::Difference(_, _)
+        ));
+        assert_eq!(*Kind::simplify(elapsed.kind()), Kind::Duration);
+
+        assert!(expression(Span::new("1d + 2020-01-01T00:00:00Z"), TOP_LEVEL).is_err());
+        assert!(expression(Span::new("2020-01-01T00:00:00Z + 1"), TOP_LEVEL).is_err());
+    }
+
+    #[test]
+    fn parse_numbers() {
+        let word = |input| match parse(input).expression {
+            ExpressionType::Word(word) => word,
+            expression => panic!("expected a word, got {:?}", expression),
+        };
+        assert_eq!(word("1_000_000"), 1_000_000);
+        assert_eq!(word("0xff_ff"), 0xffff);
+        assert_eq!(word("5libra"), 5_000_000);
+        assert!(matches!(
+            parse("-0x10").expression,
+            ExpressionType::Int(-16)
+        ));
+        assert!(matches!(
+            parse("1_000.000_5").expression,
+            ExpressionType::Decimal(1_000_000_500_000)
+        ));
+
+        let overflow = |input| match expression(Span::new(input), TOP_LEVEL) {
+            Err(Err::Failure(error)) => match error.sprint_error {
+                Some(SprintError::LiteralOverflowError(kind)) => kind,
+                error => panic!("expected an overflow, got {:?}", error),
+            },
+            _ => panic!("expected {} to fail", input),
+        };
+        assert_eq!(overflow("18446744073709551616"), Kind::Word);
+        assert_eq!(overflow("0x1_0000_0000_0000_0000"), Kind::Word);
+        assert_eq!(overflow("18446744073710libra"), Kind::Word);
+        assert_eq!(overflow("-9223372036854775809"), Kind::Int);
+        assert_eq!(overflow("+9223372036854775808"), Kind::Int);
+        assert_eq!(overflow("18446744073.709551616"), Kind::Decimal);
+        assert_eq!(overflow("30500568904944w"), Kind::Duration);
+    }
+
+    #[test]
+    fn parse_int() {
+        let int = parse("x - -5");
+
+        match &int.expression {
+            ExpressionType::Class(Class::Numerable(Numerable::Subtract(_, right))) => {
+                assert!(matches!(right.expression, ExpressionType::Int(-5)))
+            }
+            _ => panic!("expected a subtraction"),
+        }
+        assert_eq!(*Kind::simplify(int.kind()), Kind::Int);
+
+        assert!(matches!(parse("+0").expression, ExpressionType::Int(0)));
+        assert!(matches!(
+            parse("-9223372036854775808").expression,
+            ExpressionType::Int(i64::MIN)
+        ));
+        assert!(matches!(
+            parse("-0x8000_0000_0000_0000").expression,
+            ExpressionType::Int(i64::MIN)
+        ));
+        assert!(matches!(
+            parse("x -5").expression,
+            ExpressionType::Application(_, _)
+        ));
+        assert!(matches!(
+            parse("1-5").expression,
+            ExpressionType::Class(Class::Numerable(Numerable::Subtract(_, _)))
+        ));
+        assert!(!matches!(
+            expression(Span::new("1 -5"), TOP_LEVEL),
+            Ok((rest, _)) if rest.fragment.is_empty()
+        ));
+        assert!(expression(Span::new("-(1 + 2)"), TOP_LEVEL).is_err());
+    }
+
+    #[test]
+    fn parse_lambda() {
+        let lambda = parse("\\x y -> x + y");
+
+        match &lambda.expression {
+            ExpressionType::Abstraction(x, body) => match &body.expression {
+                ExpressionType::Abstraction(y, _) => assert_eq!((x.name, y.name), ("x", "y")),
+                _ => panic!("expected an abstraction"),
+            },
+            _ => panic!("expected an abstraction"),
+        }
+        assert_eq!(lambda.kind().to_string(), "a -> a -> a");
+    }
+
+    #[test]
+    fn parse_local() {
+        let local = parse("let x = 1\n    y = x * 2\n in y + 1");
+
+        match &local.expression {
+            ExpressionType::Application(f, value) => match (&f.expression, &value.expression) {
+                (ExpressionType::Abstraction(x, _), ExpressionType::Word(1)) => {
+                    assert_eq!(x.name, "x")
+                }
+                _ => panic!("expected x to be bound to 1"),
+            },
+            _ => panic!("expected an application"),
+        }
+        assert_eq!(*Kind::simplify(local.kind()), Kind::Word);
+
+        parse("let double x = x * 2; two = double 1 in two");
+        expression(Span::new("let x = True in x + 1"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("let in = 1 in in"), TOP_LEVEL).unwrap_err();
+    }
+
+    #[test]
+    fn parse_where() {
+        let (input, context) = definition(Span::new(
+            "f x = y\n  where\n    z = x + 1\n    y = z * 2\n",
+        ))
+        .unwrap();
+
+        assert_eq!(input.fragment, "\n");
+        assert!(context.variables.count("x") == 0);
+        assert!(context.variables.count("z") == 0);
+    }
+
+    #[test]
+    fn parse_kind() {
+        let parse = |input| {
+            kind(Span::new(input), &Default::default(), &Default::default())
+                .unwrap()
+                .1
+        };
+
+        assert_eq!(
+            parse("Observable Word -> Contract").to_string(),
+            "Observable Word -> Contract"
+        );
+        assert_eq!(
+            parse("(a -> b) -> a -> b").to_string(),
+            "(a -> b) -> a -> b"
+        );
+        assert_eq!(parse("a -> Observable a").to_string(), "a -> Observable a");
+        assert_eq!(parse("Date -> Decimal").to_string(), "Date -> Decimal");
+    }
+
+    #[test]
+    fn parse_conditional() {
+        let conditional = parse("if 1 > 0 then 1 else 0");
+
+        match &conditional.expression {
+            ExpressionType::Conditional(condition, _, _) => {
+                assert_eq!(*Kind::simplify(condition.kind()), Kind::Boolean)
+            }
+            _ => panic!("expected a conditional"),
+        }
+        assert_eq!(*Kind::simplify(conditional.kind()), Kind::Word);
+
+        parse("if True then\n  1\n else 2");
+        expression(Span::new("if 1 then one else zero"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("if True then False else 1"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("if True then one"), TOP_LEVEL).unwrap_err();
+    }
+
+    #[test]
+    fn parse_case() {
+        let case = parse("case 2 of\n 1 -> True\n 2 -> False\n _ -> True");
+        assert_eq!(*Kind::simplify(case.kind()), Kind::Boolean);
+
+        parse("case 1 > 0 of True -> 1; False -> 0");
+        parse("case 1 of _ -> 1");
+        expression(Span::new("case 1 of True -> 1; _ -> 0"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("case 1 of 1 -> 1; _ -> True"), TOP_LEVEL).unwrap_err();
+
+        match expression(Span::new("case 1 of 1 -> 1; 2 -> 0"), TOP_LEVEL) {
+            Err(nom::Err::Failure(error)) => assert_eq!(
+                error.sprint_error,
+                Some(SprintError::NonExhaustivePatternsError)
+            ),
+            _ => panic!("expected a non-exhaustive case to fail"),
+        }
+    }
+
+    #[test]
+    fn parse_tuples_and_records() {
+        let pair = parse("(1, True).1");
+        assert_eq!(*Kind::simplify(pair.kind()), Kind::Boolean);
+
+        let record = parse("{ strike = 5, expiry = 2020-01-01T00:00:00Z }");
+        assert_eq!(
+            record.kind().to_string(),
+            "{ expiry :: Date, strike :: Word }"
+        );
+
+        let sum = parse("case (1, 2) of (a, b) -> a + b");
+        assert_eq!(*Kind::simplify(sum.kind()), Kind::Word);
+        parse("case { a = 1 } of { a = x } -> x");
+
+        expression(Span::new("(1, 2).2"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("{ a = 1 }.b"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("case (1, 2) of (a, b, c) -> a"), TOP_LEVEL).unwrap_err();
+
+        match expression(Span::new("{ a = 1, a = 2 }"), TOP_LEVEL) {
+            Err(nom::Err::Failure(error)) => assert_eq!(
+                error.sprint_error,
+                Some(SprintError::DuplicateDefinitionError("a"))
+            ),
+            _ => panic!("expected a duplicate field to fail"),
+        }
+    }
+
+    #[test]
+    fn parse_mismatched_operands() {
+        expression(Span::new("1 + True"), TOP_LEVEL).unwrap_err();
+        expression(Span::new("True * False"), TOP_LEVEL).unwrap_err();
+    }
 }
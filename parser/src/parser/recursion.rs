@@ -0,0 +1,197 @@
+use super::{
+    error::{Error, SprintError},
+    Span,
+};
+use crate::ast::{
+    borrowed::{Class, Definition, Expression, ExpressionType, Numerable, Variable},
+    visit::{self, Visit},
+    Borrowed, Kind,
+};
+use std::{collections::HashMap, mem, rc::Rc};
+
+// Each use of a recursive definition unfolds into more of the contract, so recursion has to end.
+// It ends if every recursive use takes a smaller Word in one of the arguments, such as
+// "payments (n - 1)", or if the use is the contract of before or after, which only unfolds once
+// a date has passed. The same argument has to decrease throughout a group of mutually recursive
+// definitions, which is more conservative than it needs to be, but easy to explain.
+pub fn check<'a>(
+    group: &[&'a str],
+    definitions: &HashMap<&'a str, Definition<'a>>,
+) -> Result<(), Error<'a>> {
+    let mut calls = Vec::new();
+
+    for &name in group {
+        let definition = match definitions.get(name) {
+            Some(definition) => definition,
+            None => continue,
+        };
+
+        let mut search = Search {
+            group,
+            caller: name,
+            parameters: parameters(&definition.expression),
+            bound: Vec::new(),
+            guarded: false,
+            calls: Vec::new(),
+        };
+
+        search.visit_expression(&definition.expression);
+        calls.extend(search.calls.into_iter().filter(|call| !call.guarded));
+    }
+
+    let arguments = calls.iter().map(|call| call.decreasing.len()).max();
+    let decreasing = (0..arguments.unwrap_or_default()).any(|i| {
+        calls
+            .iter()
+            .all(|call| call.decreasing.get(i) == Some(&true))
+    });
+
+    // The use which decreases none of its arguments is the most useful one to point out.
+    let call = calls
+        .iter()
+        .find(|call| !call.decreasing.contains(&true))
+        .or_else(|| calls.first());
+
+    match call {
+        Some(call) if !decreasing => Err(Error::from_sprint_error(
+            SprintError::InfiniteContractError(call.caller),
+            call.span,
+        )),
+        _ => Ok(()),
+    }
+}
+
+// A use of a definition of the group within one of its definitions.
+struct Call<'a> {
+    caller: &'a str,
+    span: Option<Span<'a>>,
+    guarded: bool,
+    // Whether each argument is the same argument of the caller less a literal.
+    decreasing: Vec<bool>,
+}
+
+struct Search<'a, 'b> {
+    group: &'b [&'a str],
+    caller: &'a str,
+    parameters: Vec<Rc<Kind>>,
+    bound: Vec<&'a str>,
+    // Whether the expression being visited is the contract of before or after.
+    guarded: bool,
+    calls: Vec<Call<'a>>,
+}
+
+impl<'a, 'c> Visit<'c, Borrowed<'a>> for Search<'a, '_> {
+    fn visit_expression(&mut self, expression: &'c Expression<'a>) {
+        match &expression.expression {
+            ExpressionType::Application(_, _) => self.application(expression),
+            ExpressionType::Variable(variable) if self.recursive(variable.name) => {
+                self.calls.push(Call {
+                    caller: self.caller,
+                    span: expression.span,
+                    guarded: self.guarded,
+                    decreasing: Vec::new(),
+                });
+            }
+            _ => visit::visit_expression(self, expression),
+        }
+    }
+
+    fn visit_abstraction(&mut self, argument: &'c Variable<'a>, body: &'c Expression<'a>) {
+        self.bound.push(argument.name);
+        self.visit_expression(body);
+        self.bound.pop();
+    }
+}
+
+impl<'a> Search<'a, '_> {
+    fn application(&mut self, expression: &Expression<'a>) {
+        let mut head = expression;
+        let mut arguments = Vec::new();
+
+        while let ExpressionType::Application(f, argument) = &head.expression {
+            arguments.push(argument.as_ref());
+            head = f;
+        }
+        arguments.reverse();
+
+        let name = match &head.expression {
+            ExpressionType::Variable(variable) if !self.bound.contains(&variable.name) => {
+                Some(variable.name)
+            }
+            _ => None,
+        };
+
+        match name {
+            Some(name) if self.group.contains(&name) => {
+                let decreasing = arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, argument)| self.decreasing(i, argument))
+                    .collect();
+
+                self.calls.push(Call {
+                    caller: self.caller,
+                    span: head.span,
+                    guarded: self.guarded,
+                    decreasing,
+                });
+            }
+            Some("after") | Some("before") if arguments.len() == 2 => {
+                self.visit_expression(arguments[0]);
+                let guarded = mem::replace(&mut self.guarded, true);
+                self.visit_expression(arguments[1]);
+                self.guarded = guarded;
+                return;
+            }
+            _ => self.visit_expression(head),
+        }
+
+        for argument in arguments {
+            self.visit_expression(argument);
+        }
+    }
+
+    fn recursive(&self, name: &str) -> bool {
+        self.group.contains(&name) && !self.bound.contains(&name)
+    }
+
+    // Whether the argument is the Word parameter in the same position less a positive literal.
+    fn decreasing(&self, index: usize, argument: &Expression<'a>) -> bool {
+        let subtraction = match &argument.expression {
+            ExpressionType::Difference(subtraction, _) => &subtraction.expression,
+            expression => expression,
+        };
+
+        let (left, right) = match subtraction {
+            ExpressionType::Class(Class::Numerable(Numerable::Subtract(left, right))) => {
+                (left, right)
+            }
+            _ => return false,
+        };
+
+        let parameter = match &left.expression {
+            ExpressionType::Variable(variable) => {
+                self.bound.iter().rposition(|&name| name == variable.name)
+            }
+            _ => None,
+        };
+
+        let word = self.parameters.get(index).cloned().map(Kind::simplify);
+        let word = matches!(word.as_deref(), Some(Kind::Word));
+
+        parameter == Some(index)
+            && word
+            && matches!(right.expression, ExpressionType::Word(k) if k > 0)
+    }
+}
+
+fn parameters(mut expression: &Expression) -> Vec<Rc<Kind>> {
+    let mut parameters = Vec::new();
+
+    while let ExpressionType::Abstraction(parameter, body) = &expression.expression {
+        parameters.push(parameter.kind.clone());
+        expression = body;
+    }
+
+    parameters
+}
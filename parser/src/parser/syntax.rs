@@ -0,0 +1,699 @@
+use super::{
+    date::date,
+    error::Error,
+    program::{digits, hexadecimal, module_name, KEYWORDS},
+    whitespace::comment,
+    IResult, Span,
+};
+use crate::cst::{Element, Node, NodeKind, Token, TokenKind};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{
+        alphanumeric0, alphanumeric1, char, digit1, line_ending, one_of, space1,
+    },
+    combinator::{map, not, opt, recognize},
+    error::{ErrorKind, ParseError},
+    sequence::{pair, preceded, separated_pair, terminated},
+    Err,
+};
+
+type Result<'a, T> = std::result::Result<T, Error<'a>>;
+
+// Parses a program into a concrete syntax tree, which keeps its comments and whitespace. The
+// tree follows the grammar of the program parser, including its layout rules, but it doesn't
+// check names or kinds.
+pub fn tree(input: &str) -> Result<Node> {
+    let (tokens, end) = tokens(Span::new(input))?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        layout: 1,
+        line: 0,
+        end,
+    };
+
+    parser.module()
+}
+
+fn tokens(mut input: Span) -> Result<(Vec<Token>, Span)> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    while !input.fragment.is_empty() {
+        let (rest, token) = token(input, tokens.last()).map_err(|e| match e {
+            Err::Error(e) | Err::Failure(e) => e,
+            Err::Incomplete(_) => Error::from_error_kind(input, ErrorKind::Complete),
+        })?;
+
+        tokens.push(token);
+        input = rest;
+    }
+
+    Ok((tokens, input))
+}
+
+fn token<'a>(input: Span<'a>, previous: Option<&Token<'a>>) -> IResult<'a, Span<'a>, Token<'a>> {
+    // A sign starts an Int unless it comes straight after an operand, as in "n-1". After a "."
+    // a number is the index of a component, so "pair.0.1" isn't a Decimal.
+    let operand = previous.map_or(false, |token| {
+        matches!(
+            token.kind,
+            TokenKind::Identifier | TokenKind::Name | TokenKind::Literal
+        ) || token.is(")")
+            || token.is("}")
+    });
+    let index = previous.map_or(false, |token| token.is("."));
+
+    if index {
+        if let Ok((input, span)) = digit1::<_, Error>(input) {
+            return Ok((input, Token::new(TokenKind::Literal, span)));
+        }
+    }
+
+    if !operand {
+        let sign = alt((char('+'), char('-')));
+        let int = recognize(pair(sign, alt((hexadecimal, digits))));
+        if let Ok((input, span)) = int(input) {
+            return Ok((input, Token::new(TokenKind::Literal, span)));
+        }
+    }
+
+    let token = |kind| move |span| Token::new(kind, span);
+
+    alt((
+        map(space1, token(TokenKind::Whitespace)),
+        map(line_ending, token(TokenKind::Newline)),
+        map(comment, token(TokenKind::Comment)),
+        word,
+        map(literal, token(TokenKind::Literal)),
+        map(symbol, token(TokenKind::Symbol)),
+    ))(input)
+}
+
+fn word(input: Span) -> IResult<Span, Token> {
+    if let Ok((input, span)) = lowercase(input) {
+        let kind = match KEYWORDS.contains(&span.fragment) {
+            true => TokenKind::Keyword,
+            false => TokenKind::Identifier,
+        };
+
+        return Ok((input, Token::new(kind, span)));
+    }
+
+    // A qualified name, such as "Bonds.zero", is a single token.
+    let name = alt((
+        recognize(pair(
+            take_while1(|c: char| c.is_ascii_lowercase()),
+            alphanumeric0,
+        )),
+        module_name,
+    ));
+    let (input, span) = recognize(pair(module_name, opt(preceded(char('.'), name))))(input)?;
+
+    Ok((input, Token::new(TokenKind::Name, span)))
+}
+
+fn lowercase(input: Span) -> IResult<Span, Span> {
+    recognize(pair(
+        take_while1(|c: char| c.is_ascii_lowercase()),
+        alphanumeric0,
+    ))(input)
+}
+
+fn literal(input: Span) -> IResult<Span, Span> {
+    let unit = alt((char('w'), char('d'), char('h'), char('m'), char('s')));
+
+    alt((
+        recognize(date),
+        recognize(separated_pair(digits, char('.'), digits)),
+        recognize(terminated(pair(digits, unit), not(alphanumeric1))),
+        recognize(terminated(pair(digits, tag("libra")), not(alphanumeric1))),
+        hexadecimal,
+        digits,
+    ))(input)
+}
+
+fn symbol(input: Span) -> IResult<Span, Span> {
+    alt((
+        tag("::"),
+        tag("->"),
+        tag("=="),
+        tag("/="),
+        tag(">="),
+        tag("<="),
+        recognize(one_of("=<>+-*/\\(){},;|._")),
+    ))(input)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    // The index of the next token, which may be trivia.
+    position: usize,
+    // The column at which lines of the innermost block start, as in the program parser.
+    layout: usize,
+    // The line of the last token which isn't trivia.
+    line: u32,
+    end: Span<'a>,
+}
+
+impl<'a> Parser<'a> {
+    fn module(&mut self) -> Result<'a, Node<'a>> {
+        let mut module = Node::new(NodeKind::Module);
+
+        if self.peek_is("module") {
+            let mut header = Node::new(NodeKind::Header);
+            self.bump(&mut header);
+            self.expect_kind(&mut header, TokenKind::Name)?;
+            module.children.push(Element::Node(header));
+            self.separated()?;
+        }
+
+        loop {
+            self.trivia(&mut module);
+
+            match self.peek() {
+                None => break,
+                Some(token) if token.is(";") => self.bump(&mut module),
+                Some(_) => {
+                    let item = self.item()?;
+                    module.children.push(Element::Node(item));
+                    self.separated()?;
+                }
+            }
+        }
+
+        Ok(module)
+    }
+
+    // Top-level items end at a ";" or the start of the next line which isn't indented.
+    fn separated(&self) -> Result<'a, ()> {
+        match self.peek() {
+            Some(token) if self.continues() && !token.is(";") => Err(self.error()),
+            _ => Ok(()),
+        }
+    }
+
+    fn item(&mut self) -> Result<'a, Node<'a>> {
+        self.start();
+        let token = self.peek().copied().ok_or_else(|| self.error())?;
+
+        if token.is("import") {
+            let mut import = Node::new(NodeKind::Import);
+            self.bump(&mut import);
+            self.expect_kind(&mut import, TokenKind::Name)?;
+
+            if self.at("(") {
+                self.bump(&mut import);
+                self.expect_kind(&mut import, TokenKind::Identifier)?;
+                while self.at(",") {
+                    self.bump(&mut import);
+                    self.expect_kind(&mut import, TokenKind::Identifier)?;
+                }
+                self.expect(&mut import, ")")?;
+            }
+
+            Ok(import)
+        } else if token.is("type") {
+            let mut alias = Node::new(NodeKind::Alias);
+            self.bump(&mut alias);
+            self.expect_kind(&mut alias, TokenKind::Name)?;
+            self.expect(&mut alias, "=")?;
+            alias.children.push(Element::Node(self.kind()?));
+            Ok(alias)
+        } else if token.is("data") {
+            let mut data = Node::new(NodeKind::Data);
+            self.bump(&mut data);
+            self.expect_kind(&mut data, TokenKind::Name)?;
+            self.expect(&mut data, "=")?;
+            data.children.push(Element::Node(self.constructor()?));
+            while self.at("|") {
+                self.bump(&mut data);
+                data.children.push(Element::Node(self.constructor()?));
+            }
+            Ok(data)
+        } else if token.kind == TokenKind::Identifier {
+            match self.peek_nth(1) {
+                Some(token) if token.is("::") => {
+                    let mut signature = Node::new(NodeKind::Signature);
+                    self.bump(&mut signature);
+                    self.bump(&mut signature);
+                    signature.children.push(Element::Node(self.kind()?));
+                    Ok(signature)
+                }
+                _ => self.binding(),
+            }
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn constructor(&mut self) -> Result<'a, Node<'a>> {
+        let mut constructor = Node::new(NodeKind::Constructor);
+        self.expect_kind(&mut constructor, TokenKind::Name)?;
+
+        while self.continues() && self.peek().map_or(false, kind_start) {
+            constructor
+                .children
+                .push(Element::Node(self.kind_primitive()?));
+        }
+
+        Ok(constructor)
+    }
+
+    fn kind(&mut self) -> Result<'a, Node<'a>> {
+        let mut kind = Node::new(NodeKind::Kind);
+        kind.children.push(Element::Node(self.kind_primitive()?));
+
+        if self.at("->") {
+            self.bump(&mut kind);
+            kind.children.push(Element::Node(self.kind()?));
+        }
+
+        Ok(kind)
+    }
+
+    fn kind_primitive(&mut self) -> Result<'a, Node<'a>> {
+        let mut kind = Node::new(NodeKind::Kind);
+
+        if self.at("(") {
+            self.bump(&mut kind);
+            kind.children.push(Element::Node(self.kind()?));
+            while self.at(",") {
+                self.bump(&mut kind);
+                kind.children.push(Element::Node(self.kind()?));
+            }
+            self.expect(&mut kind, ")")?;
+        } else if self.at("{") {
+            self.bump(&mut kind);
+            loop {
+                self.expect_kind(&mut kind, TokenKind::Identifier)?;
+                self.expect(&mut kind, "::")?;
+                kind.children.push(Element::Node(self.kind()?));
+                if !self.at(",") {
+                    break;
+                }
+                self.bump(&mut kind);
+            }
+            self.expect(&mut kind, "}")?;
+        } else if self.continues() && self.peek().map(Token::text) == Some("Observable") {
+            self.bump(&mut kind);
+            kind.children.push(Element::Node(self.kind_primitive()?));
+        } else if self.continues() && self.peek().map_or(false, kind_start) {
+            self.bump(&mut kind);
+        } else {
+            return Err(self.error());
+        }
+
+        Ok(kind)
+    }
+
+    fn binding(&mut self) -> Result<'a, Node<'a>> {
+        let mut binding = Node::new(NodeKind::Binding);
+        self.expect_kind(&mut binding, TokenKind::Identifier)?;
+
+        while self.at_kind(TokenKind::Identifier) {
+            self.bump(&mut binding);
+        }
+
+        self.expect(&mut binding, "=")?;
+        binding.children.push(Element::Node(self.expression()?));
+
+        if self.at("where") {
+            let mut clause = Node::new(NodeKind::Where);
+            self.bump(&mut clause);
+            clause
+                .children
+                .push(Element::Node(self.block(Self::binding)?));
+            binding.children.push(Element::Node(clause));
+        }
+
+        Ok(binding)
+    }
+
+    // Bindings or alternatives, which are either aligned at the column of the first one or
+    // separated by ";".
+    fn block(&mut self, f: fn(&mut Self) -> Result<'a, Node<'a>>) -> Result<'a, Node<'a>> {
+        let mut block = Node::new(NodeKind::Block);
+        let column = match self.peek() {
+            Some(token) if self.continues() => token.span.get_column(),
+            _ => return Err(self.error()),
+        };
+
+        let layout = self.layout;
+        self.layout = column;
+        let result = self.items(&mut block, f);
+        self.layout = layout;
+
+        result.map(|_| block)
+    }
+
+    fn items(
+        &mut self,
+        block: &mut Node<'a>,
+        f: fn(&mut Self) -> Result<'a, Node<'a>>,
+    ) -> Result<'a, ()> {
+        self.start();
+        block.children.push(Element::Node(f(self)?));
+
+        loop {
+            if self.at(";") {
+                self.bump(block);
+            } else if !self.aligned()
+                || self.peek().map(|token| token.kind) == Some(TokenKind::Keyword)
+            {
+                // A keyword in line with the block ends it, as in the program parser.
+                return Ok(());
+            }
+
+            self.start();
+            block.children.push(Element::Node(f(self)?));
+        }
+    }
+
+    fn expression(&mut self) -> Result<'a, Node<'a>> {
+        if self.at("\\") {
+            let mut lambda = Node::new(NodeKind::Lambda);
+            self.bump(&mut lambda);
+            self.expect_kind(&mut lambda, TokenKind::Identifier)?;
+            while self.at_kind(TokenKind::Identifier) {
+                self.bump(&mut lambda);
+            }
+            self.expect(&mut lambda, "->")?;
+            lambda.children.push(Element::Node(self.expression()?));
+            Ok(lambda)
+        } else if self.at("let") {
+            let mut local = Node::new(NodeKind::Let);
+            self.bump(&mut local);
+            local
+                .children
+                .push(Element::Node(self.block(Self::binding)?));
+            self.expect(&mut local, "in")?;
+            local.children.push(Element::Node(self.expression()?));
+            Ok(local)
+        } else if self.at("if") {
+            let mut conditional = Node::new(NodeKind::Conditional);
+            self.bump(&mut conditional);
+            conditional.children.push(Element::Node(self.expression()?));
+            self.expect(&mut conditional, "then")?;
+            conditional.children.push(Element::Node(self.expression()?));
+            self.expect(&mut conditional, "else")?;
+            conditional.children.push(Element::Node(self.expression()?));
+            Ok(conditional)
+        } else if self.at("case") {
+            let mut case = Node::new(NodeKind::Case);
+            self.bump(&mut case);
+            case.children.push(Element::Node(self.expression()?));
+            self.expect(&mut case, "of")?;
+            case.children
+                .push(Element::Node(self.block(Self::alternative)?));
+            Ok(case)
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn alternative(&mut self) -> Result<'a, Node<'a>> {
+        let mut alternative = Node::new(NodeKind::Alternative);
+        let mut pattern = Node::new(NodeKind::Pattern);
+
+        if self.at("_") || self.at_kind(TokenKind::Literal) {
+            self.bump(&mut pattern);
+        } else if self.at_kind(TokenKind::Name) {
+            self.bump(&mut pattern);
+            while self.at_kind(TokenKind::Identifier) {
+                self.bump(&mut pattern);
+            }
+        } else if self.at("(") || self.at("{") {
+            let record = self.at("{");
+            self.bump(&mut pattern);
+            loop {
+                self.expect_kind(&mut pattern, TokenKind::Identifier)?;
+                if record {
+                    self.expect(&mut pattern, "=")?;
+                    self.expect_kind(&mut pattern, TokenKind::Identifier)?;
+                }
+                if !self.at(",") {
+                    break;
+                }
+                self.bump(&mut pattern);
+            }
+            self.expect(&mut pattern, if record { "}" } else { ")" })?;
+        } else {
+            return Err(self.error());
+        }
+
+        alternative.children.push(Element::Node(pattern));
+        self.expect(&mut alternative, "->")?;
+        alternative.children.push(Element::Node(self.expression()?));
+
+        Ok(alternative)
+    }
+
+    fn comparison(&mut self) -> Result<'a, Node<'a>> {
+        let left = self.sum()?;
+
+        match ["==", "/=", ">=", "<=", ">", "<"]
+            .iter()
+            .any(|op| self.at(op))
+        {
+            true => self.binary(left, Self::sum),
+            false => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<'a, Node<'a>> {
+        let mut left = self.product()?;
+
+        while self.at("+") || self.at("-") {
+            left = self.binary(left, Self::product)?;
+        }
+
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<'a, Node<'a>> {
+        let mut left = self.negation()?;
+
+        while self.at("*") || self.at("/") {
+            left = self.binary(left, Self::negation)?;
+        }
+
+        Ok(left)
+    }
+
+    fn binary(
+        &mut self,
+        left: Node<'a>,
+        right: fn(&mut Self) -> Result<'a, Node<'a>>,
+    ) -> Result<'a, Node<'a>> {
+        let mut binary = Node::new(NodeKind::Binary);
+        binary.children.push(Element::Node(left));
+        self.bump(&mut binary);
+        binary.children.push(Element::Node(right(self)?));
+
+        Ok(binary)
+    }
+
+    fn negation(&mut self) -> Result<'a, Node<'a>> {
+        if self.at("-") {
+            let mut negation = Node::new(NodeKind::Negation);
+            self.bump(&mut negation);
+            negation.children.push(Element::Node(self.negation()?));
+            return Ok(negation);
+        }
+
+        // Only a name which isn't projected can be applied.
+        let name = self.at_kind(TokenKind::Identifier) || self.at_kind(TokenKind::Name);
+        let projected = matches!(self.tokens.get(self.next() + 1), Some(token) if token.is("."));
+
+        if !name || projected {
+            return self.term();
+        }
+
+        let head = self.primary()?;
+        let mut arguments = Vec::new();
+
+        while self.continues() && self.peek().map_or(false, term_start) {
+            arguments.push(Element::Node(self.term()?));
+        }
+
+        if arguments.is_empty() {
+            return Ok(head);
+        }
+
+        let mut application = Node::new(NodeKind::Application);
+        application.children.push(Element::Node(head));
+        application.children.extend(arguments);
+
+        Ok(application)
+    }
+
+    fn term(&mut self) -> Result<'a, Node<'a>> {
+        let mut term = self.primary()?;
+
+        // Components are projected straight after the term, as in "pair.0".
+        while matches!(self.tokens.get(self.position), Some(token) if token.is(".")) {
+            let mut projection = Node::new(NodeKind::Projection);
+            projection.children.push(Element::Node(term));
+            self.bump(&mut projection);
+
+            match self.tokens.get(self.position) {
+                Some(token) if matches!(token.kind, TokenKind::Identifier | TokenKind::Literal) => {
+                    self.bump(&mut projection)
+                }
+                _ => return Err(self.error()),
+            }
+
+            term = projection;
+        }
+
+        Ok(term)
+    }
+
+    fn primary(&mut self) -> Result<'a, Node<'a>> {
+        if self.at("(") {
+            let mut node = Node::new(NodeKind::Parentheses);
+            self.bump(&mut node);
+            node.children.push(Element::Node(self.expression()?));
+            while self.at(",") {
+                node.kind = NodeKind::Tuple;
+                self.bump(&mut node);
+                node.children.push(Element::Node(self.expression()?));
+            }
+            self.expect(&mut node, ")")?;
+            Ok(node)
+        } else if self.at("{") {
+            let mut record = Node::new(NodeKind::Record);
+            self.bump(&mut record);
+            loop {
+                let mut field = Node::new(NodeKind::Field);
+                self.expect_kind(&mut field, TokenKind::Identifier)?;
+                self.expect(&mut field, "=")?;
+                field.children.push(Element::Node(self.expression()?));
+                record.children.push(Element::Node(field));
+                if !self.at(",") {
+                    break;
+                }
+                self.bump(&mut record);
+            }
+            self.expect(&mut record, "}")?;
+            Ok(record)
+        } else if self.at_kind(TokenKind::Identifier) || self.at_kind(TokenKind::Name) {
+            let mut name = Node::new(NodeKind::Name);
+            self.bump(&mut name);
+            Ok(name)
+        } else if self.at_kind(TokenKind::Literal) {
+            let mut literal = Node::new(NodeKind::Literal);
+            self.bump(&mut literal);
+            Ok(literal)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    // The index of the next token which isn't trivia, or the number of tokens if there isn't one.
+    fn next(&self) -> usize {
+        self.tokens[self.position..]
+            .iter()
+            .position(|token| !token.is_trivia())
+            .map_or(self.tokens.len(), |i| self.position + i)
+    }
+
+    // Items start a line of their own, so the first token of one continues it wherever it is.
+    fn start(&mut self) {
+        if let Some(token) = self.peek() {
+            self.line = token.span.line;
+        }
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.next())
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens[self.next()..]
+            .iter()
+            .filter(|token| !token.is_trivia())
+            .nth(n)
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek().map_or(false, |token| token.is(text))
+    }
+
+    // Whether the next token is part of the current construct, by being on the same line or
+    // indented past the current block.
+    fn continues(&self) -> bool {
+        self.peek().map_or(false, |token| {
+            token.span.line == self.line || token.span.get_column() > self.layout
+        })
+    }
+
+    // Whether the next token starts a new line at the column of the current block.
+    fn aligned(&self) -> bool {
+        self.peek().map_or(false, |token| {
+            token.span.line != self.line && token.span.get_column() == self.layout
+        })
+    }
+
+    fn at(&self, text: &str) -> bool {
+        self.continues() && self.peek_is(text)
+    }
+
+    fn at_kind(&self, kind: TokenKind) -> bool {
+        self.continues() && self.peek().map_or(false, |token| token.kind == kind)
+    }
+
+    fn trivia(&mut self, node: &mut Node<'a>) {
+        let next = self.next();
+        let trivia = self.tokens[self.position..next].iter();
+        node.children.extend(trivia.copied().map(Element::Token));
+        self.position = next;
+    }
+
+    // Moves the next token into the node, along with the trivia before it.
+    fn bump(&mut self, node: &mut Node<'a>) {
+        self.trivia(node);
+        let token = self.tokens[self.position];
+        self.line = token.span.line;
+        self.position += 1;
+        node.children.push(Element::Token(token));
+    }
+
+    fn expect(&mut self, node: &mut Node<'a>, text: &str) -> Result<'a, ()> {
+        match self.at(text) {
+            true => {
+                self.bump(node);
+                Ok(())
+            }
+            false => Err(self.error()),
+        }
+    }
+
+    fn expect_kind(&mut self, node: &mut Node<'a>, kind: TokenKind) -> Result<'a, ()> {
+        match self.at_kind(kind) {
+            true => {
+                self.bump(node);
+                Ok(())
+            }
+            false => Err(self.error()),
+        }
+    }
+
+    fn error(&self) -> Error<'a> {
+        let span = self.peek().map_or(self.end, |token| token.span);
+        Error::from_error_kind(span, ErrorKind::Tag)
+    }
+}
+
+fn kind_start(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Identifier | TokenKind::Name) || token.is("(") || token.is("{")
+}
+
+fn term_start(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Identifier | TokenKind::Name | TokenKind::Literal
+    ) || token.is("(")
+        || token.is("{")
+}
@@ -12,49 +12,88 @@ pub trait Unify<'a, O = Self> {
 impl<'a, T, U> Unify<'a, Context<'a, U>> for &mut Context<'a, T> {
     fn unify(self, other: Context<'a, U>) -> Result<(), Error<'a>> {
         for (name, definition) in &other.definitions {
-            if self.definitions.insert(name, definition.clone()).is_some() {
+            if let Some(original) = self.definitions.insert(name, definition.clone()) {
                 // There is a duplicate definition.
                 return Err(Error::from_sprint_error(
                     SprintError::DuplicateDefinitionError(name),
-                    definition.expression.span,
-                ));
+                    definition.variable.span,
+                )
+                .with_label(original.variable.span, "first defined here"));
             }
         }
 
-        for (variable, count) in other.variables {
-            if let Some(original) = self.variables.replace(variable.clone(), count) {
-                if let Err(e) = original.kind.unify(variable.kind.clone()) {
-                    let sprint_error =
-                        SprintError::TypeError(variable.name, e.sprint_error.unwrap().into());
-                    return Err(Error::from_sprint_error(sprint_error, variable.span));
+        for (name, signature) in other.signatures {
+            if let Some(original) = self.signatures.get(name) {
+                if let Err(e) = original.kind.clone().unify(signature.kind.clone()) {
+                    let sprint_error = SprintError::TypeError(name, e.sprint_error.unwrap().into());
+                    return Err(Error::from_sprint_error(sprint_error, signature.span)
+                        .with_label(original.span, "first signature given here"));
                 }
+            } else {
+                self.signatures.insert(name, signature);
             }
         }
 
+        // Uses of variables are only unified once it is known what they refer to.
+        self.variables.extend(other.variables);
+        self.arithmetic.extend(other.arithmetic);
+        self.matches.extend(other.matches);
+        self.accesses.extend(other.accesses);
+        self.instances.extend(other.instances);
+
         Ok(())
     }
 }
 
 impl<'a> Unify<'a> for Rc<Kind> {
     fn unify(self, other: Self) -> Result<(), Error<'a>> {
-        let mut this = Kind::simplify(self);
-        let mut other = Kind::simplify(other);
+        // Aliases are unified by the kinds which they stand for, but are kept when resolving an
+        // unresolved kind and in errors, so that their names are shown.
+        let mut this = Kind::resolve(self);
+        let mut other = Kind::resolve(other);
+        let (this_kind, other_kind) = (Kind::simplify(this.clone()), Kind::simplify(other.clone()));
 
-        match (this.as_ref(), other.as_ref()) {
+        match (this_kind.as_ref(), other_kind.as_ref()) {
             (Kind::Abstraction(this_from, this_to), Kind::Abstraction(other_from, other_to)) => {
                 this_from.clone().unify(other_from.clone())?;
                 this_to.clone().unify(other_to.clone())?;
             }
             (Kind::Boolean, Kind::Boolean) => {}
+            (Kind::Data(this_data), Kind::Data(other_data))
+                if this_data.name == other_data.name => {}
+            (Kind::Date, Kind::Date) => {}
+            (Kind::Decimal, Kind::Decimal) => {}
+            (Kind::Duration, Kind::Duration) => {}
+            (Kind::Int, Kind::Int) => {}
             (Kind::Observable(this_k), Kind::Observable(other_k)) => {
                 this_k.clone().unify(other_k.clone())?;
             }
+            (Kind::Record(this_fields), Kind::Record(other_fields))
+                if this_fields.len() == other_fields.len()
+                    && this_fields
+                        .iter()
+                        .zip(other_fields.iter())
+                        .all(|((this_name, _), (other_name, _))| this_name == other_name) =>
+            {
+                for ((_, this_k), (_, other_k)) in this_fields.iter().zip(other_fields.iter()) {
+                    this_k.clone().unify(other_k.clone())?;
+                }
+            }
             (Kind::State, Kind::State) => {}
-            (Kind::Unresolved(_), Kind::Unresolved(_)) if Rc::ptr_eq(&this, &other) => {}
-            (Kind::Unresolved(k), _) if !Kind::contains(other.clone(), this.clone()) => {
+            (Kind::Tuple(this_kinds), Kind::Tuple(other_kinds))
+                if this_kinds.len() == other_kinds.len() =>
+            {
+                for (this_k, other_k) in this_kinds.iter().zip(other_kinds.iter()) {
+                    this_k.clone().unify(other_k.clone())?;
+                }
+            }
+            (Kind::Unresolved(_), Kind::Unresolved(_)) if Rc::ptr_eq(&this_kind, &other_kind) => {}
+            (Kind::Unresolved(k), _) if !Kind::contains(other.clone(), this_kind.clone()) => {
                 *k.borrow_mut() = Some(other);
             }
-            (_, Kind::Unresolved(_)) => other.unify(this)?,
+            (_, Kind::Unresolved(k)) if !Kind::contains(this.clone(), other_kind.clone()) => {
+                *k.borrow_mut() = Some(this);
+            }
             (Kind::Word, Kind::Word) => {}
             _ => {
                 return Err(Error::from_sprint_error(
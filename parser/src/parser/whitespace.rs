@@ -0,0 +1,246 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{self, anychar, line_ending, not_line_ending},
+    combinator::{not, recognize, verify},
+    error::ParseError,
+    multi::{many0_count, many1_count},
+    sequence::{delimited, pair},
+    AsChar, Compare, IResult, InputIter, InputLength, InputTake, InputTakeAtPosition, Offset,
+    Slice,
+};
+use std::ops::{Range, RangeFrom, RangeTo};
+
+// Input types which comments can be parsed from, i.e. both &str and Span.
+pub trait Input:
+    Clone
+    + PartialEq
+    + Offset
+    + InputIter
+    + InputLength
+    + InputTake
+    + InputTakeAtPosition
+    + Compare<&'static str>
+    + Slice<Range<usize>>
+    + Slice<RangeFrom<usize>>
+    + Slice<RangeTo<usize>>
+{
+}
+
+impl<I> Input for I
+where
+    I: Clone
+        + PartialEq
+        + Offset
+        + InputIter
+        + InputLength
+        + InputTake
+        + InputTakeAtPosition
+        + Compare<&'static str>
+        + Slice<Range<usize>>
+        + Slice<RangeFrom<usize>>
+        + Slice<RangeTo<usize>>,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+{
+}
+
+/// Spaces, tabs, comments and line endings followed by indentation, which continue the
+/// current definition onto the next line. `layout` is the column at which lines of the innermost
+/// block start, which is 1 for top-level definitions.
+pub fn space0<I, E>(layout: usize) -> impl Fn(I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    recognize(many0_count(alt((
+        complete::space1,
+        comment,
+        continuation(layout),
+    ))))
+}
+
+pub fn space1<I, E>(layout: usize) -> impl Fn(I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    recognize(many1_count(alt((
+        complete::space1,
+        comment,
+        continuation(layout),
+    ))))
+}
+
+/// Any whitespace, including line endings, and comments.
+pub fn multispace0<I, E>(input: I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    recognize(many0_count(alt((complete::multispace1, comment))))(input)
+}
+
+pub fn multispace1<I, E>(input: I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    recognize(many1_count(alt((complete::multispace1, comment))))(input)
+}
+
+/// Line endings followed by a line which starts a new binding in the block whose lines start at
+/// `layout`, as in "let" and "where".
+pub fn aligned<I, E>(layout: usize) -> impl Fn(I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    indented(layout, |column, layout| column == layout)
+}
+
+// Any line which is indented further than the current block belongs to the definition before
+// it.
+fn continuation<I, E>(layout: usize) -> impl Fn(I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    indented(layout, |column, layout| column > layout)
+}
+
+// Blank lines and lines with only comments are skipped over.
+fn indented<I, E>(
+    layout: usize,
+    predicate: impl Fn(usize, usize) -> bool,
+) -> impl Fn(I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<I>,
+{
+    move |input: I| {
+        let inline = many0_count(alt((complete::space1, comment)));
+        let lines = many1_count(pair(inline, line_ending));
+        let predicate = &predicate;
+        let indentation = verify(complete::space0, move |indentation: &I| {
+            predicate(indentation.input_len() + 1, layout)
+        });
+
+        recognize(pair(lines, indentation))(input)
+    }
+}
+
+/// A "--" line comment, up to but excluding the line ending, or a "{- -}" block comment.
+pub fn comment<I, E>(input: I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    E: ParseError<I>,
+{
+    alt((line_comment, block_comment))(input)
+}
+
+fn line_comment<I, E>(input: I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    E: ParseError<I>,
+{
+    recognize(pair(tag("--"), not_line_ending))(input)
+}
+
+fn block_comment<I, E>(input: I) -> IResult<I, I, E>
+where
+    I: Input,
+    <I as InputIter>::Item: AsChar,
+    E: ParseError<I>,
+{
+    // Block comments nest, so "{- {- -} -}" is a single comment.
+    let character = recognize(pair(not(alt((tag("{-"), tag("-}")))), anychar));
+    let contents = many0_count(alt((block_comment, character)));
+
+    recognize(delimited(tag("{-"), contents, tag("-}")))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Span;
+    use super::*;
+    use nom::error::ErrorKind;
+
+    fn parse<'a>(
+        parser: impl Fn(&'a str) -> IResult<&'a str, &'a str, (&'a str, ErrorKind)>,
+        input: &'a str,
+    ) -> Option<(&'a str, &'a str)> {
+        parser(input).ok()
+    }
+
+    #[test]
+    fn parse_comment() {
+        assert_eq!(parse(comment, "-- abc\ndef"), Some(("\ndef", "-- abc")));
+        assert_eq!(parse(comment, "--"), Some(("", "--")));
+        assert_eq!(parse(comment, "{- abc -}def"), Some(("def", "{- abc -}")));
+        assert_eq!(
+            parse(comment, "{- a {- b -} c -} d"),
+            Some((" d", "{- a {- b -} c -}"))
+        );
+        assert_eq!(parse(comment, "{- a\nb -}"), Some(("", "{- a\nb -}")));
+        assert_eq!(parse(comment, "{- a {- b -}"), None);
+        assert_eq!(parse(comment, "- a"), None);
+    }
+
+    #[test]
+    fn parse_space() {
+        assert_eq!(parse(space0(1), "abc"), Some(("abc", "")));
+        assert_eq!(parse(space0(1), " {- a -} b"), Some(("b", " {- a -} ")));
+        assert_eq!(parse(space0(1), " -- a\nb"), Some(("\nb", " -- a")));
+        assert_eq!(parse(space0(1), " -- a\n b"), Some(("b", " -- a\n ")));
+        assert_eq!(
+            parse(space0(1), "\n\n-- a\n\t b"),
+            Some(("b", "\n\n-- a\n\t "))
+        );
+        assert_eq!(parse(space0(1), "\n \nb"), Some(("\n \nb", "")));
+        assert_eq!(parse(space1(1), "abc"), None);
+        assert_eq!(
+            parse(multispace1, " -- a\n {- b -}\nc"),
+            Some(("c", " -- a\n {- b -}\n"))
+        );
+    }
+
+    #[test]
+    fn parse_block() {
+        assert_eq!(parse(space0(3), "\n  b"), Some(("\n  b", "")));
+        assert_eq!(parse(space0(3), "\n    b"), Some(("b", "\n    ")));
+        assert_eq!(
+            parse(aligned(3), "\n  -- a\n  b"),
+            Some(("b", "\n  -- a\n  "))
+        );
+        assert_eq!(parse(aligned(3), "\n   b"), None);
+        assert_eq!(parse(aligned(1), "\n\nb"), Some(("b", "\n\n")));
+        assert_eq!(parse(space0(1), "\n b"), Some(("b", "\n ")));
+    }
+
+    #[test]
+    fn parse_span_position() {
+        let (input, _) =
+            multispace0::<_, (_, ErrorKind)>(Span::new("{- a\n-}\n-- b\n\n c")).unwrap();
+
+        assert_eq!(input.fragment, "c");
+        assert_eq!(input.line, 5);
+        assert_eq!(input.get_column(), 2);
+    }
+}
@@ -1,14 +1,21 @@
-use sprintc::compile;
-use structopt::StructOpt;
+use sprint_parser::parser::lint::{Level, Lint, Lints};
+use sprintc::{compile, format};
+use structopt::{
+    clap::{self, ErrorKind},
+    StructOpt,
+};
 
 use std::{error::Error, path::PathBuf};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "Sprint Compiler", about = "Compiler for Sprint to Move IR")]
 pub struct Args {
-    /// File to be compiled
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+
+    /// File to be compiled, which is required unless a subcommand is given
     #[structopt(parse(from_os_str))]
-    pub source_path: PathBuf,
+    pub source_path: Option<PathBuf>,
 
     /// Optional path to output file
     #[structopt(parse(from_os_str))]
@@ -21,16 +28,83 @@ pub struct Args {
     /// Checks program without code generation
     #[structopt(short, long)]
     pub check: bool,
+
+    /// Ignores a lint, such as unused-definition
+    #[structopt(short = "A", long)]
+    pub allow: Vec<Lint>,
+
+    /// Reports a lint as a warning, which is the default
+    #[structopt(short = "W", long)]
+    pub warn: Vec<Lint>,
+
+    /// Reports a lint as an error
+    #[structopt(short = "D", long)]
+    pub deny: Vec<Lint>,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Formats Sprint programs in place
+    #[structopt(name = "fmt")]
+    Fmt {
+        /// Files to be formatted
+        #[structopt(parse(from_os_str), required = true)]
+        source_paths: Vec<PathBuf>,
+
+        /// Checks that files are formatted without changing them
+        #[structopt(long)]
+        check: bool,
+    },
 }
 
 pub fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::from_args();
 
+    // Formatting is a subcommand, so that compiling a file doesn't need one.
+    if let Some(Command::Fmt {
+        source_paths,
+        check,
+    }) = args.command
+    {
+        let args = sprintc::FormatArgs {
+            source_paths,
+            check,
+        };
+
+        for path in format(&args)? {
+            println!("Formatted {}", path.display());
+        }
+
+        return Ok(());
+    }
+
+    let source_path = match args.source_path {
+        Some(source_path) => source_path,
+        None => clap::Error::with_description(
+            "The following required arguments were not provided:\n    <source_path>",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
+    };
+
+    let mut lints = Lints::default();
+
+    for (lints_at, level) in &[
+        (&args.allow, Level::Allow),
+        (&args.warn, Level::Warn),
+        (&args.deny, Level::Deny),
+    ] {
+        for &lint in lints_at.iter() {
+            lints.set(lint, *level);
+        }
+    }
+
     let args = sprintc::CompileArgs {
-        source_path: args.source_path,
+        source_path,
         output_path: args.output_path,
         verbose: args.verbose,
         check: args.check,
+        lints,
     };
 
     match compile(&args) {
@@ -1,11 +1,12 @@
 use sprint_move::generate;
-use sprint_parser::parser;
+use sprint_parser::parser::{self, lint::Lints};
 use std::{
     borrow::Cow,
     error::Error,
     ffi::OsStr,
     fs::File,
     io::{BufReader, Read, Write},
+    iter,
     path::{Path, PathBuf},
 };
 
@@ -21,21 +22,50 @@ pub struct CompileArgs {
     pub verbose: bool,
     /// Checks program without code generation
     pub check: bool,
+    /// Levels of the lints which are reported
+    pub lints: Lints,
 }
 
 pub fn compile<'a>(args: &'a CompileArgs) -> Result<Cow<'a, Path>, Box<dyn Error>> {
     let (source_path, output_path) = check_args(args)?;
 
     let source = read_source(source_path)?;
+    let modules = read_modules(source_path, &source)?;
+    let sources = modules
+        .iter()
+        .map(|(name, _, source)| (name.as_str(), source.as_str()))
+        .collect();
 
-    let ast = parser::contract(&source).map_err(|err| {
-        eprint!("{}", err.pretty(&source));
-        format!("Unable to parse file `{}`", source_path.display())
+    let names: Vec<_> = iter::once(source_path)
+        .chain(modules.iter().map(|(_, path, _)| path.as_path()))
+        .map(|path| path.display().to_string())
+        .collect();
+    let files: Vec<_> = names
+        .iter()
+        .map(String::as_str)
+        .zip(iter::once(&source).chain(modules.iter().map(|(_, _, source)| source)))
+        .map(|(name, source)| (name, source.as_str()))
+        .collect();
+
+    let (ast, warnings) = parser::linted(&source, &sources, &args.lints).map_err(|errors| {
+        for error in &errors {
+            eprintln!("{}", error.pretty(&files));
+        }
+
+        format!(
+            "Unable to parse file `{}` due to {} error(s)",
+            source_path.display(),
+            errors.len()
+        )
     })?;
 
+    for warning in &warnings {
+        eprintln!("{}", warning.pretty(&files));
+    }
+
     if args.verbose {
         for definition in &ast {
-            let name = definition.variable.name;
+            let name = &definition.variable.name;
             println!("{} :: {}", name, definition.variable.kind);
             println!("{} = {:#?}", name, definition.expression);
         }
@@ -49,6 +79,48 @@ pub fn compile<'a>(args: &'a CompileArgs) -> Result<Cow<'a, Path>, Box<dyn Error
     Ok(output_path)
 }
 
+pub struct FormatArgs {
+    /// Files to be formatted
+    pub source_paths: Vec<PathBuf>,
+    /// Checks that files are formatted without changing them
+    pub check: bool,
+}
+
+/// Formats the sources in place, returning the paths of those which were changed. When checking,
+/// nothing is written and it is an error for any source to need formatting.
+pub fn format(args: &FormatArgs) -> Result<Vec<&Path>, Box<dyn Error>> {
+    let mut changed = Vec::new();
+
+    for path in &args.source_paths {
+        let source = read_source(path)?;
+        let formatted = parser::format(&source).map_err(|error| {
+            let name = path.display().to_string();
+            eprintln!("{}", error.pretty(&[(&name, &source)]));
+
+            format!("Unable to format file `{}`", path.display())
+        })?;
+
+        if formatted == source {
+            continue;
+        }
+
+        if !args.check {
+            write_output(path, formatted.as_bytes())?;
+        }
+        changed.push(path.as_path());
+    }
+
+    if args.check && !changed.is_empty() {
+        for path in &changed {
+            eprintln!("File `{}` is not formatted", path.display());
+        }
+
+        return Err(format!("{} file(s) need formatting", changed.len()).into());
+    }
+
+    Ok(changed)
+}
+
 // Checks for presence of output path and that file extensions are valid.
 fn check_args(args: &CompileArgs) -> Result<(&Path, Cow<Path>), String> {
     let source = &args.source_path;
@@ -117,6 +189,34 @@ fn read_source(path: &Path) -> Result<String, String> {
     Ok(source)
 }
 
+// Reads the modules imported by the source, and the modules which they import in turn, from the
+// directory of the source. Modules which can't be found are reported by the parser.
+fn read_modules(
+    source_path: &Path,
+    source: &str,
+) -> Result<Vec<(String, PathBuf, String)>, String> {
+    let directory = source_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut modules: Vec<(String, PathBuf, String)> = Vec::new();
+    let mut imports: Vec<String> = parser::imports(source)
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    while let Some(name) = imports.pop() {
+        let path = directory.join(format!("{}.{}", name, SPRINT_EXTENSION));
+
+        if modules.iter().any(|(n, ..)| *n == name) || !path.is_file() {
+            continue;
+        }
+
+        let source = read_source(&path)?;
+        imports.extend(parser::imports(&source).into_iter().map(String::from));
+        modules.push((name, path, source));
+    }
+
+    Ok(modules)
+}
+
 fn write_output(path: &Path, buf: &[u8]) -> Result<(), String> {
     let mut move_file = File::create(path)
         .map_err(|err| format!("Unable to create file `{}`: {}", path.display(), err))?;
@@ -139,6 +239,7 @@ mod tests {
             output_path: None,
             verbose: false,
             check: false,
+            lints: Default::default(),
         };
 
         assert_eq!(
@@ -154,6 +255,7 @@ mod tests {
             output_path: Some(PathBuf::from("output.mvir")),
             verbose: false,
             check: false,
+            lints: Default::default(),
         };
 
         assert_eq!(